@@ -0,0 +1,53 @@
+use std::hint::black_box;
+
+use async_impl::{Mempool, worker};
+use bytes::Bytes;
+use criterion::{Criterion, criterion_group, criterion_main};
+use mempool::Transaction;
+
+fn cfg() -> worker::Cfg {
+    worker::Cfg {
+        capacity: 50_000,
+        submittance_back_pressure: 1_000,
+        max_drain_batch: 10_000,
+        drain_request_buffer: 10,
+        drain_retry_delay: worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+        max_drain_retry_delay: worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+        idle_shutdown: None,
+        backpressure: worker::BackpressureStrategy::Block,
+    }
+}
+
+fn encoded_tx() -> Bytes {
+    let tx = Transaction::new("tx", 100, 0, vec![0; 512]);
+    Bytes::from(serde_json::to_vec(&tx).unwrap())
+}
+
+/// Compares deserializing a transaction before submitting it with deferring the deserialization to the worker.
+fn submit_encoded(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let queue = rt.block_on(async { worker::Queue::start(cfg()) });
+    let bytes = encoded_tx();
+
+    c.bench_function("async_worker submit_deserialized", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let tx: Transaction = serde_json::from_slice(black_box(&bytes)).unwrap();
+                queue.submit(tx).await.unwrap();
+                queue.drain(1, 0).await.unwrap();
+            })
+        })
+    });
+
+    c.bench_function("async_worker submit_raw", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                queue.submit_raw(black_box(bytes.clone())).await.unwrap();
+                queue.drain(1, 0).await.unwrap();
+            })
+        })
+    });
+}
+
+criterion_group!(benches, submit_encoded);
+criterion_main!(benches);
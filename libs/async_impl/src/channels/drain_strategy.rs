@@ -5,6 +5,17 @@ use tokio::{sync, time::Instant};
 
 pub type SendBack = sync::oneshot::Sender<Vec<Transaction>>;
 pub type ReceiveDrainage = sync::oneshot::Receiver<Vec<Transaction>>;
+pub type ReceiveTiming = sync::oneshot::Receiver<DrainTiming>;
+
+/// Breakdown of the time a drain request spent in the queue, see [`DrainRequest::with_timing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrainTiming {
+    /// Time from the creation of the request until the queue started to pop transactions, which includes the
+    /// wait for enough transactions of a [`DrainStrategy::WaitForN`] request.
+    pub wait: Duration,
+    /// Time the queue spent popping the drained transactions.
+    pub pop: Duration,
+}
 
 /// Strategy to employ when draining items.
 #[derive(Debug, Clone, Copy)]
@@ -16,6 +27,12 @@ pub enum DrainStrategy {
     /// If the internal timer reaches the specified [`Instant`], the drain strategy will be converted
     /// into `DrainMax` (e.g. at most n items will be returned).
     WaitForN(Instant),
+    /// Try to drain transactions until their summed payload size reaches `target_bytes`.
+    /// If the internal timer reaches `timeout`, all available transactions up to the byte target are returned.
+    WaitForBytes {
+        target_bytes: usize,
+        timeout: Instant,
+    },
 }
 
 #[derive(Debug)]
@@ -23,6 +40,10 @@ pub struct DrainRequest {
     pub n: usize,
     pub wait_strategy: DrainStrategy,
     pub send_back: SendBack,
+    /// Number of times the request has been re-queued while waiting, used for the retry backoff.
+    pub(crate) retries: u32,
+    pub(crate) created: Instant,
+    pub(crate) timing: Option<sync::oneshot::Sender<DrainTiming>>,
 }
 
 impl DrainStrategy {
@@ -37,18 +58,61 @@ impl DrainStrategy {
     pub fn new_timeout(timeout_us: u64) -> Self {
         Self::WaitForN(Instant::now() + Duration::from_micros(timeout_us))
     }
+
+    /// Creates a new [`DrainStrategy`] that tries to drain transactions worth `target_bytes` of payload
+    /// for `timeout_us`.
+    pub fn new_byte_target(target_bytes: usize, timeout_us: u64) -> Self {
+        Self::WaitForBytes {
+            target_bytes,
+            timeout: Instant::now() + Duration::from_micros(timeout_us),
+        }
+    }
 }
 
 impl DrainRequest {
+    /// Creates a request that waits up to `timeout_us` for `n` transactions.
+    /// A `timeout_us` of `0` means "no wait" and results in a [`DrainStrategy::DrainMax`] request.
     pub fn new_with_timeout(n: usize, timeout_us: u64) -> (Self, ReceiveDrainage) {
         let (send_back, rx) = sync::oneshot::channel();
+        let wait_strategy = match timeout_us {
+            0 => DrainStrategy::new_standard(),
+            t => DrainStrategy::new_timeout(t),
+        };
         (
             Self {
                 n,
-                wait_strategy: DrainStrategy::new_timeout(timeout_us),
+                wait_strategy,
                 send_back,
+                retries: 0,
+                created: Instant::now(),
+                timing: None,
             },
             rx,
         )
     }
+
+    /// Creates a request that drains transactions until their payloads add up to at least `target_bytes`.
+    /// The request does not limit the number of transactions itself, but the queue still caps it at its
+    /// maximum drain batch.
+    pub fn new_with_byte_target(target_bytes: usize, timeout_us: u64) -> (Self, ReceiveDrainage) {
+        let (send_back, rx) = sync::oneshot::channel();
+        (
+            Self {
+                n: usize::MAX,
+                wait_strategy: DrainStrategy::new_byte_target(target_bytes, timeout_us),
+                send_back,
+                retries: 0,
+                created: Instant::now(),
+                timing: None,
+            },
+            rx,
+        )
+    }
+
+    /// Lets the queue report the [`DrainTiming`] of the request along with the drained transactions.
+    pub fn with_timing(mut self) -> (Self, ReceiveTiming) {
+        let (timing, rx) = sync::oneshot::channel();
+        self.timing = Some(timing);
+        (self, rx)
+    }
 }
@@ -2,5 +2,6 @@
 //! instead of lock-coordinated direct memory access.
 
 pub mod drain_strategy;
+mod rate_tracker;
 pub mod stress;
 pub mod worker;
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Number of one-second slots kept by the [`RateTracker`].
+const WINDOW_SECS: u64 = 60;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Slot {
+    /// Second since the start of the tracker that the counts belong to.
+    second: u64,
+    submits: u64,
+    drains: u64,
+}
+
+/// Ring buffer of per-second submit and drain counts over the last [`WINDOW_SECS`] seconds.
+/// Slots of seconds that fell out of the window are reset lazily when they are reused.
+#[derive(Debug)]
+pub(crate) struct RateTracker {
+    start: Instant,
+    slots: [Slot; WINDOW_SECS as usize],
+}
+
+impl RateTracker {
+    pub(crate) fn new(start: Instant) -> Self {
+        Self {
+            start,
+            slots: [Slot::default(); WINDOW_SECS as usize],
+        }
+    }
+
+    pub(crate) fn record_submits(&mut self, now: Instant, n: u64) {
+        self.slot_mut(now).submits += n;
+    }
+
+    pub(crate) fn record_drains(&mut self, now: Instant, n: u64) {
+        self.slot_mut(now).drains += n;
+    }
+
+    /// Returns the submitted and drained transactions per second, averaged over the window up to `now`.
+    /// While the tracker is younger than the window, the average covers its lifetime only.
+    pub(crate) fn rates(&self, now: Instant) -> (f64, f64) {
+        let current = self.second(now);
+        let oldest = current.saturating_sub(WINDOW_SECS - 1);
+        let span = now
+            .saturating_duration_since(self.start + Duration::from_secs(oldest))
+            .as_secs_f64();
+        if span == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let (submits, drains) = self
+            .slots
+            .iter()
+            .filter(|slot| (oldest..=current).contains(&slot.second))
+            .fold((0, 0), |(submits, drains), slot| {
+                (submits + slot.submits, drains + slot.drains)
+            });
+        (submits as f64 / span, drains as f64 / span)
+    }
+
+    fn second(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_secs()
+    }
+
+    fn slot_mut(&mut self, now: Instant) -> &mut Slot {
+        let second = self.second(now);
+        let slot = &mut self.slots[(second % WINDOW_SECS) as usize];
+        if slot.second != second {
+            *slot = Slot {
+                second,
+                ..Slot::default()
+            };
+        }
+        slot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_match_known_cadence() {
+        let start = Instant::now();
+        let mut tracker = RateTracker::new(start);
+
+        // -- 10 submits and 5 drained transactions per second for 5 seconds
+        for i in 0..50 {
+            let now = start + Duration::from_millis(i * 100);
+            tracker.record_submits(now, 1);
+            if i % 2 == 0 {
+                tracker.record_drains(now, 1);
+            }
+        }
+
+        let (submit_rate, drain_rate) = tracker.rates(start + Duration::from_secs(5));
+        assert!((submit_rate - 10.0).abs() < 0.5, "{submit_rate}");
+        assert!((drain_rate - 5.0).abs() < 0.5, "{drain_rate}");
+    }
+
+    #[test]
+    fn counts_leave_the_window() {
+        let start = Instant::now();
+        let mut tracker = RateTracker::new(start);
+        tracker.record_submits(start, 100);
+
+        assert_eq!(
+            tracker.rates(start + Duration::from_secs(WINDOW_SECS * 2)),
+            (0.0, 0.0)
+        );
+
+        // -- a reused slot only holds the counts of its current second
+        let now = start + Duration::from_secs(WINDOW_SECS * 2);
+        tracker.record_submits(now, WINDOW_SECS - 1);
+        let (submit_rate, _) = tracker.rates(now + Duration::from_secs(WINDOW_SECS - 1));
+        assert!((submit_rate - 1.0).abs() < 0.01, "{submit_rate}");
+    }
+}
@@ -1,6 +1,7 @@
+use anyhow::Context;
 use hdrhistogram::Histogram;
 use mempool::Transaction;
-use rand::Rng;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use reqwest::Client;
 use std::{
     sync::{
@@ -15,7 +16,7 @@ use tokio::{
     time,
 };
 
-use crate::Mempool;
+use crate::{Mempool, drain_strategy::DrainTiming};
 
 #[derive(Debug, Clone)]
 pub struct StressTestCfg {
@@ -36,8 +37,44 @@ pub struct StressTestCfg {
     pub print_stats_interval_ms: u64,
     /// Percentiles to track (e.g. [50.0, 90.0, 99.0, 99.9])
     pub latency_percentiles: Vec<f64>,
+    /// Significant figures of the latency histogram, within `0..=5`. More figures cost more memory.
+    pub histogram_sigfigs: u8,
 
     pub http_port: Option<u16>,
+    /// Let each producer generate one buffer of random bytes up front and slice its payloads from it
+    /// instead of generating every payload byte by byte.
+    pub reuse_payloads: bool,
+    /// End the test before `run_duration_seconds` have passed as soon as all producers have submitted
+    /// their `num_transactions` and all of them have been drained.
+    pub stop_when_drained: bool,
+    /// Consumers skip their drain while the queue holds fewer transactions, so batches are not skewed by
+    /// near-empty drains. Once all producers are done, the remaining transactions are drained regardless.
+    /// Has no effect on pools that cannot report their length.
+    pub min_drain_batch: usize,
+    /// Upper bound of `num_producers`, larger configurations are rejected instead of spawning an unreasonable
+    /// number of tasks.
+    pub max_producers: usize,
+    /// Seed of the transaction generators. A random seed is picked if `None`, it is reported in the
+    /// [`StressTestSummary`] to repeat the run.
+    pub seed: Option<u64>,
+}
+
+impl StressTestCfg {
+    /// Default of `max_producers`.
+    pub const DEFAULT_MAX_PRODUCERS: usize = 10_000;
+
+    /// # Error
+    /// Returns an error if `num_producers` exceeds `max_producers`.
+    pub fn check_producers(&self) -> anyhow::Result<()> {
+        if self.num_producers > self.max_producers {
+            anyhow::bail!(
+                "{} producers exceed the maximum of {} producer tasks",
+                self.num_producers,
+                self.max_producers
+            );
+        }
+        Ok(())
+    }
 }
 
 struct TestStats {
@@ -45,22 +82,87 @@ struct TestStats {
     drained_txs: AtomicU64,
     submit_errors: AtomicU64,
     drain_errors: AtomicU64,
+    /// One set of histograms per consumer, merged only when the stats are read. A consumer only ever
+    /// locks its own set, so drains of different consumers do not serialize on a shared lock.
+    consumer_hists: Vec<std::sync::Mutex<ConsumerHistograms>>,
+    /// Number of latencies above the histogram's maximum, which are recorded as the maximum.
+    latency_overflows: AtomicU64,
+}
+
+/// Histograms recorded by a single consumer.
+struct ConsumerHistograms {
     // Store latencies in a histogram for percentile calculation
-    latency_hist: Mutex<Histogram<u64>>,
+    latency: Histogram<u64>,
+    /// Time drain requests waited for transactions, for pools that report a [`DrainTiming`].
+    drain_wait: Histogram<u64>,
+    /// Time pools spent popping the drained transactions, for pools that report a [`DrainTiming`].
+    drain_pop: Histogram<u64>,
 }
 
-impl TestStats {
-    fn new() -> Self {
+impl ConsumerHistograms {
+    /// Empty histograms with the same bounds and precision as `self`.
+    fn empty_like(&self) -> Self {
         Self {
+            latency: Histogram::new_from(&self.latency),
+            drain_wait: Histogram::new_from(&self.drain_wait),
+            drain_pop: Histogram::new_from(&self.drain_pop),
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        for (hist, other) in [
+            (&mut self.latency, &other.latency),
+            (&mut self.drain_wait, &other.drain_wait),
+            (&mut self.drain_pop, &other.drain_pop),
+        ] {
+            hist.add(other)
+                .expect("histograms share their bounds, so values cannot exceed them");
+        }
+    }
+}
+
+impl TestStats {
+    /// Creates the stats for a test with `num_consumers` consumers, each recording into its own histograms.
+    ///
+    /// # Error
+    /// Returns an error if `histogram_sigfigs` is outside of `0..=5`.
+    fn new(histogram_sigfigs: u8, num_consumers: usize) -> anyhow::Result<Self> {
+        let histogram = || {
+            Histogram::new_with_max(60_000_000, histogram_sigfigs)
+                .context("could not initialize latency histogram")
+        };
+        let consumer_hists = (0..num_consumers.max(1))
+            .map(|_| {
+                Ok(std::sync::Mutex::new(ConsumerHistograms {
+                    latency: histogram()?,
+                    drain_wait: histogram()?,
+                    drain_pop: histogram()?,
+                }))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
             submitted_txs: AtomicU64::new(0),
             drained_txs: AtomicU64::new(0),
             submit_errors: AtomicU64::new(0),
             drain_errors: AtomicU64::new(0),
-            latency_hist: Mutex::new(
-                Histogram::new_with_max(60_000_000, 3)
-                    .expect("Initializing the histogram should work"),
-            ),
+            consumer_hists,
+            latency_overflows: AtomicU64::new(0),
+        })
+    }
+
+    fn consumer_hists(&self, consumer: usize) -> std::sync::MutexGuard<'_, ConsumerHistograms> {
+        self.consumer_hists[consumer]
+            .lock()
+            .expect("recording cannot panic while holding the lock")
+    }
+
+    /// Merges the histograms of all consumers.
+    fn merged_hists(&self) -> ConsumerHistograms {
+        let mut merged = self.consumer_hists(0).empty_like();
+        for consumer in 0..self.consumer_hists.len() {
+            merged.add(&self.consumer_hists(consumer));
         }
+        merged
     }
 
     fn record_submission_success(&self) {
@@ -79,16 +181,54 @@ impl TestStats {
         self.drain_errors.fetch_add(1, Ordering::Relaxed);
     }
 
-    async fn record_latency(&self, latency_us: u64) {
+    /// Resolves once `expected` transactions have been submitted and all of them have been drained.
+    async fn drained_all(&self, expected: u64) {
+        const POLL_INTERVAL: Duration = Duration::from_millis(1);
+        loop {
+            let submitted = self.submitted_txs.load(Ordering::Relaxed);
+            if submitted >= expected && self.drained_txs.load(Ordering::Relaxed) >= submitted {
+                return;
+            }
+            time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Records a latency observed by `consumer`. Latencies above the histogram's maximum are clamped to it
+    /// and counted as overflows.
+    fn record_latency(&self, consumer: usize, latency: Duration) {
+        let latency_us = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
         // Add to histogram for percentile calculation
-        let mut hist = self.latency_hist.lock().await;
+        let hist = &mut self.consumer_hists(consumer).latency;
+        if latency_us > hist.high() {
+            self.latency_overflows.fetch_add(1, Ordering::Relaxed);
+        }
         let lat = latency_us.min(hist.high());
         hist.record(lat).expect("cannot exceed max");
     }
 
+    /// Records the wait and pop time of a drain by `consumer`. Times above the histograms' maximum are
+    /// clamped to it.
+    fn record_drain_timing(&self, consumer: usize, timing: DrainTiming) {
+        let mut hists = self.consumer_hists(consumer);
+        let hists = &mut *hists;
+        for (hist, time) in [
+            (&mut hists.drain_wait, timing.wait),
+            (&mut hists.drain_pop, timing.pop),
+        ] {
+            let time_us = u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+            let clamped = time_us.min(hist.high());
+            hist.record(clamped).expect("cannot exceed max");
+        }
+    }
+
+    /// Approximate number of queued transactions. The counters are read one after another, so drains of
+    /// transactions submitted in between can make `drained` exceed `submitted`, which yields `0`.
+    fn queue_size(submitted: u64, drained: u64) -> u64 {
+        submitted.saturating_sub(drained)
+    }
+
     // Calculate the specified percentile from the histogram
-    async fn calculate_percentile(&self, percentile: f64) -> Option<u64> {
-        let hist = self.latency_hist.lock().await;
+    fn calculate_percentile(hist: &Histogram<u64>, percentile: f64) -> Option<u64> {
         if hist.is_empty() {
             return None;
         }
@@ -107,15 +247,17 @@ impl TestStats {
         let submit_rate = submitted as f64 / elapsed_seconds;
         let drain_rate = drained as f64 / elapsed_seconds;
 
-        let (avg_latency, max_latency) = {
-            let hist = self.latency_hist.lock().await;
-            (hist.mean(), hist.max())
+        let hists = self.merged_hists();
+        let (avg_latency, max_latency, high) = {
+            let hist = &hists.latency;
+            (hist.mean(), hist.max(), hist.high())
         };
+        let overflows = self.latency_overflows.load(Ordering::Relaxed);
 
         println!("--- MEMPOOL STATS [{:.2}s] ---", elapsed_seconds);
         println!("Submitted: {} txs ({:.2} txs/sec)", submitted, submit_rate);
         println!("Drained:   {} txs ({:.2} txs/sec)", drained, drain_rate);
-        println!("Queue size: ~{} txs", submitted - drained);
+        println!("Queue size: ~{} txs", Self::queue_size(submitted, drained));
         println!("Errors: {} submit, {} drain", sub_errors, drain_errors);
 
         println!(
@@ -123,21 +265,47 @@ impl TestStats {
             ((avg_latency * 10.0) as u64 / 10).to_formatted_string(&locale),
             max_latency.to_formatted_string(&locale)
         );
+        if overflows > 0 {
+            println!(
+                "Latency overflows: {} (recorded as {} μs)",
+                overflows.to_formatted_string(&locale),
+                high.to_formatted_string(&locale)
+            );
+        }
 
         // Print percentiles
         print!("Percentiles: ");
         for &p in percentiles {
-            if let Some(latency) = self.calculate_percentile(p).await {
+            if let Some(latency) = Self::calculate_percentile(&hists.latency, p) {
                 print!("P{:.1}: {} μs, ", p, latency.to_formatted_string(&locale));
             }
         }
         println!();
 
+        let (wait_hist, pop_hist) = (&hists.drain_wait, &hists.drain_pop);
+        if !wait_hist.is_empty() {
+            println!(
+                "Drain wait: P50 {} μs, P99 {} μs; pop: P50 {} μs, P99 {} μs",
+                wait_hist
+                    .value_at_quantile(0.5)
+                    .to_formatted_string(&locale),
+                wait_hist
+                    .value_at_quantile(0.99)
+                    .to_formatted_string(&locale),
+                pop_hist.value_at_quantile(0.5).to_formatted_string(&locale),
+                pop_hist
+                    .value_at_quantile(0.99)
+                    .to_formatted_string(&locale)
+            );
+        }
+
         println!("---------------------------");
     }
 }
 
 async fn run_producer<T: Mempool>(
+    producer_id: u64,
+    seed: u64,
     queue: T,
     cfg: StressTestCfg,
     stats: Arc<TestStats>,
@@ -160,13 +328,18 @@ async fn run_producer<T: Mempool>(
     };
 
     let mut interval = delay.map(time::interval);
+    // Every producer gets its own generator, derived from the run seed.
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(producer_id));
+    let payload_buffer = cfg
+        .reuse_payloads
+        .then(|| generate_payload_buffer(&cfg, &mut rng));
 
     while stop_signal.load(Ordering::Relaxed) == 0 && tx_counter < cfg.num_transactions {
         // If rate limiting is enabled, wait for the next tick
         if let Some(ref mut i) = interval {
             i.tick().await;
         }
-        let tx = generate_random_transaction(&cfg, tx_counter);
+        let tx = generate_random_transaction(&cfg, &mut rng, tx_counter, payload_buffer.as_deref());
 
         match queue.submit(tx).await {
             Ok(_) => {
@@ -183,6 +356,7 @@ async fn run_producer<T: Mempool>(
 }
 
 async fn run_consumer<T: Mempool>(
+    consumer: usize,
     queue: T,
     cfg: StressTestCfg,
     stats: Arc<TestStats>,
@@ -194,24 +368,31 @@ async fn run_consumer<T: Mempool>(
 
     let mut interval = time::interval(Duration::from_micros(cfg.drain_interval_us));
 
+    let expected = (cfg.num_producers * cfg.num_transactions) as u64;
+
     while stop_signal.load(Ordering::Relaxed) == 0 {
         interval.tick().await;
 
+        let producing = stats.submitted_txs.load(Ordering::Relaxed) < expected;
+        if producing
+            && cfg.min_drain_batch > 0
+            && queue.len().await.is_ok_and(|len| len < cfg.min_drain_batch)
+        {
+            continue;
+        }
+
         let start = Instant::now();
         // Send drain request
         match queue
-            .drain(cfg.drain_batch_size, cfg.drain_timeout_us)
+            .drain_timed(cfg.drain_batch_size, cfg.drain_timeout_us)
             .await
         {
-            Ok(txs) => {
+            Ok((txs, timing)) => {
                 if cfg.latency_tracking && !txs.is_empty() {
-                    let delta_us: u64 = start
-                        .elapsed()
-                        .as_micros()
-                        .try_into()
-                        .expect("conversion okay for the next few years");
-
-                    stats.record_latency(delta_us).await;
+                    stats.record_latency(consumer, start.elapsed());
+                    if let Some(timing) = timing {
+                        stats.record_drain_timing(consumer, timing);
+                    }
                 }
 
                 stats.record_drain_success(txs.len() as u64);
@@ -223,11 +404,48 @@ async fn run_consumer<T: Mempool>(
     }
 }
 
-pub async fn run_stress_test<T: Mempool + Clone>(config: StressTestCfg, queue: T) {
+/// Outcome of one [`run_stress_test`].
+#[derive(Debug, Clone)]
+pub struct StressTestSummary {
+    /// Seed the run's transaction generators were derived from.
+    pub seed: u64,
+    pub num_consumers: usize,
+    pub drained_txs: u64,
+    /// Time between the start of all tasks and the shutdown signal.
+    pub elapsed: Duration,
+    /// 99th percentile of the submission-to-drain latency in microseconds, `None` if no latency has been
+    /// recorded.
+    pub p99_latency_us: Option<u64>,
+}
+
+impl StressTestSummary {
+    /// Drained transactions per second.
+    pub fn drain_throughput(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        self.drained_txs as f64 / elapsed
+    }
+}
+
+/// # Error
+/// Returns an error without spawning any task if the `config` asks for more than `max_producers` producers or
+/// for `histogram_sigfigs` outside of `0..=5`.
+pub async fn run_stress_test<T: Mempool + Clone>(
+    config: StressTestCfg,
+    queue: T,
+) -> anyhow::Result<StressTestSummary> {
+    config.check_producers()?;
+    let seed = config.seed.unwrap_or_else(rand::random);
+    println!("Run seed: {seed:#018x}");
     println!("Starting mempool stress test with config: {:?}", config);
 
     // Create shared stats collector
-    let stats = Arc::new(TestStats::new());
+    let stats = Arc::new(TestStats::new(
+        config.histogram_sigfigs,
+        config.num_consumers,
+    )?);
 
     // Start barrier ensures all producers and consumers start simultaneously
     let start_barrier = Arc::new(Barrier::new(
@@ -239,13 +457,15 @@ pub async fn run_stress_test<T: Mempool + Clone>(config: StressTestCfg, queue: T
 
     // Spawn producers
     let mut producer_handles = Vec::with_capacity(config.num_producers);
-    for _ in 0..config.num_producers {
+    for producer_id in 1..=config.num_producers as u64 {
         let producer_queue_handle = queue.clone();
         let producer_stats = Arc::clone(&stats);
         let producer_barrier = Arc::clone(&start_barrier);
         let producer_stop = Arc::clone(&stop_signal);
 
         let handle = tokio::spawn(run_producer(
+            producer_id,
+            seed,
             producer_queue_handle,
             config.clone(),
             producer_stats,
@@ -258,13 +478,14 @@ pub async fn run_stress_test<T: Mempool + Clone>(config: StressTestCfg, queue: T
 
     // Spawn consumers
     let mut consumer_handles = Vec::with_capacity(config.num_consumers);
-    for _ in 0..config.num_consumers {
+    for consumer in 0..config.num_consumers {
         let consumer_channels = queue.clone();
         let consumer_stats = Arc::clone(&stats);
         let consumer_barrier = Arc::clone(&start_barrier);
         let consumer_stop = Arc::clone(&stop_signal);
 
         let handle = tokio::spawn(run_consumer(
+            consumer,
             consumer_channels,
             config.clone(),
             consumer_stats,
@@ -302,13 +523,24 @@ pub async fn run_stress_test<T: Mempool + Clone>(config: StressTestCfg, queue: T
     println!("Waiting for all tasks to be ready...");
     start_barrier.wait().await;
     println!("Test started!");
+    let started = Instant::now();
 
     // Run for specified duration
-    time::sleep(Duration::from_secs(config.run_duration_seconds)).await;
+    let run_duration = time::sleep(Duration::from_secs(config.run_duration_seconds));
+    let expected = (config.num_producers * config.num_transactions) as u64;
+    if config.stop_when_drained {
+        tokio::select! {
+            _ = run_duration => println!("Test duration completed, shutting down..."),
+            _ = stats.drained_all(expected) => println!("All transactions drained, shutting down..."),
+        }
+    } else {
+        run_duration.await;
+        println!("Test duration completed, shutting down...");
+    }
 
     // Signal shutdown
-    println!("Test duration completed, shutting down...");
     stop_signal.store(1, Ordering::SeqCst);
+    let elapsed = started.elapsed();
 
     // Wait for all tasks to complete
     for handle in producer_handles {
@@ -320,15 +552,147 @@ pub async fn run_stress_test<T: Mempool + Clone>(config: StressTestCfg, queue: T
     }
 
     let _ = stats_printer.await;
+    println!("Run seed: {seed:#018x}");
+
+    Ok(StressTestSummary {
+        seed,
+        num_consumers: config.num_consumers,
+        drained_txs: stats.drained_txs.load(Ordering::Relaxed),
+        elapsed,
+        p99_latency_us: TestStats::calculate_percentile(&stats.merged_hists().latency, 99.0),
+    })
+}
+
+// region: auto tuning
+
+/// Settings of [`auto_tune`].
+#[derive(Debug, Clone)]
+pub struct AutoTuneCfg {
+    /// Largest number of consumers to try. Trials start with one consumer and double the count up to
+    /// this maximum.
+    pub max_consumers: usize,
+    /// Trials whose P99 submission-to-drain latency exceeds this target are not recommended.
+    pub target_p99_latency_us: u64,
+}
+
+impl AutoTuneCfg {
+    /// Consumer counts of the sub-trials: powers of two below `max_consumers`, followed by `max_consumers`.
+    fn consumer_counts(&self) -> Vec<usize> {
+        let max = self.max_consumers.max(1);
+        let mut counts: Vec<usize> =
+            std::iter::successors(Some(1), |&n: &usize| n.checked_mul(2).filter(|&n| n < max))
+                .collect();
+        if counts.last() != Some(&max) {
+            counts.push(max);
+        }
+        counts
+    }
+}
+
+/// Results of all [`auto_tune`] sub-trials.
+#[derive(Debug, Clone)]
+pub struct AutoTuneReport {
+    pub trials: Vec<StressTestSummary>,
+    pub target_p99_latency_us: u64,
+    /// Consumer count with the highest drain throughput among the trials that met the latency target.
+    pub recommended_consumers: Option<usize>,
+}
+
+impl AutoTuneReport {
+    fn new(trials: Vec<StressTestSummary>, target_p99_latency_us: u64) -> Self {
+        let recommended_consumers = trials
+            .iter()
+            .filter(|trial| {
+                trial
+                    .p99_latency_us
+                    .is_some_and(|p99| p99 <= target_p99_latency_us)
+            })
+            .max_by(|a, b| a.drain_throughput().total_cmp(&b.drain_throughput()))
+            .map(|trial| trial.num_consumers);
+        Self {
+            trials,
+            target_p99_latency_us,
+            recommended_consumers,
+        }
+    }
+
+    pub fn print_summary(&self) {
+        println!("\n=== Auto Tune Summary ===");
+        println!(
+            "{:>10} | {:>16} | {:>12}",
+            "Consumers", "Drained txs/s", "P99 (μs)"
+        );
+        for trial in &self.trials {
+            let p99 = trial
+                .p99_latency_us
+                .map_or_else(|| "-".to_string(), |p99| p99.to_string());
+            println!(
+                "{:>10} | {:>16.0} | {:>12}",
+                trial.num_consumers,
+                trial.drain_throughput(),
+                p99
+            );
+        }
+        match self.recommended_consumers {
+            Some(consumers) => println!(
+                "Recommended consumers: {consumers} (P99 target {}μs)",
+                self.target_p99_latency_us
+            ),
+            None => println!(
+                "No consumer count kept the P99 latency below {}μs",
+                self.target_p99_latency_us
+            ),
+        }
+    }
+}
+
+/// Runs one short [`run_stress_test`] per consumer count of `tune` against a fresh queue of
+/// `create_queue` and recommends the count that maximizes the drain throughput while keeping the P99
+/// latency within the target. Latency tracking is enabled for all sub-trials.
+///
+/// # Error
+/// Returns an error if the `config` is rejected by [`run_stress_test`].
+pub async fn auto_tune<T: Mempool + Clone>(
+    config: StressTestCfg,
+    tune: &AutoTuneCfg,
+    mut create_queue: impl FnMut() -> T,
+) -> anyhow::Result<AutoTuneReport> {
+    config.check_producers()?;
+    let mut trials = Vec::new();
+    for num_consumers in tune.consumer_counts() {
+        let trial_cfg = StressTestCfg {
+            num_consumers,
+            latency_tracking: true,
+            ..config.clone()
+        };
+        trials.push(run_stress_test(trial_cfg, create_queue()).await?);
+    }
+    Ok(AutoTuneReport::new(trials, tune.target_p99_latency_us))
 }
 
-fn generate_random_transaction(cfg: &StressTestCfg, tx_counter: usize) -> Transaction {
-    // Generate random transaction
+// endregion: auto tuning
+
+/// Generates a buffer of random bytes that is large enough to hold the biggest configured payload.
+fn generate_payload_buffer(cfg: &StressTestCfg, rng: &mut impl Rng) -> Vec<u8> {
+    (0..cfg.payload_size_range.1)
+        .map(|_| rng.random::<u8>())
+        .collect()
+}
 
-    let mut rng = rand::rng();
+/// Generates a random transaction. If a `payload_buffer` is passed, the payload is copied from its
+/// head instead of being generated from scratch.
+fn generate_random_transaction(
+    cfg: &StressTestCfg,
+    rng: &mut impl Rng,
+    tx_counter: usize,
+    payload_buffer: Option<&[u8]>,
+) -> Transaction {
     let gas_price = rng.random_range(cfg.gas_price_range.0..=cfg.gas_price_range.1);
     let payload_size = rng.random_range(cfg.payload_size_range.0..=cfg.payload_size_range.1);
-    let payload = (0..payload_size).map(|_| rng.random::<u8>()).collect();
+    let payload = match payload_buffer {
+        Some(buffer) => buffer[..payload_size].to_vec(),
+        None => (0..payload_size).map(|_| rng.random::<u8>()).collect(),
+    };
 
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -339,12 +703,7 @@ fn generate_random_transaction(cfg: &StressTestCfg, tx_counter: usize) -> Transa
 
     let id = format!("tx-{}", tx_counter);
 
-    Transaction {
-        id,
-        gas_price,
-        timestamp,
-        payload,
-    }
+    Transaction::from_parts(id, gas_price, timestamp, payload)
 }
 
 /// HTTP implementor of `Mempool` trait.
@@ -427,6 +786,37 @@ impl HttpFacade {
     }
 }
 
+/// Connection settings of the clients in a [`ClientPool`]. The defaults match those of [`Client::new`].
+#[derive(Debug, Clone)]
+pub struct ClientCfg {
+    /// Time an idle connection is kept open for reuse. Idle connections are never closed if `None`.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum number of idle connections each client keeps open per host.
+    pub pool_max_idle_per_host: usize,
+    /// Interval of TCP keep-alive probes on open connections. Keep-alive is disabled if `None`.
+    pub tcp_keepalive: Option<Duration>,
+}
+
+impl Default for ClientCfg {
+    fn default() -> Self {
+        Self {
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: usize::MAX,
+            tcp_keepalive: None,
+        }
+    }
+}
+
+impl ClientCfg {
+    fn build_client(&self) -> reqwest::Result<Client> {
+        Client::builder()
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .tcp_keepalive(self.tcp_keepalive)
+            .build()
+    }
+}
+
 /// Very simple pool implementation to use during the HTTP stress test.
 /// The pool creates a few clients in advance and wraps them in `Arc<Mutex>` so
 /// that they can be used within any task that needs to send HTTP requests.
@@ -435,20 +825,30 @@ impl HttpFacade {
 pub struct ClientPool {
     clients: Arc<Mutex<Vec<Client>>>,
     max_clients: usize,
+    cfg: ClientCfg,
 }
 
 impl ClientPool {
     pub fn new(max_clients: usize) -> Self {
-        let mut clients = Vec::with_capacity(max_clients);
-
-        for _ in 0..max_clients {
-            clients.push(Client::new());
-        }
+        Self::with_config(max_clients, ClientCfg::default())
+            .expect("default client configuration is valid")
+    }
 
-        ClientPool {
+    /// Creates a pool of `max_clients` clients with the connection settings of `cfg`, e.g. to reduce
+    /// connection churn during high-rate stress tests.
+    /// # Error
+    /// Returns an error if a client cannot be built, e.g. because the TLS backend cannot be initialized.
+    pub fn with_config(max_clients: usize, cfg: ClientCfg) -> anyhow::Result<Self> {
+        let clients = (0..max_clients)
+            .map(|_| cfg.build_client())
+            .collect::<Result<Vec<_>, _>>()
+            .context("could not build http client")?;
+
+        Ok(ClientPool {
             clients: Arc::new(Mutex::new(clients)),
             max_clients,
-        }
+            cfg,
+        })
     }
 
     pub async fn get_client(&self) -> Option<Client> {
@@ -456,7 +856,7 @@ impl ClientPool {
 
         if clients.is_empty() {
             if clients.len() < self.max_clients {
-                Some(Client::new())
+                self.cfg.build_client().ok()
             } else {
                 None // Pool exhausted
             }
@@ -474,3 +874,345 @@ impl ClientPool {
         // If over max_clients, client is dropped
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn test_cfg() -> StressTestCfg {
+        StressTestCfg {
+            num_producers: 1,
+            num_transactions: 100,
+            num_consumers: 1,
+            payload_size_range: (10, 50),
+            drain_interval_us: 1,
+            drain_timeout_us: 1,
+            drain_batch_size: 10,
+            gas_price_range: (1, 1000),
+            run_duration_seconds: 1,
+            submission_rate: None,
+            latency_tracking: false,
+            print_stats_interval_ms: 1000,
+            latency_percentiles: vec![],
+            histogram_sigfigs: 3,
+            http_port: None,
+            reuse_payloads: true,
+            stop_when_drained: false,
+            min_drain_batch: 0,
+            max_producers: StressTestCfg::DEFAULT_MAX_PRODUCERS,
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn reused_payloads_vary_in_size_within_range() {
+        let cfg = test_cfg();
+        let mut rng = rand::rng();
+        let buffer = generate_payload_buffer(&cfg, &mut rng);
+
+        let sizes: HashSet<usize> = (0..1_000)
+            .map(|i| generate_random_transaction(&cfg, &mut rng, i, Some(&buffer)))
+            .map(|tx| tx.payload.len())
+            .collect();
+
+        assert!(sizes.len() > 1);
+        assert!(
+            sizes
+                .iter()
+                .all(|size| (cfg.payload_size_range.0..=cfg.payload_size_range.1).contains(size))
+        );
+    }
+
+    #[tokio::test]
+    async fn seeded_runs_submit_the_same_transactions() {
+        /// Records every submitted transaction.
+        #[derive(Clone)]
+        struct SubmitRecorder {
+            inner: Arc<crate::LockedQueue>,
+            submitted: Arc<std::sync::Mutex<Vec<Transaction>>>,
+        }
+
+        #[async_trait::async_trait]
+        impl Mempool for SubmitRecorder {
+            async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
+                self.submitted.lock().unwrap().push(tx.clone());
+                self.inner.submit(tx).await
+            }
+
+            async fn drain(&self, n: usize, timeout_us: u64) -> anyhow::Result<Vec<Transaction>> {
+                self.inner.drain(n, timeout_us).await
+            }
+        }
+
+        let submitted = |seed| async move {
+            let cfg = StressTestCfg {
+                num_transactions: 20,
+                print_stats_interval_ms: 10,
+                stop_when_drained: true,
+                reuse_payloads: false,
+                seed,
+                ..test_cfg()
+            };
+            let pool = SubmitRecorder {
+                inner: Arc::new(crate::LockedQueue::new(20)),
+                submitted: Arc::default(),
+            };
+            let summary = run_stress_test(cfg, pool.clone()).await.unwrap();
+            let submitted: Vec<(u64, Vec<u8>)> =
+                std::mem::take(&mut *pool.submitted.lock().unwrap())
+                    .into_iter()
+                    .map(|tx| (tx.gas_price, tx.payload))
+                    .collect();
+            (summary.seed, submitted)
+        };
+
+        let (seed, first) = submitted(Some(0x5eed)).await;
+        assert_eq!(seed, 0x5eed);
+        assert_eq!(first.len(), 20);
+        assert_eq!(submitted(Some(0x5eed)).await.1, first);
+
+        // -- the reported seed of an unseeded run reproduces it
+        let (seed, unseeded) = submitted(None).await;
+        assert_eq!(submitted(Some(seed)).await.1, unseeded);
+    }
+
+    #[tokio::test]
+    async fn stats_with_custom_histogram_sigfigs() {
+        let stats = TestStats::new(5, 1).unwrap();
+        stats.record_latency(0, Duration::from_micros(1_234));
+        assert_eq!(stats.merged_hists().latency.len(), 1);
+
+        assert!(TestStats::new(6, 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn huge_latency_counts_as_overflow() {
+        let stats = TestStats::new(3, 1).unwrap();
+        stats.record_latency(0, Duration::from_micros(10));
+        assert_eq!(stats.latency_overflows.load(Ordering::Relaxed), 0);
+
+        stats.record_latency(0, Duration::MAX);
+        assert_eq!(stats.latency_overflows.load(Ordering::Relaxed), 1);
+        let hist = stats.merged_hists().latency;
+        assert_eq!(hist.len(), 2);
+        assert!(hist.equivalent(hist.max(), hist.high()));
+    }
+
+    #[tokio::test]
+    async fn merged_histograms_count_every_consumer_recording() {
+        let stats = TestStats::new(3, 4).unwrap();
+        let recordings = [3, 0, 7, 1];
+        for (consumer, &count) in recordings.iter().enumerate() {
+            for i in 0..count {
+                stats.record_latency(consumer, Duration::from_micros(10 * (i + 1)));
+                let timing = DrainTiming {
+                    wait: Duration::from_micros(i),
+                    pop: Duration::from_micros(1),
+                };
+                stats.record_drain_timing(consumer, timing);
+            }
+        }
+
+        let merged = stats.merged_hists();
+        let total: u64 = recordings.iter().sum();
+        assert_eq!(merged.latency.len(), total);
+        assert_eq!(merged.drain_wait.len(), total);
+        assert_eq!(merged.drain_pop.len(), total);
+        assert_eq!(merged.latency.max(), 70);
+
+        // -- merging does not consume the per-consumer recordings
+        assert_eq!(stats.merged_hists().latency.len(), total);
+    }
+
+    #[tokio::test]
+    async fn skewed_counters_print_empty_queue() {
+        assert_eq!(TestStats::queue_size(10, 12), 0);
+        assert_eq!(TestStats::queue_size(12, 10), 2);
+
+        // -- printing must not panic on the underflow in debug builds
+        let stats = TestStats::new(3, 1).unwrap();
+        stats.submitted_txs.store(10, Ordering::Relaxed);
+        stats.drained_txs.store(12, Ordering::Relaxed);
+        stats.print_stats(1.0, &[50.0]).await;
+    }
+
+    #[tokio::test]
+    async fn client_pool_with_custom_config_sends_requests() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n")
+                .unwrap();
+        });
+
+        let pool = ClientPool::with_config(
+            2,
+            ClientCfg {
+                pool_idle_timeout: Some(Duration::from_secs(5)),
+                pool_max_idle_per_host: 4,
+                tcp_keepalive: Some(Duration::from_secs(30)),
+            },
+        )
+        .unwrap();
+        let client = pool.get_client().await.unwrap();
+        let response = client.get(format!("http://{addr}/")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        pool.return_client(client).await;
+
+        server.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn too_many_producers_are_rejected() {
+        let cfg = StressTestCfg {
+            num_producers: 3,
+            max_producers: 2,
+            ..test_cfg()
+        };
+
+        let err = run_stress_test(cfg, crate::LockedQueue::new(10))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("3 producers exceed the maximum of 2")
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_histogram_sigfigs_are_rejected() {
+        let cfg = StressTestCfg {
+            histogram_sigfigs: 6,
+            ..test_cfg()
+        };
+
+        let err = run_stress_test(cfg, crate::LockedQueue::new(10))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("latency histogram"));
+    }
+
+    #[tokio::test]
+    async fn stops_early_once_drained() {
+        let cfg = StressTestCfg {
+            run_duration_seconds: 60,
+            print_stats_interval_ms: 10,
+            stop_when_drained: true,
+            ..test_cfg()
+        };
+        let queue = crate::worker::Queue::start(crate::worker::Cfg {
+            capacity: 100,
+            submittance_back_pressure: 100,
+            max_drain_batch: 100,
+            drain_request_buffer: 10,
+            drain_retry_delay: crate::worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: crate::worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: crate::worker::BackpressureStrategy::Block,
+        });
+
+        time::timeout(Duration::from_secs(10), run_stress_test(cfg, queue.clone()))
+            .await
+            .expect("test should stop once all transactions are drained")
+            .unwrap();
+
+        queue.stop();
+    }
+
+    #[test]
+    fn auto_tune_doubles_consumers_up_to_max() {
+        let counts = |max_consumers| {
+            AutoTuneCfg {
+                max_consumers,
+                target_p99_latency_us: 0,
+            }
+            .consumer_counts()
+        };
+        assert_eq!(counts(0), vec![1]);
+        assert_eq!(counts(1), vec![1]);
+        assert_eq!(counts(4), vec![1, 2, 4]);
+        assert_eq!(counts(6), vec![1, 2, 4, 6]);
+    }
+
+    #[tokio::test]
+    async fn auto_tune_recommends_consumers_within_range() {
+        let cfg = StressTestCfg {
+            num_transactions: 50,
+            print_stats_interval_ms: 10,
+            stop_when_drained: true,
+            ..test_cfg()
+        };
+        let tune = AutoTuneCfg {
+            max_consumers: 3,
+            target_p99_latency_us: u64::MAX,
+        };
+
+        let report = auto_tune(cfg, &tune, || crate::LockedQueue::new(100))
+            .await
+            .unwrap();
+
+        let consumers: Vec<usize> = report.trials.iter().map(|t| t.num_consumers).collect();
+        assert_eq!(consumers, vec![1, 2, 3]);
+        let recommended = report.recommended_consumers.unwrap();
+        assert!((1..=3).contains(&recommended));
+    }
+
+    #[tokio::test]
+    async fn consumer_waits_for_min_drain_batch() {
+        /// Records the size of every drained batch.
+        #[derive(Clone)]
+        struct BatchRecorder {
+            inner: Arc<crate::LockedQueue>,
+            batches: Arc<std::sync::Mutex<Vec<usize>>>,
+        }
+
+        #[async_trait::async_trait]
+        impl Mempool for BatchRecorder {
+            async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
+                self.inner.submit(tx).await
+            }
+
+            async fn drain(&self, n: usize, timeout_us: u64) -> anyhow::Result<Vec<Transaction>> {
+                let drained = self.inner.drain(n, timeout_us).await?;
+                self.batches.lock().unwrap().push(drained.len());
+                Ok(drained)
+            }
+
+            async fn len(&self) -> anyhow::Result<usize> {
+                self.inner.len().await
+            }
+        }
+
+        let cfg = StressTestCfg {
+            num_transactions: 20,
+            drain_interval_us: 1_000,
+            drain_timeout_us: 1_000_000,
+            drain_batch_size: 5,
+            run_duration_seconds: 10,
+            submission_rate: Some(500.0),
+            print_stats_interval_ms: 10,
+            stop_when_drained: true,
+            min_drain_batch: 5,
+            ..test_cfg()
+        };
+        let pool = BatchRecorder {
+            inner: Arc::new(crate::LockedQueue::new(20)),
+            batches: Arc::default(),
+        };
+
+        run_stress_test(cfg, pool.clone()).await.unwrap();
+
+        // -- once all transactions are drained, consumers may drain the empty queue until the test stops
+        let batches = pool.batches.lock().unwrap();
+        assert_eq!(batches[..4], [5, 5, 5, 5]);
+        assert!(batches[4..].iter().all(|&batch| batch == 0));
+    }
+}
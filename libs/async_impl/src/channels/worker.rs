@@ -1,12 +1,29 @@
-use std::{collections::BinaryHeap, sync::Arc, time::Duration};
+use std::{
+    collections::{BinaryHeap, HashMap},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
-use anyhow::Context;
+use anyhow::{Context, bail};
+use bytes::Bytes;
+use futures::Stream;
 use mempool::Transaction;
-use tokio::{select, sync, task::JoinHandle, time::Instant};
+use tokio::{
+    select,
+    sync::{self, mpsc::error::TrySendError},
+    task::JoinHandle,
+    time::Instant,
+};
 
 use crate::{Mempool, channels::drain_strategy::DrainStrategy};
 
-use super::drain_strategy::DrainRequest;
+use super::{
+    drain_strategy::{DrainRequest, DrainTiming},
+    rate_tracker::RateTracker,
+};
 
 #[derive(Clone)]
 pub struct Queue {
@@ -15,27 +32,104 @@ pub struct Queue {
     /// Handle to the worker task that manages the internal storage of the queue.
     /// Abort this task to drop the associated memory and stop
     runner_handle: Arc<JoinHandle<Option<()>>>,
+
+    pause_switch: Arc<PauseSwitch>,
+
+    /// Submit and drain counts of the recent past, updated by the worker task.
+    rate_tracker: Arc<Mutex<RateTracker>>,
+
+    /// Number of times waiting drain requests have been re-queued.
+    drain_requeues: Arc<AtomicU64>,
+
+    /// Number of transactions in the storage of the worker task, updated after every request it handles.
+    len: Arc<AtomicUsize>,
+
+    backpressure: BackpressureStrategy,
+}
+
+/// Suspends the servicing of drain requests, see [`Queue::pause`].
+#[derive(Debug, Default)]
+struct PauseSwitch {
+    paused: AtomicBool,
+    /// Wakes the worker task after pausing or resuming, so that it re-reads `paused` right away. After resuming, it
+    /// picks up the drain requests that piled up.
+    toggled: sync::Notify,
 }
 
 #[async_trait::async_trait]
 impl Mempool for Queue {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(id = %tx.id, duration_us = tracing::field::Empty))
+    )]
     async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
+        #[cfg(feature = "tracing")]
+        let submit_start = Instant::now();
+        let result = self.submit_with_backpressure(tx).await;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("duration_us", submit_start.elapsed().as_micros() as u64);
+        result
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip(self),
+            fields(batch_size = tracing::field::Empty, duration_us = tracing::field::Empty)
+        )
+    )]
+    async fn drain(&self, n: usize, timeout_us: u64) -> anyhow::Result<Vec<Transaction>> {
+        #[cfg(feature = "tracing")]
+        let drain_start = Instant::now();
+        let (req, rx_drainage) = DrainRequest::new_with_timeout(n, timeout_us);
         self.channels
-            .submittance_source
-            .send(tx)
+            .drain_request_source
+            .send(req)
+            .await
+            .context("could not send drain request to queue")?;
+        let drained = rx_drainage
             .await
-            .context("could not submit transaction to queue")
+            .context("could not receive drainage result from queue")?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("batch_size", drained.len())
+            .record("duration_us", drain_start.elapsed().as_micros() as u64);
+        Ok(drained)
     }
-    async fn drain(&self, n: usize, timeout_us: u64) -> anyhow::Result<Vec<Transaction>> {
+
+    async fn drain_timed(
+        &self,
+        n: usize,
+        timeout_us: u64,
+    ) -> anyhow::Result<(Vec<Transaction>, Option<DrainTiming>)> {
         let (req, rx_drainage) = DrainRequest::new_with_timeout(n, timeout_us);
+        let (req, rx_timing) = req.with_timing();
         self.channels
             .drain_request_source
             .send(req)
             .await
             .context("could not send drain request to queue")?;
-        rx_drainage
+        let drained = rx_drainage
+            .await
+            .context("could not receive drainage result from queue")?;
+        Ok((drained, rx_timing.await.ok()))
+    }
+
+    /// Transactions that are still buffered in the submittance channel when the flush request is received
+    /// are added to the queue first, so they have to be drained as well.
+    async fn flush(&self) -> anyhow::Result<()> {
+        let (reply, rx) = sync::oneshot::channel();
+        self.channels
+            .flush_request_source
+            .send(reply)
             .await
-            .context("could not receive drainage result from queue")
+            .context("could not send flush request to queue")?;
+        rx.await.context("queue has stopped before it was flushed")
+    }
+
+    /// Transactions that are still buffered in the submittance channel are not counted.
+    async fn len(&self) -> anyhow::Result<usize> {
+        Ok(self.len.load(Ordering::Relaxed))
     }
 }
 #[derive(Debug, Clone)]
@@ -47,12 +141,110 @@ pub struct Cfg {
     /// Number of [`Transaction`]s to keep in the submitter channels buffer before
     /// blocking senders.
     pub submittance_back_pressure: usize,
+    /// Maximum number of [`Transaction`]s that are returned by a single drain request.
+    /// Requests for more transactions are clamped to this value.
+    pub max_drain_batch: usize,
+    /// Number of drain requests to keep in the drain request channels buffer before blocking senders.
+    /// Limits the number of concurrently pending drain requests.
+    pub drain_request_buffer: usize,
+    /// Delay before a waiting drain request is re-queued for the first time, see [`Cfg::DEFAULT_DRAIN_RETRY_DELAY`].
+    pub drain_retry_delay: Duration,
+    /// The retry delay doubles with every re-queue of a drain request until it reaches this cap.
+    /// Set it to `drain_retry_delay` to retry at a constant rate.
+    pub max_drain_retry_delay: Duration,
+    /// The worker stops itself once it has not received any request for this long, e.g. to free the resources
+    /// of serverless deployments. Transactions still held by the queue are dropped. Runs until stopped if `None`.
+    pub idle_shutdown: Option<Duration>,
+    /// Handling of submits via [`Queue`]'s [`Mempool::submit`] while the submittance buffer is full.
+    /// Senders obtained via [`Queue::detach_channels`] always block.
+    pub backpressure: BackpressureStrategy,
+}
+
+/// Handling of a submit while the submittance buffer of the [`Queue`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackpressureStrategy {
+    /// The submitter waits until the worker makes room in the buffer.
+    #[default]
+    Block,
+    /// The submitted transaction is dropped and the submit succeeds.
+    DropNewest,
+    /// The submitted transaction bypasses the buffer and replaces the queued transaction with the earliest
+    /// `timestamp`, which is dropped. The submit succeeds without waiting, and the submit does not grow the queue.
+    /// Displacing transactions wait in a second buffer of the same size as the submittance buffer. Once it is
+    /// full as well, the submit fails as with [`BackpressureStrategy::Reject`].
+    DropOldest,
+    /// The submit fails, so the submitter can decide how to proceed.
+    Reject,
+}
+
+impl Cfg {
+    pub const DEFAULT_DRAIN_RETRY_DELAY: Duration = Duration::from_micros(50);
+    pub const DEFAULT_MAX_DRAIN_RETRY_DELAY: Duration = Duration::from_millis(1);
+
+    /// Delay before re-queuing a waiting drain request that has been re-queued `retries` times before.
+    fn drain_retry_delay(&self, retries: u32) -> Duration {
+        self.drain_retry_delay
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max_drain_retry_delay)
+    }
+}
+
+/// Reply channel of a peek request.
+pub type PeekRequest = sync::oneshot::Sender<Option<Transaction>>;
+
+/// Reply channel of a flush request, signaled once the queue is empty.
+type FlushRequest = sync::oneshot::Sender<()>;
+
+/// Transaction and the reply channel that is signaled once it has been added to the storage.
+type ConfirmedSubmittance = (Transaction, sync::oneshot::Sender<()>);
+
+/// Id of the transaction to wait for and the reply channel that is signaled once it is drained.
+type DrainWaiter = (String, sync::oneshot::Sender<()>);
+
+/// Drain request that waits for more transactions, along with the instant it is checked again.
+type WaitingDrain = (Instant, DrainRequest);
+
+/// Reply channels of the drain waiters by the id of the transaction they wait for.
+type DrainWaiters = HashMap<String, Vec<sync::oneshot::Sender<()>>>;
+
+/// Number of transactions [`Queue::drain_incremental`] streams ahead of its receiver. Further transactions stay
+/// in the queue until the receiver has taken some of them.
+const INCREMENTAL_DRAIN_BUFFER: usize = 64;
+
+/// Pending request of [`Queue::drain_incremental`].
+#[derive(Debug)]
+struct IncrementalDrain {
+    /// Number of transactions that are still to be streamed.
+    remaining: usize,
+    /// The stream ends at this instant, even if fewer transactions have been streamed.
+    deadline: Instant,
+    sink: sync::mpsc::Sender<Transaction>,
 }
 
+/// Id of the transaction to remove and the reply channel for the removed transaction.
+pub type RemoveRequest = (String, sync::oneshot::Sender<Option<Transaction>>);
+
+/// Resolves once the worker has deserialized a raw submittance, see [`Queue::submit_raw`].
+pub type RawVerdict = sync::oneshot::Receiver<anyhow::Result<()>>;
+
+/// JSON encoded [`Transaction`] and the reply channel for the result of its deserialization.
+type RawSubmittance = (Bytes, sync::oneshot::Sender<anyhow::Result<()>>);
+
 #[derive(Debug, Clone)]
 pub struct Channels {
     submittance_source: sync::mpsc::Sender<Transaction>,
+    raw_submittance_source: sync::mpsc::Sender<RawSubmittance>,
+    confirmed_submittance_source: sync::mpsc::Sender<ConfirmedSubmittance>,
     drain_request_source: sync::mpsc::Sender<DrainRequest>,
+    incremental_drain_source: sync::mpsc::Sender<IncrementalDrain>,
+    peek_request_source: sync::mpsc::Sender<PeekRequest>,
+    remove_request_source: sync::mpsc::Sender<RemoveRequest>,
+    flush_request_source: sync::mpsc::Sender<FlushRequest>,
+    /// Transactions that replace the oldest queued transaction, see [`BackpressureStrategy::DropOldest`].
+    displacement_source: sync::mpsc::Sender<Transaction>,
+    drain_waiter_source: sync::mpsc::Sender<DrainWaiter>,
+    /// Holds `true` while the queue contains at least one transaction.
+    non_empty_sink: sync::watch::Receiver<bool>,
 }
 
 impl Channels {
@@ -64,43 +256,257 @@ impl Channels {
     ) {
         (self.submittance_source, self.drain_request_source)
     }
+
+    /// Returns a sender for requests to remove transactions by id, see [`Queue::remove`].
+    pub fn remove_request_source(&self) -> sync::mpsc::Sender<RemoveRequest> {
+        self.remove_request_source.clone()
+    }
 }
 
 impl Queue {
-    const DRAIN_RETRY_DELAY: Duration = Duration::from_nanos(100);
-
     pub fn start(cfg: Cfg) -> Self {
         let (channels, internal_channels) = prepare_channels(&cfg);
 
-        let runner_handle = Arc::new(tokio::task::spawn(Self::run(cfg, internal_channels)));
+        let pause_switch = Arc::new(PauseSwitch::default());
+        let rate_tracker = Arc::new(Mutex::new(RateTracker::new(Instant::now())));
+        let drain_requeues = Arc::new(AtomicU64::new(0));
+        let len = Arc::new(AtomicUsize::new(0));
+        let backpressure = cfg.backpressure;
+
+        let runner_handle = Arc::new(tokio::task::spawn(Self::run(
+            cfg,
+            internal_channels,
+            pause_switch.clone(),
+            rate_tracker.clone(),
+            drain_requeues.clone(),
+            len.clone(),
+        )));
         Self {
             runner_handle,
             channels,
+            pause_switch,
+            rate_tracker,
+            drain_requeues,
+            len,
+            backpressure,
         }
     }
 
-    async fn run(cfg: Cfg, mut channels: InternalChannels) -> Option<()> {
+    async fn run(
+        cfg: Cfg,
+        mut channels: InternalChannels,
+        pause_switch: Arc<PauseSwitch>,
+        rate_tracker: Arc<Mutex<RateTracker>>,
+        drain_requeues: Arc<AtomicU64>,
+        len: Arc<AtomicUsize>,
+    ) -> Option<()> {
         let mut storage = BinaryHeap::with_capacity(cfg.capacity);
+        let mut flush_waiters: Vec<FlushRequest> = vec![];
+        let mut drain_waiters = DrainWaiters::new();
+        let mut incremental_drains: Vec<IncrementalDrain> = vec![];
+        let mut waiting_drains: Vec<WaitingDrain> = vec![];
+        // Drain requests received after a pause that has not been observed at the start of the iteration.
+        let mut parked_drains: Vec<DrainRequest> = vec![];
+        let mut last_activity = Instant::now();
 
         loop {
+            let len_before = storage.len();
+            let mut submitted = 0;
+            let mut evicted = 0;
+            let mut removed = 0;
+            let idle_deadline = last_activity + cfg.idle_shutdown.unwrap_or_default();
+            // -- drains whose receiver lags behind are fed again after the retry delay
+            let incremental_deadline = incremental_drains
+                .iter()
+                .map(|drain| match drain.sink.capacity() {
+                    0 => drain.deadline.min(Instant::now() + cfg.drain_retry_delay),
+                    _ => drain.deadline,
+                })
+                .min()
+                .unwrap_or(idle_deadline);
+            let next_drain_check = waiting_drains
+                .iter()
+                .map(|(check_at, _)| *check_at)
+                .min()
+                .unwrap_or(idle_deadline);
+            let paused = pause_switch.paused.load(Ordering::Acquire);
             select! {
                 t = channels.submittance_sink.recv() => {
                     storage.push(t?);
+                    submitted = 1;
+                }
+                // Raw submittance is optional, a closed raw channel must not stop the queue.
+                Some((bytes, verdict)) = channels.raw_submittance_sink.recv() => {
+                    let result = serde_json::from_slice::<Transaction>(&bytes)
+                        .map(|tx| {
+                            storage.push(tx);
+                            submitted = 1;
+                        })
+                        .context("could not deserialize raw transaction");
+                    verdict.send(result).ok();
+                }
+                // Confirmed submittance is optional, a closed confirmation channel must not stop the queue.
+                Some((t, ack)) = channels.confirmed_submittance_sink.recv() => {
+                    storage.push(t);
+                    submitted = 1;
+                    ack.send(()).ok();
+                }
+                // Peeking is optional, a closed peek channel must not stop the queue.
+                Some(reply) = channels.peek_request_sink.recv() => {
+                    reply.send(storage.peek().cloned()).ok();
                 }
-                req = channels.drain_request_sink.recv() => {
-                    let req = req?;
-                    match req.wait_strategy {
-                        DrainStrategy::DrainMax => Self::handle_drain_max(req, &mut storage),
-                        DrainStrategy::WaitForN(_) => {
-                            Self::handle_drain_waiting(req, &mut storage, &mut channels.drain_request_source).await;
-                        }
+                // Flushing is optional, a closed flush channel must not stop the queue.
+                Some(reply) = channels.flush_request_sink.recv() => {
+                    while let Ok(t) = channels.submittance_sink.try_recv() {
+                        storage.push(t);
+                        submitted += 1;
                     }
+                    flush_waiters.push(reply);
+                }
+                // Displacing is optional, a closed displacement channel must not stop the queue.
+                Some(t) = channels.displacement_sink.recv() => {
+                    // Displace all pending transactions at once, so the heap is rebuilt once per batch.
+                    let mut displacing = vec![t];
+                    while let Ok(t) = channels.displacement_sink.try_recv() {
+                        displacing.push(t);
+                    }
+                    for oldest in Self::evict_oldest(&mut storage, displacing.len()) {
+                        eprintln!("Warn! Submittance buffer is full, transaction {} is dropped.", oldest.id);
+                        evicted += 1;
+                    }
+                    submitted = displacing.len();
+                    storage.extend(displacing);
+                }
+                // Waiting for drains is optional, a closed drain waiter channel must not stop the queue.
+                Some((id, reply)) = channels.drain_waiter_sink.recv() => {
+                    // Drop the waiters that have given up, as the transaction they wait for might never be drained.
+                    drain_waiters.retain(|_, replies| {
+                        replies.retain(|reply| !reply.is_closed());
+                        !replies.is_empty()
+                    });
+                    drain_waiters.entry(id).or_default().push(reply);
+                }
+                // Removing is optional, a closed remove channel must not stop the queue.
+                Some((id, reply)) = channels.remove_request_sink.recv() => {
+                    let tx = Self::remove_by_id(&mut storage, &id);
+                    removed = usize::from(tx.is_some());
+                    reply.send(tx).ok();
+                }
+                // While paused, drain requests stay buffered in their channel.
+                Some(req) = channels.drain_request_sink.recv(), if !paused => {
+                    // The queue may have been paused since the flag has been read for this iteration.
+                    if pause_switch.paused.load(Ordering::Acquire) {
+                        parked_drains.push(req);
+                    } else {
+                        waiting_drains.extend(Self::handle_drain_request(req, &mut storage, &mut drain_waiters, &cfg, &drain_requeues));
+                    }
+                }
+                // Waiting drain requests are checked again in the order they have been received, and not at all while paused.
+                _ = tokio::time::sleep_until(next_drain_check), if !waiting_drains.is_empty() && !paused => {
+                    let now = Instant::now();
+                    let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut waiting_drains)
+                        .into_iter()
+                        .partition(|(check_at, _)| *check_at <= now);
+                    waiting_drains = pending;
+                    for (_, req) in due {
+                        waiting_drains.extend(Self::handle_drain_waiting(req, &mut storage, &mut drain_waiters, &cfg, &drain_requeues));
+                    }
+                }
+                // Incremental drains are optional, a closed incremental drain channel must not stop the queue.
+                Some(mut drain) = channels.incremental_drain_sink.recv() => {
+                    if drain.remaining > cfg.max_drain_batch {
+                        eprintln!("Warn! Incremental drain of {} transactions is clamped to {}.", drain.remaining, cfg.max_drain_batch);
+                        drain.remaining = cfg.max_drain_batch;
+                    }
+                    incremental_drains.push(drain);
+                }
+                // Ends the streams of incremental drains whose timeout fires while the queue is quiet.
+                _ = tokio::time::sleep_until(incremental_deadline), if !incremental_drains.is_empty() => {}
+                _ = pause_switch.toggled.notified() => {}
+                _ = tokio::time::sleep_until(idle_deadline), if cfg.idle_shutdown.is_some() => {
+                    eprintln!("Warn! Queue has been idle for {:?} and shuts down.", cfg.idle_shutdown.unwrap_or_default());
+                    return Some(());
+                }
+            }
+            last_activity = Instant::now();
+            if !parked_drains.is_empty() && !pause_switch.paused.load(Ordering::Acquire) {
+                for req in std::mem::take(&mut parked_drains) {
+                    waiting_drains.extend(Self::handle_drain_request(
+                        req,
+                        &mut storage,
+                        &mut drain_waiters,
+                        &cfg,
+                        &drain_requeues,
+                    ));
+                }
+            }
+            Self::feed_incremental_drains(
+                &mut incremental_drains,
+                &mut storage,
+                &mut drain_waiters,
+                pause_switch.paused.load(Ordering::Acquire),
+            );
+
+            // Re-queued transactions of hung up requesters, evicted and removed transactions do not count as drained.
+            let drained =
+                (len_before + submitted).saturating_sub(storage.len() + evicted + removed);
+            if submitted > 0 || drained > 0 {
+                let mut rate_tracker = rate_tracker.lock().unwrap();
+                let now = Instant::now();
+                rate_tracker.record_submits(now, submitted as u64);
+                rate_tracker.record_drains(now, drained as u64);
+            }
+
+            len.store(storage.len(), Ordering::Relaxed);
+            if storage.is_empty() {
+                for waiter in flush_waiters.drain(..) {
+                    waiter.send(()).ok();
                 }
             }
+
+            channels.non_empty_source.send_if_modified(|non_empty| {
+                let was_non_empty = std::mem::replace(non_empty, !storage.is_empty());
+                was_non_empty != *non_empty
+            });
+        }
+    }
+
+    /// Clamps `req` to [`Cfg::max_drain_batch`] and services it according to its wait strategy. Returns the request
+    /// if it has to wait for more transactions.
+    fn handle_drain_request(
+        mut req: DrainRequest,
+        storage: &mut BinaryHeap<Transaction>,
+        drain_waiters: &mut DrainWaiters,
+        cfg: &Cfg,
+        drain_requeues: &AtomicU64,
+    ) -> Option<WaitingDrain> {
+        if req.n > cfg.max_drain_batch {
+            // Byte target requests do not ask for a count, so only count requests are worth a warning.
+            if !matches!(req.wait_strategy, DrainStrategy::WaitForBytes { .. }) {
+                eprintln!(
+                    "Warn! Drain request for {} transactions is clamped to {}.",
+                    req.n, cfg.max_drain_batch
+                );
+            }
+            req.n = cfg.max_drain_batch;
+        }
+        match req.wait_strategy {
+            DrainStrategy::DrainMax => {
+                Self::handle_drain_max(req, storage, drain_waiters);
+                None
+            }
+            DrainStrategy::WaitForN(_) | DrainStrategy::WaitForBytes { .. } => {
+                Self::handle_drain_waiting(req, storage, drain_waiters, cfg, drain_requeues)
+            }
         }
     }
 
-    fn handle_drain_max(req: DrainRequest, storage: &mut BinaryHeap<Transaction>) {
+    fn handle_drain_max(
+        req: DrainRequest,
+        storage: &mut BinaryHeap<Transaction>,
+        drain_waiters: &mut DrainWaiters,
+    ) {
+        let pop_start = Instant::now();
         let mut drained = Vec::with_capacity(req.n);
         for _ in 0..req.n {
             let Some(item) = storage.pop() else {
@@ -109,34 +515,389 @@ impl Queue {
             drained.push(item);
         }
 
-        // TODO: Feed back drained elements in case of error
-        req.send_back.send(drained).inspect_err(|_|eprintln!("Warn! Queue has been drained but requester has hung up. Drained elements are thrown away.")).ok();
+        Self::send_back(req, drained, pop_start, storage, drain_waiters);
+    }
+
+    /// Streams queued transactions to the pending incremental drains in the order of their requests, and signals
+    /// the drain waiters of the streamed transactions. A drain whose stream buffer is full is skipped until its
+    /// receiver catches up. Drains are completed once they have streamed all requested transactions, their deadline
+    /// has passed or their requester has hung up. While `paused`, no transactions are streamed, but drains still
+    /// complete at their deadline.
+    fn feed_incremental_drains(
+        drains: &mut Vec<IncrementalDrain>,
+        storage: &mut BinaryHeap<Transaction>,
+        drain_waiters: &mut DrainWaiters,
+        paused: bool,
+    ) {
+        let now = Instant::now();
+        drains.retain_mut(|drain| {
+            while !paused && drain.remaining > 0 {
+                let Some(tx) = storage.pop() else {
+                    break;
+                };
+                let id = (!drain_waiters.is_empty()).then(|| tx.id.clone());
+                match drain.sink.try_send(tx) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(tx)) => {
+                        storage.push(tx);
+                        break;
+                    }
+                    Err(TrySendError::Closed(tx)) => {
+                        storage.push(tx);
+                        return false;
+                    }
+                }
+                drain.remaining -= 1;
+                for reply in id
+                    .and_then(|id| drain_waiters.remove(&id))
+                    .into_iter()
+                    .flatten()
+                {
+                    reply.send(()).ok();
+                }
+            }
+            drain.remaining > 0 && now < drain.deadline && !drain.sink.is_closed()
+        });
+    }
+
+    /// Removes the transaction with the given `id` from the `storage` by rebuilding the heap without it.
+    fn remove_by_id(storage: &mut BinaryHeap<Transaction>, id: &str) -> Option<Transaction> {
+        let idx = storage.iter().position(|tx| tx.id == id)?;
+        let mut items = std::mem::take(storage).into_vec();
+        let removed = items.swap_remove(idx);
+        *storage = BinaryHeap::from(items);
+        Some(removed)
+    }
+
+    /// Removes up to `k` transactions with the earliest `timestamp` from the `storage`. They are selected in
+    /// `O(n)` and the heap is rebuilt once without them, regardless of `k`.
+    fn evict_oldest(storage: &mut BinaryHeap<Transaction>, k: usize) -> Vec<Transaction> {
+        let k = k.min(storage.len());
+        if k == 0 {
+            return vec![];
+        }
+        let mut items = std::mem::take(storage).into_vec();
+        items.select_nth_unstable_by_key(k - 1, |tx| tx.timestamp);
+        let kept = items.split_off(k);
+        *storage = BinaryHeap::from(kept);
+        items
+    }
+
+    /// Sends the `drained` transactions back to the requester and signals the drain waiters of the transactions.
+    /// Should the requester have hung up in the meantime, the transactions are fed back into the `storage`.
+    /// The [`DrainTiming`] is sent first if the requester asked for it, with the pop time counted from `pop_start`.
+    fn send_back(
+        req: DrainRequest,
+        drained: Vec<Transaction>,
+        pop_start: Instant,
+        storage: &mut BinaryHeap<Transaction>,
+        drain_waiters: &mut DrainWaiters,
+    ) {
+        if let Some(timing) = req.timing {
+            timing
+                .send(DrainTiming {
+                    wait: pop_start.saturating_duration_since(req.created),
+                    pop: pop_start.elapsed(),
+                })
+                .ok();
+        }
+        let ids: Vec<String> = if drain_waiters.is_empty() {
+            vec![]
+        } else {
+            drained.iter().map(|tx| tx.id.clone()).collect()
+        };
+        if let Err(drained) = req.send_back.send(drained) {
+            eprintln!(
+                "Warn! Queue has been drained but requester has hung up. Drained elements are re-queued."
+            );
+            storage.extend(drained);
+            return;
+        }
+        for reply in ids
+            .iter()
+            .filter_map(|id| drain_waiters.remove(id))
+            .flatten()
+        {
+            reply.send(()).ok();
+        }
     }
 
-    async fn handle_drain_waiting(
+    /// Pops transactions until their summed payload size reaches `target_bytes`, `req.n` transactions are popped
+    /// or the queue is empty.
+    fn handle_drain_bytes(
         req: DrainRequest,
         storage: &mut BinaryHeap<Transaction>,
-        drain_request_source: &mut sync::mpsc::Sender<DrainRequest>,
+        drain_waiters: &mut DrainWaiters,
+        target_bytes: usize,
     ) {
-        let timeout = match req.wait_strategy {
-            DrainStrategy::DrainMax => return,
-            DrainStrategy::WaitForN(timeout) => timeout,
+        let pop_start = Instant::now();
+        let mut drained = vec![];
+        let mut drained_bytes = 0;
+        while drained_bytes < target_bytes && drained.len() < req.n {
+            let Some(item) = storage.pop() else {
+                break;
+            };
+            drained_bytes += item.payload.len();
+            drained.push(item);
+        }
+
+        Self::send_back(req, drained, pop_start, storage, drain_waiters);
+    }
+
+    /// Checks whether the payloads of all stored transactions add up to at least `target_bytes`.
+    /// Stops counting as soon as the target is reached.
+    fn holds_bytes(storage: &BinaryHeap<Transaction>, target_bytes: usize) -> bool {
+        let mut bytes = 0;
+        storage.iter().any(|tx| {
+            bytes += tx.payload.len();
+            bytes >= target_bytes
+        }) || target_bytes == 0
+    }
+
+    /// Answers the waiting drain request `req` if the queue holds enough transactions or its timeout has passed.
+    /// Otherwise, the request is returned along with the instant it is to be checked again, which is after the
+    /// retry delay but no later than its timeout.
+    fn handle_drain_waiting(
+        mut req: DrainRequest,
+        storage: &mut BinaryHeap<Transaction>,
+        drain_waiters: &mut DrainWaiters,
+        cfg: &Cfg,
+        drain_requeues: &AtomicU64,
+    ) -> Option<WaitingDrain> {
+        let (ready, timeout) = match req.wait_strategy {
+            DrainStrategy::DrainMax => return None,
+            DrainStrategy::WaitForN(timeout) => (storage.len() >= req.n, timeout),
+            DrainStrategy::WaitForBytes {
+                target_bytes,
+                timeout,
+            } => (
+                storage.len() >= req.n || Self::holds_bytes(storage, target_bytes),
+                timeout,
+            ),
         };
 
         // stop waiting if there are enough elements in the queue or the timeout is reached
-        if (storage.len() >= req.n) || (Instant::now() + Self::DRAIN_RETRY_DELAY > timeout) {
-            Self::handle_drain_max(req, storage);
-            return;
+        let now = Instant::now();
+        if ready || now >= timeout {
+            match req.wait_strategy {
+                DrainStrategy::WaitForBytes { target_bytes, .. } => {
+                    Self::handle_drain_bytes(req, storage, drain_waiters, target_bytes)
+                }
+                _ => Self::handle_drain_max(req, storage, drain_waiters),
+            }
+            return None;
         }
-        // if there are not enough elements in the buffer, wait a little bit before issuing another drain request
-        tokio::time::sleep(Self::DRAIN_RETRY_DELAY).await;
-        drain_request_source
-            .send(req)
+        // if there are not enough elements in the buffer, check again after a little while. The worker keeps
+        // serving other requests in the meantime.
+        let delay = cfg.drain_retry_delay(req.retries).min(timeout - now);
+        req.retries = req.retries.saturating_add(1);
+        drain_requeues.fetch_add(1, Ordering::Relaxed);
+        Some((now + delay, req))
+    }
+
+    /// Sends `tx` to the submittance buffer and handles a full buffer according to the [`BackpressureStrategy`].
+    async fn submit_with_backpressure(&self, tx: Transaction) -> anyhow::Result<()> {
+        let tx = match self.channels.submittance_source.try_send(tx) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Closed(_)) => bail!("could not submit transaction to queue"),
+            Err(TrySendError::Full(tx)) => tx,
+        };
+        match self.backpressure {
+            BackpressureStrategy::Block => self
+                .channels
+                .submittance_source
+                .send(tx)
+                .await
+                .context("could not submit transaction to queue"),
+            BackpressureStrategy::DropNewest => {
+                eprintln!(
+                    "Warn! Submittance buffer is full, transaction {} is dropped.",
+                    tx.id
+                );
+                Ok(())
+            }
+            BackpressureStrategy::DropOldest => {
+                match self.channels.displacement_source.try_send(tx) {
+                    Ok(()) => Ok(()),
+                    Err(TrySendError::Closed(_)) => bail!("could not submit transaction to queue"),
+                    Err(TrySendError::Full(tx)) => bail!(
+                        "submittance and displacement buffers are full, transaction {} is rejected",
+                        tx.id
+                    ),
+                }
+            }
+            BackpressureStrategy::Reject => {
+                bail!(
+                    "submittance buffer is full, transaction {} is rejected",
+                    tx.id
+                )
+            }
+        }
+    }
+
+    /// Submits a JSON encoded [`Transaction`] without deserializing it on the caller's side.
+    /// Deserialization is deferred to the worker task, which keeps the latency of e.g. HTTP handlers low
+    /// when they merely forward requests.
+    ///
+    /// Returns as soon as the bytes are handed over to the worker. The returned [`RawVerdict`] resolves to an
+    /// error if the bytes do not hold a valid transaction. It may be dropped if the caller is not interested.
+    pub async fn submit_raw(&self, bytes: Bytes) -> anyhow::Result<RawVerdict> {
+        let (verdict, rx) = sync::oneshot::channel();
+        self.channels
+            .raw_submittance_source
+            .send((bytes, verdict))
+            .await
+            .context("could not submit raw transaction to queue")?;
+        Ok(rx)
+    }
+
+    /// Submits `tx` and resolves only once the worker has added it to the queue, whereas [`Mempool::submit`]
+    /// resolves as soon as `tx` is buffered in the submittance channel. A drain issued after this call returns
+    /// is therefore guaranteed to see `tx`.
+    ///
+    /// # Note
+    /// Confirmed submits use a separate buffer and wait for room in it regardless of the configured
+    /// [`BackpressureStrategy`].
+    pub async fn submit_confirmed(&self, tx: Transaction) -> anyhow::Result<()> {
+        let (ack, rx) = sync::oneshot::channel();
+        self.channels
+            .confirmed_submittance_source
+            .send((tx, ack))
             .await
-            .inspect_err(|_| {
-                eprintln!("Warn! Could not send drain request as channels are closed.")
+            .context("could not submit transaction to queue")?;
+        rx.await
+            .context("queue has stopped before the transaction was queued")
+    }
+
+    /// Drains up to `n` transactions like [`Mempool::drain`] with a timeout of `timeout_us`, but streams them as the
+    /// worker pops them instead of collecting them into a single vector first. This cuts the time to the first
+    /// transaction of large drains. Transactions submitted while the drain is pending are streamed as well.
+    ///
+    /// The stream ends once `n` transactions have been streamed or once the timeout fires. A `timeout_us` of `0`
+    /// streams the transactions queued at the time the worker receives the request only.
+    ///
+    /// `n` is clamped to [`Cfg::max_drain_batch`]. Only a small buffer of transactions is streamed ahead of the
+    /// receiver, the others stay in the queue until it catches up.
+    ///
+    /// # Note
+    /// Dropping the stream ends the drain and leaves the remaining transactions in the queue, but transactions
+    /// that have been streamed and not yet received are lost.
+    pub async fn drain_incremental(
+        &self,
+        n: usize,
+        timeout_us: u64,
+    ) -> anyhow::Result<impl Stream<Item = Transaction> + Unpin + use<>> {
+        let (sink, mut rx) = sync::mpsc::channel(n.clamp(1, INCREMENTAL_DRAIN_BUFFER));
+        self.channels
+            .incremental_drain_source
+            .send(IncrementalDrain {
+                remaining: n,
+                deadline: Instant::now() + Duration::from_micros(timeout_us),
+                sink,
             })
-            .ok();
+            .await
+            .context("could not send incremental drain request to queue")?;
+        Ok(futures::stream::poll_fn(move |cx| rx.poll_recv(cx)))
+    }
+
+    /// Returns a clone of the highest priority [`Transaction`] in the queue without removing it.
+    /// Returns `None` if the queue is empty.
+    pub async fn peek(&self) -> anyhow::Result<Option<Transaction>> {
+        let (reply, rx) = sync::oneshot::channel();
+        self.channels
+            .peek_request_source
+            .send(reply)
+            .await
+            .context("could not send peek request to queue")?;
+        rx.await.context("could not receive peek result from queue")
+    }
+
+    /// Removes the transaction with the given `id` from the queue and returns it.
+    /// Returns `None` if the queue does not hold a transaction with this id.
+    ///
+    /// # Note
+    /// The removal takes effect even if the caller stops waiting for the result.
+    pub async fn remove(&self, id: &str) -> anyhow::Result<Option<Transaction>> {
+        let (reply, rx) = sync::oneshot::channel();
+        self.channels
+            .remove_request_source
+            .send((id.to_string(), reply))
+            .await
+            .context("could not send remove request to queue")?;
+        rx.await
+            .context("could not receive remove result from queue")
+    }
+
+    /// Waits at maximum `timeout` for the transaction `id` to be drained, e.g. to confirm the inclusion of a
+    /// submitted transaction. Returns `true` if it has been drained in time and `false` otherwise.
+    ///
+    /// # Note
+    /// Only drains after the call are taken into account, so a transaction that has been drained before waits
+    /// for the full `timeout`.
+    pub async fn await_drained(&self, id: &str, timeout: Duration) -> anyhow::Result<bool> {
+        let drained = async {
+            let (reply, rx) = sync::oneshot::channel();
+            self.channels
+                .drain_waiter_source
+                .send((id.to_string(), reply))
+                .await
+                .context("could not send drain waiter to queue")?;
+            rx.await
+                .context("queue has stopped before the transaction was drained")
+        };
+        match tokio::time::timeout(timeout, drained).await {
+            Ok(result) => result.map(|()| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Resolves as soon as the queue holds at least one transaction. Resolves immediately if the queue
+    /// is not empty at the time of the call.
+    ///
+    /// Consumers can await this instead of polling an empty queue. As the emptiness of the queue is
+    /// tracked as a state rather than an event, no transition can be missed.
+    /// # Note
+    /// Another consumer might drain the queue between the notification and a subsequent drain.
+    pub async fn notified(&self) -> anyhow::Result<()> {
+        let mut non_empty = self.channels.non_empty_sink.clone();
+        non_empty
+            .wait_for(|non_empty| *non_empty)
+            .await
+            .context("queue has stopped")?;
+        Ok(())
+    }
+
+    /// Returns the number of submitted and drained transactions per second, averaged over the last 60 seconds.
+    pub fn recent_rates(&self) -> (f64, f64) {
+        self.rate_tracker.lock().unwrap().rates(Instant::now())
+    }
+
+    /// Returns how often waiting drain requests have been re-queued since the queue was started.
+    pub fn drain_requeues(&self) -> u64 {
+        self.drain_requeues.load(Ordering::Relaxed)
+    }
+
+    /// Stops servicing drain requests, e.g. for maintenance windows. Submits are still accepted, so the
+    /// queue keeps filling up.
+    ///
+    /// Drain requests issued while paused are not rejected but stay pending until [`Queue::resume`] is called.
+    /// Once the request buffer is full, further drain requests block their senders. Waiting drain requests
+    /// whose timeout passes while paused are serviced right after resuming with whatever the queue holds.
+    pub fn pause(&self) {
+        self.pause_switch.paused.store(true, Ordering::Release);
+        self.pause_switch.toggled.notify_one();
+    }
+
+    /// Resumes servicing drain requests after [`Queue::pause`]. Pending drain requests are serviced in order.
+    pub fn resume(&self) {
+        self.pause_switch.paused.store(false, Ordering::Release);
+        self.pause_switch.toggled.notify_one();
+    }
+
+    /// Returns `false` once the worker task has stopped, e.g. after an idle shutdown (see [`Cfg::idle_shutdown`])
+    /// or after all submitters hung up.
+    pub fn is_running(&self) -> bool {
+        !self.runner_handle.is_finished()
     }
 
     /// Stops the manager task of the queue and drops all included items
@@ -154,23 +915,61 @@ impl Queue {
 
 struct InternalChannels {
     submittance_sink: sync::mpsc::Receiver<Transaction>,
+    raw_submittance_sink: sync::mpsc::Receiver<RawSubmittance>,
+    confirmed_submittance_sink: sync::mpsc::Receiver<ConfirmedSubmittance>,
     drain_request_sink: sync::mpsc::Receiver<DrainRequest>,
-    drain_request_source: sync::mpsc::Sender<DrainRequest>,
+    incremental_drain_sink: sync::mpsc::Receiver<IncrementalDrain>,
+    peek_request_sink: sync::mpsc::Receiver<PeekRequest>,
+    remove_request_sink: sync::mpsc::Receiver<RemoveRequest>,
+    flush_request_sink: sync::mpsc::Receiver<FlushRequest>,
+    displacement_sink: sync::mpsc::Receiver<Transaction>,
+    drain_waiter_sink: sync::mpsc::Receiver<DrainWaiter>,
+    non_empty_source: sync::watch::Sender<bool>,
 }
 
 fn prepare_channels(cfg: &Cfg) -> (Channels, InternalChannels) {
     let (submittance_source, submittance_sink) = sync::mpsc::channel(cfg.submittance_back_pressure);
-    let (drain_request_source, drain_request_sink) = sync::mpsc::channel(10);
+    let (raw_submittance_source, raw_submittance_sink) =
+        sync::mpsc::channel(cfg.submittance_back_pressure);
+    let (confirmed_submittance_source, confirmed_submittance_sink) =
+        sync::mpsc::channel(cfg.submittance_back_pressure);
+    let (drain_request_source, drain_request_sink) = sync::mpsc::channel(cfg.drain_request_buffer);
+    let (incremental_drain_source, incremental_drain_sink) =
+        sync::mpsc::channel(cfg.drain_request_buffer);
+    let (peek_request_source, peek_request_sink) = sync::mpsc::channel(10);
+    let (remove_request_source, remove_request_sink) = sync::mpsc::channel(10);
+    let (flush_request_source, flush_request_sink) = sync::mpsc::channel(10);
+    let (displacement_source, displacement_sink) =
+        sync::mpsc::channel(cfg.submittance_back_pressure);
+    let (drain_waiter_source, drain_waiter_sink) = sync::mpsc::channel(10);
+    let (non_empty_source, non_empty_sink) = sync::watch::channel(false);
 
     (
         Channels {
             submittance_source,
-            drain_request_source: drain_request_source.clone(),
+            raw_submittance_source,
+            confirmed_submittance_source,
+            drain_request_source,
+            incremental_drain_source,
+            peek_request_source,
+            remove_request_source,
+            flush_request_source,
+            displacement_source,
+            drain_waiter_source,
+            non_empty_sink,
         },
         InternalChannels {
             submittance_sink,
+            raw_submittance_sink,
+            confirmed_submittance_sink,
             drain_request_sink,
-            drain_request_source,
+            incremental_drain_sink,
+            peek_request_sink,
+            remove_request_sink,
+            flush_request_sink,
+            displacement_sink,
+            drain_waiter_sink,
+            non_empty_source,
         },
     )
 }
@@ -183,13 +982,22 @@ mod tests {
     use super::*;
     use mempool::Transaction;
 
-    fn setup_queue() -> Queue {
+    fn setup_cfg() -> Cfg {
         // Small back pressure buffer
-        let cfg = Cfg {
+        Cfg {
             capacity: 10,
             submittance_back_pressure: 10,
-        };
-        Queue::start(cfg)
+            max_drain_batch: 5,
+            drain_request_buffer: 10,
+            drain_retry_delay: Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: BackpressureStrategy::Block,
+        }
+    }
+
+    fn setup_queue() -> Queue {
+        Queue::start(setup_cfg())
     }
 
     #[tokio::test]
@@ -202,7 +1010,7 @@ mod tests {
 
         let tx2_ident = Transaction::with_empty_load("tx2", 200, 2);
         let tx3_ident = Transaction::with_empty_load("tx3", 100, 0);
-        
+
         queue.submit(tx1).await.unwrap();
         queue.submit(tx2).await.unwrap();
         queue.submit(tx3).await.unwrap();
@@ -216,6 +1024,133 @@ mod tests {
         queue.stop();
     }
 
+    #[tokio::test]
+    async fn test_equal_priorities_drain_by_id() {
+        let queue = setup_queue();
+
+        // -- submit in a scrambled order, 3 is coprime to 10
+        for i in 0..10 {
+            let id = format!("tx{}", i * 3 % 10);
+            queue
+                .submit_confirmed(Transaction::with_empty_load(&id, 100, 1))
+                .await
+                .unwrap();
+        }
+
+        let mut ids = vec![];
+        while ids.len() < 10 {
+            let drained = queue.drain(5, 0).await.unwrap();
+            assert!(!drained.is_empty());
+            ids.extend(drained.into_iter().map(|tx| tx.id));
+        }
+        let expected: Vec<String> = (0..10).map(|i| format!("tx{i}")).collect();
+        assert_eq!(ids, expected);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_submit_confirmed_is_drainable_without_sleep() {
+        let queue = setup_queue();
+
+        for round in 0..100 {
+            let id = format!("tx{round}");
+            queue
+                .submit_confirmed(Transaction::with_empty_load(&id, 100, round))
+                .await
+                .unwrap();
+            let drained = queue.drain(1, 0).await.unwrap();
+            assert_eq!(drained.len(), 1, "round {round}");
+            assert_eq!(drained[0].id, id);
+        }
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_incremental_streams_before_batch_is_complete() {
+        use futures::StreamExt;
+
+        let queue = setup_queue();
+        queue
+            .submit_confirmed(Transaction::with_empty_load("tx_first", 100, 1))
+            .await
+            .unwrap();
+
+        let start = time::Instant::now();
+        let mut stream = queue.drain_incremental(1_000, 200_000).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().id, "tx_first");
+        // -- a regular drain of 1_000 transactions would only return at the timeout
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        // -- transactions submitted while the drain is pending are streamed as well
+        queue
+            .submit_confirmed(Transaction::with_empty_load("tx_second", 50, 2))
+            .await
+            .unwrap();
+        assert_eq!(stream.next().await.unwrap().id, "tx_second");
+
+        // -- the stream ends once the timeout fires
+        assert!(stream.next().await.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_incremental_keeps_untaken_transactions_queued() {
+        use futures::StreamExt;
+
+        let queue = Queue::start(Cfg {
+            capacity: 200,
+            submittance_back_pressure: 200,
+            max_drain_batch: 150,
+            ..setup_cfg()
+        });
+        for i in 0..200 {
+            queue
+                .submit_confirmed(Transaction::with_empty_load(&format!("tx{i}"), i, 1))
+                .await
+                .unwrap();
+        }
+
+        // -- the drain is clamped to `max_drain_batch`, and only the stream buffer is filled up front
+        let mut stream = queue.drain_incremental(1_000, 1_000_000).await.unwrap();
+        time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(queue.len().await.unwrap(), 200 - INCREMENTAL_DRAIN_BUFFER);
+
+        let mut gas_prices = vec![];
+        while let Some(tx) = stream.next().await {
+            gas_prices.push(tx.gas_price);
+        }
+        assert_eq!(gas_prices, (50..200).rev().collect::<Vec<_>>());
+        assert_eq!(queue.len().await.unwrap(), 50);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_timing_separates_wait_from_pop() {
+        let queue = setup_queue();
+
+        let delayed_queue = queue.clone();
+        tokio::spawn(async move {
+            time::sleep(Duration::from_millis(50)).await;
+            delayed_queue
+                .submit(Transaction::with_empty_load("tx_delayed", 150, 5))
+                .await
+                .unwrap();
+        });
+
+        let (drained, timing) = queue.drain_timed(1, 1_000_000).await.unwrap();
+        assert_eq!(drained.len(), 1);
+        let timing = timing.unwrap();
+        assert!(timing.wait >= Duration::from_millis(40), "{timing:?}");
+        assert!(timing.pop < Duration::from_millis(5), "{timing:?}");
+
+        queue.stop();
+    }
+
     #[tokio::test]
     async fn test_drain_waiting_timeout_returns_partial_or_empty() {
         let queue = setup_queue();
@@ -253,4 +1188,659 @@ mod tests {
 
         queue.stop();
     }
+
+    #[tokio::test]
+    async fn test_peek_returns_highest_priority_without_removing() {
+        let queue = setup_queue();
+        assert_eq!(queue.peek().await.unwrap(), None);
+
+        queue
+            .submit(Transaction::with_empty_load("tx1", 100, 1))
+            .await
+            .unwrap();
+        queue
+            .submit(Transaction::with_empty_load("tx2", 200, 2))
+            .await
+            .unwrap();
+        queue
+            .submit(Transaction::with_empty_load("tx3", 200, 0))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        let peeked = queue.peek().await.unwrap().unwrap();
+        assert_eq!(peeked.id, "tx3");
+        assert_eq!(queue.peek().await.unwrap().unwrap().id, "tx3");
+
+        let drained = queue.drain(3, 0).await.unwrap();
+        assert_eq!(drained.len(), 3);
+        assert_eq!(drained[0], peeked);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_waiting_for_bytes() {
+        let queue = setup_queue();
+        for (i, gas_price) in [10, 40, 30, 20].into_iter().enumerate() {
+            queue
+                .submit(Transaction::new(
+                    &format!("tx{i}"),
+                    gas_price,
+                    i as u64,
+                    vec![0; 100],
+                ))
+                .await
+                .unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        let (channels, runner_handle) = queue.detach_channels();
+        let (_submittance_source, drain_request_source) = channels.into_parts();
+        let (req, rx) = DrainRequest::new_with_byte_target(250, 10_000_000);
+
+        let start = time::Instant::now();
+        drain_request_source.send(req).await.unwrap();
+        let drained = rx.await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let drained_bytes: usize = drained.iter().map(|tx| tx.payload.len()).sum();
+        assert_eq!(drained_bytes, 300);
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["tx1", "tx2", "tx3"]);
+
+        runner_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_drain_waiting_for_bytes_is_capped_at_max_drain_batch() {
+        let queue = setup_queue();
+        for i in 0..10 {
+            queue
+                .submit_confirmed(Transaction::new(&format!("tx{i}"), 10, i, vec![0; 100]))
+                .await
+                .unwrap();
+        }
+
+        let (channels, runner_handle) = queue.detach_channels();
+        let (_submittance_source, drain_request_source) = channels.into_parts();
+        let (req, rx) = DrainRequest::new_with_byte_target(10_000, 10_000_000);
+
+        // -- a full batch is returned right away instead of waiting for the byte target
+        let start = time::Instant::now();
+        drain_request_source.send(req).await.unwrap();
+        let drained = rx.await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(drained.len(), setup_cfg().max_drain_batch);
+
+        runner_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_drained_items_are_requeued_when_requester_hung_up() {
+        let queue = setup_queue();
+        queue
+            .submit(Transaction::with_empty_load("tx1", 100, 1))
+            .await
+            .unwrap();
+        queue
+            .submit(Transaction::with_empty_load("tx2", 200, 2))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        let (req, rx) = DrainRequest::new_with_timeout(2, 0);
+        drop(rx);
+        queue.channels.drain_request_source.send(req).await.unwrap();
+
+        let drained = queue.drain(2, 0).await.unwrap();
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["tx2", "tx1"]);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_is_clamped_to_max_batch() {
+        let queue = setup_queue();
+        for i in 0..8 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        assert_eq!(queue.drain(1_000_000_000, 0).await.unwrap().len(), 5);
+        // -- the remaining queue is smaller than the cap
+        assert_eq!(queue.drain(1_000_000_000, 0).await.unwrap().len(), 3);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_notified_wakes_consumer_on_first_submit() {
+        let queue = setup_queue();
+
+        let consumer_queue = queue.clone();
+        let consumer = tokio::spawn(async move {
+            consumer_queue.notified().await.unwrap();
+            consumer_queue.drain(1, 0).await.unwrap()
+        });
+
+        time::sleep(Duration::from_millis(10)).await;
+        assert!(!consumer.is_finished());
+
+        queue
+            .submit(Transaction::with_empty_load("tx1", 100, 1))
+            .await
+            .unwrap();
+        let drained = time::timeout(Duration::from_secs(1), consumer)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(drained[0].id, "tx1");
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_submit_raw_drains_deserialized_transaction() {
+        let queue = setup_queue();
+        let tx = Transaction::new("tx1", 100, 1, vec![1, 2, 3]);
+        let bytes = Bytes::from(serde_json::to_vec(&tx).unwrap());
+
+        let verdict = queue.submit_raw(bytes).await.unwrap();
+        verdict.await.unwrap().unwrap();
+
+        let drained = queue.drain(1, 0).await.unwrap();
+        assert_eq!(drained, [tx]);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_submit_raw_rejects_malformed_bytes() {
+        let queue = setup_queue();
+
+        let verdict = queue
+            .submit_raw(Bytes::from_static(b"{\"id\": \"tx1\""))
+            .await
+            .unwrap();
+        assert!(verdict.await.unwrap().is_err());
+
+        // -- the worker keeps serving requests
+        assert!(queue.drain(1, 0).await.unwrap().is_empty());
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_paused_queue_services_drains_after_resume() {
+        let queue = setup_queue();
+        queue.pause();
+
+        queue
+            .submit(Transaction::with_empty_load("tx1", 100, 1))
+            .await
+            .unwrap();
+
+        let consumer_queue = queue.clone();
+        let consumer = tokio::spawn(async move { consumer_queue.drain(1, 0).await.unwrap() });
+
+        time::sleep(Duration::from_millis(20)).await;
+        assert!(!consumer.is_finished());
+
+        queue.resume();
+        let drained = time::timeout(Duration::from_secs(1), consumer)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(drained[0].id, "tx1");
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_pausing_a_running_worker_holds_back_drains() {
+        let queue = setup_queue();
+        // -- let the worker serve a drain first, so it is parked with the drain branch armed
+        queue
+            .submit_confirmed(Transaction::with_empty_load("tx0", 100, 0))
+            .await
+            .unwrap();
+        assert_eq!(queue.drain(1, 0).await.unwrap().len(), 1);
+        queue
+            .submit_confirmed(Transaction::with_empty_load("tx1", 100, 1))
+            .await
+            .unwrap();
+
+        queue.pause();
+        let consumer_queue = queue.clone();
+        let consumer = tokio::spawn(async move { consumer_queue.drain(1, 0).await.unwrap() });
+
+        time::sleep(Duration::from_millis(20)).await;
+        assert!(!consumer.is_finished());
+
+        queue.resume();
+        let drained = time::timeout(Duration::from_secs(1), consumer)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(drained[0].id, "tx1");
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_recent_rates_count_submits_and_drains() {
+        let queue = setup_queue();
+        assert_eq!(queue.recent_rates(), (0.0, 0.0));
+
+        for i in 0..4 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+        time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(queue.drain(3, 0).await.unwrap().len(), 3);
+
+        let (submit_rate, drain_rate) = queue.recent_rates();
+        assert!(submit_rate > 0.0);
+        assert!((drain_rate / submit_rate - 0.75).abs() < 1e-9);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_request_buffer_limits_pending_drains() {
+        async fn pending_drains(drain_request_buffer: usize) -> usize {
+            let queue = Queue::start(Cfg {
+                capacity: 10,
+                submittance_back_pressure: 10,
+                max_drain_batch: 5,
+                drain_request_buffer,
+                drain_retry_delay: Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+                max_drain_retry_delay: Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+                idle_shutdown: None,
+                backpressure: BackpressureStrategy::Block,
+            });
+            queue.pause();
+
+            let mut pending = 0;
+            let mut receivers = vec![];
+            while pending < 100 {
+                let (req, rx) = DrainRequest::new_with_timeout(1, 0);
+                if queue.channels.drain_request_source.try_send(req).is_err() {
+                    break;
+                }
+                receivers.push(rx);
+                pending += 1;
+            }
+            queue.stop();
+            pending
+        }
+
+        assert_eq!(pending_drains(2).await, 2);
+        assert_eq!(pending_drains(50).await, 50);
+    }
+
+    #[tokio::test]
+    async fn test_remove_by_id() {
+        let queue = setup_queue();
+        for (id, gas_price) in [("tx1", 10), ("tx2", 30), ("tx3", 20)] {
+            queue
+                .submit(Transaction::with_empty_load(id, gas_price, 0))
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        assert_eq!(queue.remove("tx2").await.unwrap().unwrap().id, "tx2");
+        assert_eq!(queue.remove("tx2").await.unwrap(), None);
+        // -- removed transactions are not drained
+        assert_eq!(queue.recent_rates().1, 0.0);
+
+        let drained = queue.drain(3, 0).await.unwrap();
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["tx3", "tx1"]);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_retry_backoff_reduces_requeues() {
+        async fn requeues_until_drained(max_drain_retry_delay: Duration) -> u64 {
+            let queue = Queue::start(Cfg {
+                capacity: 10,
+                submittance_back_pressure: 10,
+                max_drain_batch: 5,
+                drain_request_buffer: 10,
+                drain_retry_delay: Duration::from_millis(1),
+                max_drain_retry_delay,
+                idle_shutdown: None,
+                backpressure: BackpressureStrategy::Block,
+            });
+
+            let delayed_queue = queue.clone();
+            tokio::spawn(async move {
+                time::sleep(Duration::from_millis(60)).await;
+                for i in 0..2 {
+                    delayed_queue
+                        .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                        .await
+                        .unwrap();
+                }
+            });
+
+            let drained = queue.drain(2, 10_000_000).await.unwrap();
+            assert_eq!(drained.len(), 2);
+            let requeues = queue.drain_requeues();
+            queue.stop();
+            requeues
+        }
+
+        let constant = requeues_until_drained(Duration::from_millis(1)).await;
+        let backoff = requeues_until_drained(Duration::from_millis(16)).await;
+        assert!(backoff > 0);
+        assert!(backoff < constant, "{backoff} >= {constant}");
+    }
+
+    #[test]
+    fn test_drain_retry_delay_grows_up_to_cap() {
+        let cfg = Cfg {
+            capacity: 10,
+            submittance_back_pressure: 10,
+            max_drain_batch: 5,
+            drain_request_buffer: 10,
+            drain_retry_delay: Duration::from_micros(50),
+            max_drain_retry_delay: Duration::from_micros(300),
+            idle_shutdown: None,
+            backpressure: BackpressureStrategy::Block,
+        };
+        let delays: Vec<u128> = (0..5)
+            .map(|retries| cfg.drain_retry_delay(retries).as_micros())
+            .collect();
+        assert_eq!(delays, [50, 100, 200, 300, 300]);
+        assert_eq!(cfg.drain_retry_delay(u32::MAX).as_micros(), 300);
+    }
+
+    #[tokio::test]
+    async fn test_sub_millisecond_timeout_drain_waits() {
+        let queue = setup_queue();
+
+        let start = time::Instant::now();
+        let drained = queue.drain(1, 800).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_micros(800));
+        assert!(drained.is_empty());
+        assert!(queue.drain_requeues() > 0);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_zero_timeout_drain_does_not_wait() {
+        let queue = setup_queue();
+        for i in 0..3 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+        time::sleep(Duration::from_millis(1)).await;
+
+        let start = time::Instant::now();
+        let drained = queue.drain(5, 0).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(5));
+        assert_eq!(drained.len(), 3);
+        assert_eq!(queue.drain_requeues(), 0);
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_flush_resolves_once_drained() {
+        let queue = setup_queue();
+        for i in 0..20 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+
+        let consumer_queue = queue.clone();
+        let consumer = tokio::spawn(async move {
+            loop {
+                consumer_queue.drain(3, 0).await.unwrap();
+                time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+
+        time::timeout(Duration::from_secs(1), queue.flush())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(queue.peek().await.unwrap(), None);
+
+        consumer.abort();
+        queue.stop();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_consumers_drain_each_transaction_once() {
+        use std::collections::HashSet;
+        use std::sync::atomic::AtomicUsize;
+
+        const TOTAL: usize = 10_000;
+        let queue = setup_queue();
+        let drained_count = Arc::new(AtomicUsize::new(0));
+
+        let consumers: Vec<_> = (0..10)
+            .map(|_| {
+                let queue = queue.clone();
+                let drained_count = drained_count.clone();
+                tokio::spawn(async move {
+                    let mut drained = vec![];
+                    while drained_count.load(Ordering::Relaxed) < TOTAL {
+                        let batch = queue.drain(3, 1_000).await.unwrap();
+                        drained_count.fetch_add(batch.len(), Ordering::Relaxed);
+                        drained.extend(batch);
+                    }
+                    drained
+                })
+            })
+            .collect();
+
+        for i in 0..TOTAL as u64 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i % 100, i))
+                .await
+                .unwrap();
+        }
+
+        let mut ids = HashSet::with_capacity(TOTAL);
+        for consumer in consumers {
+            let drained = time::timeout(Duration::from_secs(10), consumer)
+                .await
+                .unwrap()
+                .unwrap();
+            for tx in drained {
+                assert!(ids.insert(tx.id.clone()), "{} drained twice", tx.id);
+            }
+        }
+        assert_eq!(ids.len(), TOTAL);
+        assert!((0..TOTAL).all(|i| ids.contains(&format!("tx{i}"))));
+
+        queue.stop();
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_submit_and_drain_emit_spans() {
+        use mempool::test::tracing::RecordingSubscriber;
+
+        let subscriber = RecordingSubscriber::default();
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+        let queue = setup_queue();
+        queue
+            .submit(Transaction::with_empty_load("tx", 10, 1))
+            .await
+            .unwrap();
+        time::sleep(Duration::from_millis(1)).await;
+        assert_eq!(queue.drain(3, 0).await.unwrap().len(), 1);
+
+        let submits = subscriber.spans("submit");
+        assert_eq!(submits.len(), 1);
+        assert_eq!(submits[0].fields["id"], "tx");
+        assert!(submits[0].fields.contains_key("duration_us"));
+
+        let drains = subscriber.spans("drain");
+        assert_eq!(drains.len(), 1);
+        assert_eq!(drains[0].fields["n"], "3");
+        assert_eq!(drains[0].fields["timeout_us"], "0");
+        assert_eq!(drains[0].fields["batch_size"], "1");
+        assert!(drains[0].fields.contains_key("duration_us"));
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_drain_batches_chunks_single_drain() {
+        let queue = Queue::start(Cfg {
+            max_drain_batch: 100,
+            ..setup_cfg()
+        });
+        for i in 0..25 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+        time::sleep(Duration::from_millis(1)).await;
+
+        let batches = queue.drain_batches(10, 3, 0).await.unwrap();
+        let sizes: Vec<_> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [10, 10, 5]);
+        let gas_prices: Vec<_> = batches.concat().iter().map(|tx| tx.gas_price).collect();
+        assert!(gas_prices.windows(2).all(|w| w[0] > w[1]));
+        assert!(queue.drain_batches(0, 3, 0).await.is_err());
+
+        queue.stop();
+    }
+
+    #[tokio::test]
+    async fn test_idle_shutdown_stops_inactive_worker() {
+        let queue = Queue::start(Cfg {
+            idle_shutdown: Some(Duration::from_millis(100)),
+            ..setup_cfg()
+        });
+
+        for i in 0..10 {
+            time::sleep(Duration::from_millis(20)).await;
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+            assert!(queue.is_running());
+        }
+
+        time::sleep(Duration::from_millis(300)).await;
+        assert!(!queue.is_running());
+        assert!(
+            queue
+                .submit(Transaction::with_empty_load("late", 1, 1))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backpressure_strategies_on_full_buffer() {
+        use std::{
+            future::Future,
+            task::{Context, Waker},
+        };
+
+        /// Fills the submittance buffer of a queue using `backpressure` and returns the queue along with an
+        /// overflowing transaction. The worker task does not run before the test yields.
+        async fn saturated_queue(backpressure: BackpressureStrategy) -> (Queue, Transaction) {
+            let queue = Queue::start(Cfg {
+                submittance_back_pressure: 1,
+                backpressure,
+                ..setup_cfg()
+            });
+            queue
+                .submit(Transaction::with_empty_load("oldest", 10, 0))
+                .await
+                .unwrap();
+            time::sleep(Duration::from_millis(1)).await;
+            queue
+                .submit(Transaction::with_empty_load("buffered", 10, 1))
+                .await
+                .unwrap();
+            (queue, Transaction::with_empty_load("newest", 10, 2))
+        }
+
+        async fn drained_ids(queue: &Queue) -> Vec<String> {
+            time::sleep(Duration::from_millis(1)).await;
+            let drained = queue.drain(10, 0).await.unwrap();
+            drained.into_iter().map(|tx| tx.id).collect()
+        }
+
+        let (queue, newest) = saturated_queue(BackpressureStrategy::Block).await;
+        let mut submit = std::pin::pin!(queue.submit(newest));
+        let poll = submit
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()));
+        assert!(poll.is_pending());
+        submit.await.unwrap();
+        assert_eq!(drained_ids(&queue).await, ["oldest", "buffered", "newest"]);
+
+        let (queue, newest) = saturated_queue(BackpressureStrategy::DropNewest).await;
+        queue.submit(newest).await.unwrap();
+        assert_eq!(drained_ids(&queue).await, ["oldest", "buffered"]);
+
+        let (queue, newest) = saturated_queue(BackpressureStrategy::DropOldest).await;
+        queue.submit(newest).await.unwrap();
+        // -- the displacement buffer is as small as the submittance buffer and full as well
+        let overflow = Transaction::with_empty_load("overflow", 10, 3);
+        assert!(queue.submit(overflow).await.is_err());
+        assert_eq!(drained_ids(&queue).await, ["buffered", "newest"]);
+
+        let (queue, newest) = saturated_queue(BackpressureStrategy::Reject).await;
+        assert!(queue.submit(newest).await.is_err());
+        assert_eq!(drained_ids(&queue).await, ["oldest", "buffered"]);
+    }
+
+    #[tokio::test]
+    async fn test_await_drained_resolves_once_drained() {
+        let queue = setup_queue();
+        queue
+            .submit(Transaction::with_empty_load("tx", 10, 1))
+            .await
+            .unwrap();
+
+        let consumer = queue.clone();
+        tokio::spawn(async move {
+            time::sleep(Duration::from_millis(10)).await;
+            consumer.drain(1, 0).await.unwrap();
+        });
+
+        assert!(
+            queue
+                .await_drained("tx", Duration::from_secs(1))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !queue
+                .await_drained("unknown", Duration::from_millis(10))
+                .await
+                .unwrap()
+        );
+
+        queue.stop();
+    }
 }
@@ -1,10 +1,14 @@
+use drain_strategy::DrainTiming;
 use mempool::Transaction;
 
 mod channels;
 mod locks;
 
 pub use channels::drain_strategy;
-pub use channels::stress::{HttpFacade, StressTestCfg, run_stress_test};
+pub use channels::stress::{
+    AutoTuneCfg, AutoTuneReport, ClientCfg, ClientPool, HttpFacade, StressTestCfg,
+    StressTestSummary, auto_tune, run_stress_test,
+};
 pub use channels::worker;
 pub use locks::LockedQueue;
 
@@ -12,4 +16,54 @@ pub use locks::LockedQueue;
 pub trait Mempool: Send + Sync + 'static {
     async fn submit(&self, tx: Transaction) -> anyhow::Result<()>;
     async fn drain(&self, n: usize, timeout_us: u64) -> anyhow::Result<Vec<Transaction>>;
+
+    /// Drains like [`Mempool::drain`] and additionally reports how the time of the drain splits into the wait for
+    /// transactions and the popping of them, e.g. to attribute drain latencies in benchmarks.
+    /// The default implementation reports no timing.
+    async fn drain_timed(
+        &self,
+        n: usize,
+        timeout_us: u64,
+    ) -> anyhow::Result<(Vec<Transaction>, Option<DrainTiming>)> {
+        Ok((self.drain(n, timeout_us).await?, None))
+    }
+
+    /// Drains up to `batch_size * num_batches` transactions with a single drain request and splits them into
+    /// batches of `batch_size` in priority order. Only the last batch may hold fewer transactions.
+    /// Pools that limit the size of a drain, e.g. the [`worker::Queue`], apply their limit to the total.
+    async fn drain_batches(
+        &self,
+        batch_size: usize,
+        num_batches: usize,
+        timeout_us: u64,
+    ) -> anyhow::Result<Vec<Vec<Transaction>>> {
+        anyhow::ensure!(batch_size > 0, "batch size must be at least 1");
+        let mut drained = self
+            .drain(batch_size.saturating_mul(num_batches), timeout_us)
+            .await?
+            .into_iter();
+        let batches = std::iter::from_fn(|| {
+            let batch: Vec<_> = drained.by_ref().take(batch_size).collect();
+            (!batch.is_empty()).then_some(batch)
+        });
+        Ok(batches.collect())
+    }
+
+    /// Resolves once all transactions submitted before the call have left the pool, e.g. to synchronize tests.
+    /// This is only meaningful while consumers are actively draining, otherwise it never resolves.
+    /// The default implementation returns an error for pools that do not support flushing.
+    async fn flush(&self) -> anyhow::Result<()> {
+        anyhow::bail!("flush is not supported by this mempool")
+    }
+
+    /// Number of transactions currently held by the pool.
+    /// The default implementation returns an error for pools that cannot report their length.
+    async fn len(&self) -> anyhow::Result<usize> {
+        anyhow::bail!("len is not supported by this mempool")
+    }
+
+    /// Returns `true` if the pool holds no transactions, see [`Mempool::len`].
+    async fn is_empty(&self) -> anyhow::Result<bool> {
+        Ok(self.len().await? == 0)
+    }
 }
@@ -11,6 +11,10 @@ pub struct LockedQueue {
 }
 
 impl LockedQueue {
+    /// Initial and maximum delay between two checks of [`Mempool::flush`].
+    const FLUSH_POLL_DELAY: (Duration, Duration) =
+        (Duration::from_micros(10), Duration::from_millis(1));
+
     pub fn new(capacity: usize) -> Self {
         Self {
             storage: Arc::new(Mutex::new(BinaryHeap::with_capacity(capacity))),
@@ -53,4 +57,73 @@ impl Mempool for LockedQueue {
 
         Ok(drained_items)
     }
+
+    async fn len(&self) -> anyhow::Result<usize> {
+        Ok(self.storage.lock().await.len())
+    }
+
+    /// Polls the length of the storage with an exponential backoff until it is empty.
+    async fn flush(&self) -> anyhow::Result<()> {
+        let (mut delay, max_delay) = Self::FLUSH_POLL_DELAY;
+        while !self.storage.lock().await.is_empty() {
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(max_delay);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn equal_priorities_drain_by_id() {
+        let queue = LockedQueue::new(10);
+        // -- submit in a scrambled order, 3 is coprime to 10
+        for i in 0..10 {
+            let id = format!("tx{}", i * 3 % 10);
+            queue
+                .submit(Transaction::with_empty_load(&id, 100, 1))
+                .await
+                .unwrap();
+        }
+
+        let ids: Vec<String> = queue
+            .drain(10, 1_000)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tx| tx.id)
+            .collect();
+        let expected: Vec<String> = (0..10).map(|i| format!("tx{i}")).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[tokio::test]
+    async fn flush_resolves_once_drained() {
+        let queue = LockedQueue::new(10);
+        for i in 0..20 {
+            queue
+                .submit(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+
+        let consumer_queue = queue.clone();
+        let consumer = tokio::spawn(async move {
+            loop {
+                consumer_queue.drain(3, 1_000).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+
+        tokio::time::timeout(Duration::from_secs(1), queue.flush())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(queue.storage.lock().await.is_empty());
+
+        consumer.abort();
+    }
 }
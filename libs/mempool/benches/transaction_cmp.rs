@@ -0,0 +1,5 @@
+use criterion::{criterion_group, criterion_main};
+use mempool::test::bench::bench_transaction_cmp;
+
+criterion_group!(benches, bench_transaction_cmp);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use crate::Transaction;
+
+/// Ids of the transactions that entered and left a pool between two snapshots, see [`diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PoolDiff {
+    /// Ids that are contained in the later snapshot only.
+    pub added: HashSet<String>,
+    /// Ids that are contained in the earlier snapshot only.
+    pub removed: HashSet<String>,
+}
+
+/// Compares two snapshots of a pool by the ids of their transactions, e.g. to inspect the churn of a pool
+/// during a time window. The order within the snapshots does not matter, and transactions contained in both
+/// snapshots are not reported, even if they changed in between.
+pub fn diff(before: &[Transaction], after: &[Transaction]) -> PoolDiff {
+    let ids =
+        |txs: &[Transaction]| -> HashSet<String> { txs.iter().map(|tx| tx.id.clone()).collect() };
+    let before = ids(before);
+    let after = ids(after);
+    PoolDiff {
+        added: after.difference(&before).cloned().collect(),
+        removed: before.difference(&after).cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_added_and_removed_ids() {
+        let snapshot = |ids: &[&str]| -> Vec<Transaction> {
+            ids.iter()
+                .map(|id| Transaction::with_empty_load(id, 1, 0))
+                .collect()
+        };
+        let before = snapshot(&["a", "b", "c", "d"]);
+        let after = snapshot(&["e", "c", "a", "f"]);
+
+        let diff = diff(&before, &after);
+
+        let set =
+            |ids: &[&str]| -> HashSet<String> { ids.iter().map(|id| id.to_string()).collect() };
+        assert_eq!(diff.added, set(&["e", "f"]));
+        assert_eq!(diff.removed, set(&["b", "d"]));
+        assert_eq!(super::diff(&after, &after), PoolDiff::default());
+    }
+}
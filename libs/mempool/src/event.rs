@@ -0,0 +1,15 @@
+use crate::RejectReason;
+
+/// Lifecycle event of a [`Transaction`](crate::Transaction) in a [`Mempool`](crate::Mempool),
+/// identified by the transaction's id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MempoolEvent {
+    /// The transaction has been accepted into the pool.
+    Submitted(String),
+    /// The transaction has been drained from the pool.
+    Drained(String),
+    /// The transaction has been removed to make room for another one.
+    Evicted(String),
+    /// The transaction has not been accepted into the pool.
+    Rejected(String, RejectReason),
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use crate::{Mempool, Transaction};
+
+/// [`Mempool`] that spills transactions to a `secondary` pool whenever the `primary` pool rejects them,
+/// e.g. because it is full.
+///
+/// Drains are served from the `primary` pool first. Only if it cannot provide `n` transactions, the
+/// remainder is taken from the `secondary` pool. The combined batch is sorted by priority.
+#[derive(Debug)]
+pub struct FallbackPool<P: Mempool, S: Mempool> {
+    primary: P,
+    secondary: S,
+}
+
+impl<P: Mempool, S: Mempool> FallbackPool<P, S> {
+    pub fn new(primary: P, secondary: S) -> Self {
+        Self { primary, secondary }
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+}
+
+impl<P: Mempool, S: Mempool> Mempool for FallbackPool<P, S> {
+    fn submit(&self, tx: Transaction) {
+        if let Err(tx) = self.primary.try_submit(tx) {
+            self.secondary.submit(*tx);
+        }
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let mut drained = self.primary.drain(n);
+        if drained.len() < n {
+            drained.append(&mut self.secondary.drain(n - drained.len()));
+            drained.sort_by(|a, b| b.cmp(a));
+        }
+        drained
+    }
+
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        self.primary
+            .try_submit(tx)
+            .or_else(|tx| self.secondary.try_submit(*tx))
+    }
+
+    fn estimated_drain_delay(&self, n: usize) -> Duration {
+        self.primary.estimated_drain_delay(n)
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        self.primary.get(id).or_else(|| self.secondary.get(id))
+    }
+
+    /// Only the `primary` reserves capacity, the `secondary` merely takes its overflow.
+    fn reserve(&self, additional: usize) {
+        self.primary.reserve(additional)
+    }
+
+    /// Compacts both pools in place, so no transactions move between them.
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        self.primary.compact(keep) + self.secondary.compact(keep)
+    }
+}
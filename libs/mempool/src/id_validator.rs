@@ -0,0 +1,58 @@
+use std::fmt::Debug;
+
+/// Checks the format of transaction ids, e.g. to reject malformed ids when they are submitted to a pool.
+pub trait IdValidator: Debug + Send + Sync {
+    /// Returns whether `id` is well-formed.
+    fn is_valid(&self, id: &str) -> bool;
+}
+
+/// Accepts ids that hex-encode exactly `bytes` bytes, without a `0x` prefix. Both upper and lower case digits
+/// are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexValidator {
+    pub bytes: usize,
+}
+
+impl HexValidator {
+    pub fn new(bytes: usize) -> Self {
+        Self { bytes }
+    }
+}
+
+/// Accepts hex-encoded 32 byte ids, e.g. transaction hashes.
+impl Default for HexValidator {
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+impl IdValidator for HexValidator {
+    fn is_valid(&self, id: &str) -> bool {
+        id.len() == 2 * self.bytes && id.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+}
+
+/// Accepts ids in any of the textual formats of a UUID, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UuidValidator;
+
+impl IdValidator for UuidValidator {
+    fn is_valid(&self, id: &str) -> bool {
+        uuid::Uuid::try_parse(id).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_validator_checks_length_and_digits() {
+        let validator = HexValidator::new(2);
+        assert!(validator.is_valid("0aFf"));
+        assert!(!validator.is_valid("0aF"));
+        assert!(!validator.is_valid("0aFf00"));
+        assert!(!validator.is_valid("0x0a"));
+        assert!(!validator.is_valid("0aFg"));
+    }
+}
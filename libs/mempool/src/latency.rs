@@ -0,0 +1,100 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// Weight of the most recent sample in the moving averages.
+const ALPHA: f64 = 0.2;
+
+/// Exponentially weighted moving average (EWMA) of recent drain operations.
+///
+/// Tracks both the duration of a drain and the number of transactions it returned, so that the
+/// time it takes to drain an arbitrary number of transactions can be estimated.
+/// The averages are stored as `f64` bits in atomics, which lets concurrent drains record samples
+/// without taking a lock.
+#[derive(Debug, Default)]
+pub struct DrainLatency {
+    avg_duration_ns: AtomicU64,
+    avg_batch_size: AtomicU64,
+}
+
+impl DrainLatency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a drain operation that took `duration` and returned `batch_size` transactions to the averages.
+    /// Empty drains are ignored, as they carry no information about the drain throughput.
+    pub fn record(&self, duration: Duration, batch_size: usize) {
+        if batch_size == 0 {
+            return;
+        }
+        Self::update(&self.avg_duration_ns, duration.as_nanos() as f64);
+        Self::update(&self.avg_batch_size, batch_size as f64);
+    }
+
+    /// Rough estimate of how long it takes to drain `n` transactions, based on the average drain duration
+    /// and the number of average sized batches needed to get `n` transactions.
+    /// Returns [`Duration::ZERO`] if no drain has been recorded yet.
+    pub fn estimate(&self, n: usize) -> Duration {
+        let avg_batch_size = f64::from_bits(self.avg_batch_size.load(Ordering::Relaxed));
+        if avg_batch_size == 0.0 {
+            return Duration::ZERO;
+        }
+        let avg_duration_ns = f64::from_bits(self.avg_duration_ns.load(Ordering::Relaxed));
+        let batches = (n as f64 / avg_batch_size).ceil();
+
+        Duration::from_nanos((batches * avg_duration_ns) as u64)
+    }
+
+    fn update(avg: &AtomicU64, sample: f64) {
+        avg.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            let current = f64::from_bits(bits);
+            // -- the first sample initializes the average
+            let next = if current == 0.0 {
+                sample
+            } else {
+                ALPHA * sample + (1.0 - ALPHA) * current
+            };
+            Some(next.to_bits())
+        })
+        .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::DrainLatency;
+
+    #[test]
+    fn estimate_without_samples_is_zero() {
+        assert_eq!(DrainLatency::new().estimate(100), Duration::ZERO);
+    }
+
+    #[test]
+    fn estimate_scales_with_required_batches() {
+        let latency = DrainLatency::new();
+        for _ in 0..10 {
+            latency.record(Duration::from_millis(10), 10);
+        }
+        latency.record(Duration::from_millis(50), 0); // -- ignored
+
+        assert_eq!(latency.estimate(10), Duration::from_millis(10));
+        assert_eq!(latency.estimate(25), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn estimate_follows_recent_samples() {
+        let latency = DrainLatency::new();
+        latency.record(Duration::from_millis(10), 10);
+        for _ in 0..20 {
+            latency.record(Duration::from_millis(20), 10);
+        }
+
+        let estimate = latency.estimate(10);
+        assert!(estimate > Duration::from_millis(19));
+        assert!(estimate <= Duration::from_millis(20));
+    }
+}
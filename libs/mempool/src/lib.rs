@@ -1,6 +1,24 @@
+mod diff;
+mod event;
+mod fallback;
+mod id_validator;
+mod latency;
 mod mempool;
+mod mirrored;
+mod rate_limited;
+mod reject;
 pub mod test;
+mod timed;
 
 // region:    --- Exports
+pub use diff::{PoolDiff, diff};
+pub use event::MempoolEvent;
+pub use fallback::FallbackPool;
+pub use id_validator::{HexValidator, IdValidator, UuidValidator};
+pub use latency::DrainLatency;
 pub use mempool::{Mempool, Transaction};
+pub use mirrored::MirroredPool;
+pub use rate_limited::RateLimitedPool;
+pub use reject::RejectReason;
+pub use timed::TimedPool;
 // endregion: --- Exports
@@ -1,45 +1,359 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, time::Duration};
+
+use sha2::{Digest, Sha256};
+
+/// Number of transactions the default [`Mempool::compact`] drains at once.
+const COMPACT_CHUNK: usize = 1_024;
 
 pub trait Mempool: Send + Sync + 'static {
     fn submit(&self, tx: Transaction);
+
+    /// Removes and returns up to `n` transactions, highest priority first.
+    ///
+    /// Transactions are drained in the order of their [`Ord`] implementation, which is total: transactions of
+    /// equal gas price, timestamp and override are ordered by their id. Implementations therefore drain
+    /// identical input in the identical order, unless they document a deviation (e.g. expired transactions).
     fn drain(&self, n: usize) -> Vec<Transaction>;
+
+    /// Submits `tx` to the pool. Pools that can reject transactions (e.g. because they are full or
+    /// rate limited) return the rejected transaction to the caller, boxed to keep the `Result` small.
+    /// The default implementation accepts every transaction.
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        self.submit(tx);
+        Ok(())
+    }
+
+    /// Rough estimate of how long it takes to drain `n` transactions under the current load.
+    /// Implementations that do not keep track of their drain durations return [`Duration::ZERO`].
+    fn estimated_drain_delay(&self, _n: usize) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Returns clones of up to `k` transactions chosen uniformly at random, e.g. to get a representative
+    /// cross-section of the pool for fee estimation. Nothing is removed from the pool.
+    /// Implementations that cannot inspect their contents return an empty vector.
+    fn sample(&self, _k: usize) -> Vec<Transaction> {
+        vec![]
+    }
+
+    /// Returns a clone of the queued transaction with the given `id`, e.g. to query the status of a pending
+    /// transaction. Nothing is removed from the pool. If several queued transactions share the `id`, any of
+    /// them is returned. Implementations that cannot inspect their contents return `None`.
+    fn get(&self, _id: &str) -> Option<Transaction> {
+        None
+    }
+
+    /// Reserves capacity for at least `additional` more transactions, e.g. ahead of a known burst of submits to
+    /// avoid reallocations in the middle of it. Implementations without preallocated storage ignore the call.
+    fn reserve(&self, _additional: usize) {}
+
+    /// Drains `n` transactions by priority like [`Mempool::drain`] and sorts the returned batch by `key`.
+    /// The sort only affects the order of the batch, not which transactions are selected.
+    fn drain_sorted_by<K: Ord>(&self, n: usize, key: impl Fn(&Transaction) -> K) -> Vec<Transaction>
+    where
+        Self: Sized,
+    {
+        let mut drained = self.drain(n);
+        drained.sort_by_key(key);
+        drained
+    }
+
+    /// Drains `n` transactions by priority like [`Mempool::drain`] and applies `f` to each of them, e.g. to keep
+    /// only the ids of the drained transactions without a separate map step. `f` is applied in priority order.
+    fn drain_map<R>(&self, n: usize, f: impl Fn(Transaction) -> R) -> Vec<R>
+    where
+        Self: Sized,
+    {
+        self.drain(n).into_iter().map(f).collect()
+    }
+
+    /// Removes all transactions that do not satisfy `keep`, e.g. to prune expired or underpriced transactions
+    /// during housekeeping. Returns the number of removed transactions.
+    ///
+    /// # Note
+    /// The default implementation drains the whole pool in chunks of bounded size and re-submits the kept
+    /// transactions afterwards, so concurrent drains may miss them while the compaction is in
+    /// progress.
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        let mut kept = vec![];
+        let mut dropped = 0;
+        loop {
+            let chunk = self.drain(COMPACT_CHUNK);
+            let exhausted = chunk.len() < COMPACT_CHUNK;
+            for tx in chunk {
+                if keep(&tx) {
+                    kept.push(tx);
+                } else {
+                    dropped += 1;
+                }
+            }
+            if exhausted {
+                break;
+            }
+        }
+        kept.into_iter().for_each(|tx| self.submit(tx));
+        dropped
+    }
+}
+
+/// Lets pools that are selected at runtime be used wherever a [`Mempool`] is expected.
+impl Mempool for Box<dyn Mempool> {
+    fn submit(&self, tx: Transaction) {
+        (**self).submit(tx)
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        (**self).drain(n)
+    }
+
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        (**self).try_submit(tx)
+    }
+
+    fn estimated_drain_delay(&self, n: usize) -> Duration {
+        (**self).estimated_drain_delay(n)
+    }
+
+    fn sample(&self, k: usize) -> Vec<Transaction> {
+        (**self).sample(k)
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        (**self).get(id)
+    }
+
+    fn reserve(&self, additional: usize) {
+        (**self).reserve(additional)
+    }
+
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        (**self).compact(keep)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// # Hash
+/// Every transaction carries a SHA-256 `hash` of its other fields, which is serialized as a hex string.
+/// The hash is computed on construction. Code that mutates the fields of a transaction is responsible
+/// for updating the hash via [`Transaction::compute_hash`].
+/// On deserialization, a supplied hash is verified against the contents of the transaction and a mismatch is
+/// rejected. Transactions without a hash get one assigned.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "WireTransaction")]
 pub struct Transaction {
     pub id: String,
     pub gas_price: u64,
     pub timestamp: u64,
     pub payload: Vec<u8>,
+    /// Unix time in microseconds after which the transaction must no longer be drained. Never expires if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<u64>,
+    /// Account that submitted the transaction, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    /// Priority of privileged transactions, e.g. local or system transactions, that takes precedence over the
+    /// `gas_price`, see [`Transaction::with_priority_override`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_override: Option<u64>,
+    #[serde(with = "hex_hash")]
+    pub hash: [u8; 32],
 }
 
 impl Transaction {
     /// As defined in the assignment, priority is determined using the following criteria:
     /// - Higher gas prices lead to a higher priority.
     /// - On equal gas price, an earlier timestamp leads to a higher priority.
+    ///
+    /// Transactions with a `priority_override` outrank all transactions without one and are ordered among
+    /// themselves by the override, before the criteria above apply.
+    ///
+    /// Remaining ties are broken by the id, where the lexicographically smaller id has the higher priority, so
+    /// every pool drains transactions of equal priority in the same order.
     fn priority(&self, other: &Self) -> Ordering {
+        match (self.priority_override, other.priority_override) {
+            (Some(own), Some(other)) if own != other => return own.cmp(&other),
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            _ => {}
+        }
         if self.gas_price != other.gas_price {
             return self.gas_price.cmp(&other.gas_price);
         }
-        other.timestamp.cmp(&self.timestamp)
+        other
+            .timestamp
+            .cmp(&self.timestamp)
+            .then_with(|| other.id.cmp(&self.id))
     }
 
     pub fn new(id: &str, gas_price: u64, timestamp: u64, payload: Vec<u8>) -> Self {
-        Self {
-            id: id.to_string(),
-            gas_price,
-            timestamp,
-            payload,
-        }
+        Self::from_parts(id.to_string(), gas_price, timestamp, payload)
     }
 
     /// Creates a [`Transaction`] with an empty vector as its `payload`.
     pub fn with_empty_load(id: &str, gas_price: u64, timestamp: u64) -> Self {
-        Self {
-            id: id.to_string(),
+        Self::new(id, gas_price, timestamp, vec![])
+    }
+
+    /// Creates a [`Transaction`] from owned parts and computes its hash.
+    pub fn from_parts(id: String, gas_price: u64, timestamp: u64, payload: Vec<u8>) -> Self {
+        let mut tx = Self {
+            id,
             gas_price,
             timestamp,
-            payload: vec![],
+            payload,
+            valid_until: None,
+            sender: None,
+            priority_override: None,
+            hash: [0; 32],
+        };
+        tx.hash = tx.compute_hash();
+        tx
+    }
+
+    /// Sets the deadline `valid_until` (unix time in microseconds) and updates the hash.
+    pub fn with_valid_until(mut self, valid_until: u64) -> Self {
+        self.valid_until = Some(valid_until);
+        self.hash = self.compute_hash();
+        self
+    }
+
+    /// Sets the `sender` account and updates the hash.
+    pub fn with_sender(mut self, sender: &str) -> Self {
+        self.sender = Some(sender.to_string());
+        self.hash = self.compute_hash();
+        self
+    }
+
+    /// Sets the `priority_override`, which lets the transaction be drained before all transactions without one
+    /// regardless of their gas price, and updates the hash.
+    pub fn with_priority_override(mut self, priority: u64) -> Self {
+        self.priority_override = Some(priority);
+        self.hash = self.compute_hash();
+        self
+    }
+
+    /// Checks whether the deadline of the transaction has passed at the unix time `now_us` (in microseconds).
+    pub fn is_expired(&self, now_us: u64) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| now_us > valid_until)
+    }
+
+    /// Compares all fields except the `timestamp` and therefore the `hash`.
+    /// Use this to detect duplicates that were received at different times, whereas `==` compares all fields.
+    pub fn same_content(&self, other: &Self) -> bool {
+        // -- destructured, so that a new field has to be considered here
+        let Self {
+            id,
+            gas_price,
+            timestamp: _,
+            payload,
+            valid_until,
+            sender,
+            priority_override,
+            hash: _,
+        } = self;
+        *id == other.id
+            && *gas_price == other.gas_price
+            && *payload == other.payload
+            && *valid_until == other.valid_until
+            && *sender == other.sender
+            && *priority_override == other.priority_override
+    }
+
+    /// Computes the SHA-256 hash of all fields except the `hash` itself.
+    /// Variable sized fields are prefixed with their length to keep the encoding unambiguous.
+    pub fn compute_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update((self.id.len() as u64).to_le_bytes());
+        hasher.update(self.id.as_bytes());
+        hasher.update(self.gas_price.to_le_bytes());
+        hasher.update(self.timestamp.to_le_bytes());
+        hasher.update((self.payload.len() as u64).to_le_bytes());
+        hasher.update(&self.payload);
+        // Optional fields are appended only if set and tagged to tell them apart.
+        // This keeps the hashes of transactions without optional fields unchanged.
+        if let Some(valid_until) = self.valid_until {
+            hasher.update(b"v");
+            hasher.update(valid_until.to_le_bytes());
+        }
+        if let Some(sender) = &self.sender {
+            hasher.update(b"s");
+            hasher.update((sender.len() as u64).to_le_bytes());
+            hasher.update(sender.as_bytes());
+        }
+        if let Some(priority_override) = self.priority_override {
+            hasher.update(b"p");
+            hasher.update(priority_override.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Serialized form of a [`Transaction`], used to verify the hash on deserialization.
+#[derive(serde::Deserialize)]
+struct WireTransaction {
+    id: String,
+    gas_price: u64,
+    timestamp: u64,
+    payload: Vec<u8>,
+    #[serde(default)]
+    valid_until: Option<u64>,
+    #[serde(default)]
+    sender: Option<String>,
+    #[serde(default)]
+    priority_override: Option<u64>,
+    #[serde(default, with = "hex_hash::optional")]
+    hash: Option<[u8; 32]>,
+}
+
+impl TryFrom<WireTransaction> for Transaction {
+    type Error = String;
+
+    fn try_from(wire: WireTransaction) -> Result<Self, Self::Error> {
+        let mut tx = Transaction::from_parts(wire.id, wire.gas_price, wire.timestamp, wire.payload);
+        if let Some(valid_until) = wire.valid_until {
+            tx = tx.with_valid_until(valid_until);
+        }
+        if let Some(sender) = wire.sender {
+            tx = tx.with_sender(&sender);
+        }
+        if let Some(priority_override) = wire.priority_override {
+            tx = tx.with_priority_override(priority_override);
+        }
+        match wire.hash {
+            Some(hash) if hash != tx.hash => Err(format!(
+                "hash of transaction {} does not match its contents",
+                tx.id
+            )),
+            _ => Ok(tx),
+        }
+    }
+}
+
+/// (De-)serializes a hash as a hex string.
+mod hex_hash {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(hash: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(hash))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let hex_str = String::deserialize(deserializer)?;
+        let mut hash = [0; 32];
+        hex::decode_to_slice(hex_str, &mut hash).map_err(D::Error::custom)?;
+        Ok(hash)
+    }
+
+    pub mod optional {
+        use serde::{Deserialize, Deserializer};
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<[u8; 32]>, D::Error> {
+            #[derive(Deserialize)]
+            struct Hash(#[serde(with = "super")] [u8; 32]);
+
+            Ok(Option::<Hash>::deserialize(deserializer)?.map(|Hash(hash)| hash))
         }
     }
 }
@@ -62,7 +376,8 @@ impl Ord for Transaction {
 
 #[cfg(test)]
 mod tests {
-    use super::Transaction;
+    use super::{COMPACT_CHUNK, Mempool, Transaction};
+    use crate::test::heap_pool::HeapPool;
     use std::cmp::Ordering;
 
     /// Higher gas price -> Higher priority
@@ -85,20 +400,42 @@ mod tests {
         assert_eq!(late.cmp(&early), Ordering::Less);
     }
 
-    /// Equal priority conditions lead to an `Ordering::Equal` evaluation, so in these cases
-    /// sorting becomes a no-op.
+    /// On equal gas price and timestamp, the smaller id has higher priority
     #[test]
     fn cmp_ordering_equal_tx() {
         let a = Transaction::with_empty_load("a", 10, 100);
         let b = Transaction::with_empty_load("b", 10, 100);
 
-        assert_eq!(a.cmp(&b), Ordering::Equal);
-        assert_eq!(b.partial_cmp(&a), Some(Ordering::Equal));
+        assert_eq!(a.cmp(&b), Ordering::Greater);
+        assert_eq!(b.partial_cmp(&a), Some(Ordering::Less));
+        assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+    }
+
+    /// A priority override outranks any gas price and orders override transactions among themselves
+    #[test]
+    fn cmp_priority_override() {
+        let rich = Transaction::with_empty_load("rich", 1_000, 100);
+        let system = Transaction::with_empty_load("system", 1, 200).with_priority_override(1);
+        let urgent = Transaction::with_empty_load("urgent", 1, 300).with_priority_override(2);
+
+        assert_eq!(system.cmp(&rich), Ordering::Greater);
+        assert_eq!(rich.cmp(&system), Ordering::Less);
+        assert_eq!(urgent.cmp(&system), Ordering::Greater);
+    }
+
+    #[test]
+    fn priority_override_drains_first() {
+        let pool = HeapPool::default();
+        pool.submit(Transaction::with_empty_load("rich", 1_000, 100));
+        pool.submit(Transaction::with_empty_load("system", 1, 200).with_priority_override(0));
+
+        let ids: Vec<String> = pool.drain(2).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec!["system", "rich"]);
     }
 
     #[test]
     fn sort_transactions() {
-        let mut txs = vec![
+        let mut txs = [
             Transaction::with_empty_load("t1", 5, 100), // -- lowest price, recent addition
             Transaction::with_empty_load("t2", 5, 300), // -- lowest price, late addition
             Transaction::with_empty_load("t3", 20, 50), // -- highest price
@@ -109,4 +446,137 @@ mod tests {
         let ids: Vec<&str> = txs.iter().map(|tx| tx.id.as_str()).collect();
         assert_eq!(ids, vec!["t2", "t1", "t4", "t3"]);
     }
+
+    #[test]
+    fn drain_map_keeps_drain_order() {
+        let txs = [("a", 30, 0), ("b", 10, 0), ("c", 30, 5), ("d", 20, 0)];
+        let mapped = HeapPool::default();
+        let plain = HeapPool::default();
+        for (id, gas_price, timestamp) in txs {
+            mapped.submit(Transaction::with_empty_load(id, gas_price, timestamp));
+            plain.submit(Transaction::with_empty_load(id, gas_price, timestamp));
+        }
+
+        let ids: Vec<String> = mapped.drain_map(3, |tx| tx.id);
+        let expected: Vec<String> = plain.drain(3).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, expected);
+        assert_eq!(ids, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn hash_covers_contents() {
+        let tx = Transaction::new("tx", 10, 100, vec![1, 2, 3]);
+        assert_eq!(tx.hash, tx.compute_hash());
+        assert_ne!(tx.hash, Transaction::new("tx", 10, 100, vec![1, 2]).hash);
+        assert_ne!(tx.hash, Transaction::new("tx", 11, 100, vec![1, 2, 3]).hash);
+        assert_eq!(tx.hash, Transaction::new("tx", 10, 100, vec![1, 2, 3]).hash);
+    }
+
+    #[test]
+    fn hash_serializes_as_hex() {
+        let tx = Transaction::with_empty_load("tx", 10, 100);
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["hash"], hex::encode(tx.hash));
+
+        let deserialized: Transaction = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, tx);
+    }
+
+    #[test]
+    fn deserialize_rejects_tampered_hash() {
+        let mut json = serde_json::to_value(Transaction::with_empty_load("tx", 10, 100)).unwrap();
+        json["gas_price"] = 1_000.into();
+
+        let err = serde_json::from_value::<Transaction>(json).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn deserialize_assigns_missing_hash() {
+        let json = r#"{"id":"tx","gas_price":10,"timestamp":100,"payload":[]}"#;
+        let tx: Transaction = serde_json::from_str(json).unwrap();
+        assert_eq!(tx, Transaction::with_empty_load("tx", 10, 100));
+    }
+
+    #[test]
+    fn same_content_ignores_timestamp() {
+        let tx = Transaction::new("tx", 10, 100, vec![1, 2]);
+        let resubmitted = Transaction::new("tx", 10, 200, vec![1, 2]);
+
+        assert!(tx.same_content(&resubmitted));
+        assert_ne!(tx, resubmitted);
+        assert!(!tx.same_content(&Transaction::new("tx", 10, 100, vec![1])));
+    }
+
+    #[test]
+    fn same_content_compares_all_other_fields() {
+        let tx = Transaction::new("tx", 10, 100, vec![1, 2]);
+
+        assert!(!tx.same_content(&tx.clone().with_valid_until(500)));
+        assert!(!tx.same_content(&tx.clone().with_sender("alice")));
+        assert!(!tx.same_content(&tx.clone().with_priority_override(1)));
+        let resubmitted = Transaction::new("tx", 10, 200, vec![1, 2]).with_sender("alice");
+        assert!(tx.clone().with_sender("alice").same_content(&resubmitted));
+    }
+
+    #[test]
+    fn compact_resubmits_kept_transactions() {
+        let pool = HeapPool::default();
+        for gas_price in 1..=6 {
+            pool.submit(Transaction::with_empty_load("tx", gas_price, 0));
+        }
+
+        assert_eq!(pool.compact(&|tx| tx.gas_price % 2 == 0), 3);
+        let gas_prices: Vec<u64> = pool.drain(10).iter().map(|tx| tx.gas_price).collect();
+        assert_eq!(gas_prices, [6, 4, 2]);
+    }
+
+    #[test]
+    fn compact_spans_several_chunks_and_is_forwarded_by_boxes() {
+        let pool: Box<dyn Mempool> = Box::new(HeapPool::default());
+        let total = 2 * COMPACT_CHUNK as u64 + 1;
+        for gas_price in 0..total {
+            pool.submit(Transaction::with_empty_load("tx", gas_price, 0));
+        }
+
+        assert_eq!(
+            pool.compact(&|tx| tx.gas_price % 2 == 0),
+            total as usize / 2
+        );
+        assert_eq!(pool.drain(usize::MAX).len(), total as usize / 2 + 1);
+    }
+
+    #[test]
+    fn drain_sorted_by_reorders_priority_selection() {
+        let pool = HeapPool::default();
+        for (id, gas_price) in [("c", 30), ("a", 20), ("d", 1), ("b", 40)] {
+            pool.submit(Transaction::with_empty_load(id, gas_price, 0));
+        }
+
+        let drained = pool.drain_sorted_by(3, |tx| tx.id.clone());
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(pool.drain(1)[0].id, "d");
+    }
+
+    #[test]
+    fn deadline_round_trips_and_is_hashed() {
+        let tx = Transaction::with_empty_load("tx", 10, 100).with_valid_until(500);
+        assert_ne!(tx.hash, Transaction::with_empty_load("tx", 10, 100).hash);
+        assert!(!tx.is_expired(500));
+        assert!(tx.is_expired(501));
+
+        let json = serde_json::to_string(&tx).unwrap();
+        assert_eq!(serde_json::from_str::<Transaction>(&json).unwrap(), tx);
+    }
+
+    #[test]
+    fn sender_round_trips_and_is_hashed() {
+        let tx = Transaction::with_empty_load("tx", 10, 100).with_sender("alice");
+        assert_ne!(tx.hash, Transaction::with_empty_load("tx", 10, 100).hash);
+        assert_ne!(tx.hash, tx.clone().with_sender("bob").hash);
+
+        let json = serde_json::to_string(&tx).unwrap();
+        assert_eq!(serde_json::from_str::<Transaction>(&json).unwrap(), tx);
+    }
 }
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use crate::{Mempool, Transaction};
+
+/// [`Mempool`] that mirrors every submitted transaction to a `replica` pool, e.g. to keep a standby warm
+/// for a failover.
+///
+/// Drains are served from the `primary` pool only, so drained transactions stay in the `replica`.
+/// Submits to the `replica` are best-effort: a rejection is logged but does not affect the `primary`.
+#[derive(Debug)]
+pub struct MirroredPool<P: Mempool, R: Mempool> {
+    primary: P,
+    replica: R,
+}
+
+impl<P: Mempool, R: Mempool> MirroredPool<P, R> {
+    pub fn new(primary: P, replica: R) -> Self {
+        Self { primary, replica }
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn replica(&self) -> &R {
+        &self.replica
+    }
+
+    fn mirror(&self, tx: Transaction) {
+        if let Err(tx) = self.replica.try_submit(tx) {
+            eprintln!("Warn! Replica rejected transaction {}", tx.id);
+        }
+    }
+}
+
+impl<P: Mempool, R: Mempool> Mempool for MirroredPool<P, R> {
+    fn submit(&self, tx: Transaction) {
+        self.mirror(tx.clone());
+        self.primary.submit(tx);
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        self.primary.drain(n)
+    }
+
+    /// Only transactions accepted by the `primary` are mirrored.
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        self.primary.try_submit(tx.clone())?;
+        self.mirror(tx);
+        Ok(())
+    }
+
+    fn estimated_drain_delay(&self, n: usize) -> Duration {
+        self.primary.estimated_drain_delay(n)
+    }
+
+    fn sample(&self, k: usize) -> Vec<Transaction> {
+        self.primary.sample(k)
+    }
+
+    /// Drained transactions remain in the `replica`, so only the `primary` is queried.
+    fn get(&self, id: &str) -> Option<Transaction> {
+        self.primary.get(id)
+    }
+
+    /// Both pools receive every submitted transaction, so both reserve capacity.
+    fn reserve(&self, additional: usize) {
+        self.primary.reserve(additional);
+        self.replica.reserve(additional);
+    }
+
+    /// Only the `primary` is compacted, the kept transactions are not mirrored again.
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        self.primary.compact(keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::heap_pool::HeapPool;
+
+    #[test]
+    fn submits_reach_both_pools_drains_only_primary() {
+        let pool = MirroredPool::new(HeapPool::default(), HeapPool::default());
+        for i in 0..5 {
+            pool.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+        assert_eq!(pool.primary().len(), 5);
+        assert_eq!(pool.replica().len(), 5);
+
+        let drained = pool.drain(3);
+        assert_eq!(drained.len(), 3);
+        assert_eq!(pool.primary().len(), 2);
+        assert_eq!(pool.replica().len(), 5);
+
+        let mirrored = pool.replica().drain(5);
+        assert!(drained.iter().all(|tx| mirrored.contains(tx)));
+    }
+}
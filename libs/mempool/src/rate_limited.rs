@@ -0,0 +1,130 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{Mempool, Transaction};
+
+/// [`Mempool`] decorator that enforces a global rate limit on submits using a token bucket.
+///
+/// The bucket holds at most `burst` tokens and refills with `max_per_second` tokens per second.
+/// Every accepted submit consumes one token, submits that find the bucket empty are rejected.
+/// Drains are forwarded to the wrapped pool unchanged.
+#[derive(Debug)]
+pub struct RateLimitedPool<T: Mempool> {
+    inner: T,
+    bucket: TokenBucket,
+}
+
+impl<T: Mempool> RateLimitedPool<T> {
+    pub fn new(inner: T, max_per_second: f64, burst: usize) -> Self {
+        Self {
+            inner,
+            bucket: TokenBucket::new(max_per_second, burst as f64),
+        }
+    }
+}
+
+impl<T: Mempool> Mempool for RateLimitedPool<T> {
+    /// Submits `tx` if the rate limit allows it, otherwise the transaction is dropped.
+    /// Use [`Mempool::try_submit`] to get rejected transactions back.
+    fn submit(&self, tx: Transaction) {
+        if self.try_submit(tx).is_err() {
+            eprintln!("Warn! Submit rate limit exceeded, transaction is dropped.");
+        }
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        self.inner.drain(n)
+    }
+
+    fn estimated_drain_delay(&self, n: usize) -> Duration {
+        self.inner.estimated_drain_delay(n)
+    }
+
+    fn sample(&self, k: usize) -> Vec<Transaction> {
+        self.inner.sample(k)
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        self.inner.get(id)
+    }
+
+    fn reserve(&self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+
+    /// Compacts the inner pool directly, so the kept transactions do not consume submit tokens.
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        self.inner.compact(keep)
+    }
+
+    /// Returns `tx` back to the caller if the rate limit is exceeded.
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        if !self.bucket.try_acquire() {
+            return Err(Box::new(tx));
+        }
+        self.inner.try_submit(tx)
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    max_per_second: f64,
+    burst: f64,
+    /// Available tokens and the point in time they were last refilled.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(max_per_second: f64, burst: f64) -> Self {
+        Self {
+            max_per_second,
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Refills the bucket based on the time passed since the last refill and takes one token if available.
+    fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, last_refill) = &mut *state;
+
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(*last_refill);
+        *tokens = (*tokens + elapsed.as_secs_f64() * self.max_per_second).min(self.burst);
+        *last_refill = now;
+
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::heap_pool::HeapPool;
+
+    #[test]
+    fn rejects_submits_above_rate() {
+        let pool = RateLimitedPool::new(HeapPool::default(), 20.0, 5);
+
+        let rejected = (0..20)
+            .map(|i| Transaction::with_empty_load(&format!("tx{i}"), i, i))
+            .filter_map(|tx| pool.try_submit(tx).err())
+            .count();
+        assert_eq!(rejected, 15);
+        assert_eq!(pool.inner.len(), 5);
+
+        // -- the bucket refills over time (two tokens per 100 ms)
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(
+            pool.try_submit(Transaction::with_empty_load("late", 1, 1))
+                .is_ok()
+        );
+        assert_eq!(pool.drain(10).len(), 6);
+    }
+}
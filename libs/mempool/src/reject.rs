@@ -0,0 +1,14 @@
+/// Reason why a [`Mempool`](crate::Mempool) rejected a submitted [`Transaction`](crate::Transaction).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// The payload exceeds the maximum payload size of the pool.
+    PayloadTooLarge,
+    /// The gas price is below the minimum gas price of the pool.
+    BelowFeeFloor,
+    /// The pool holds its maximum number of transactions.
+    PoolFull,
+    /// The pool holds the maximum number of transactions of the sender.
+    SenderLimit,
+    /// The id does not match the format required by the pool.
+    InvalidId,
+}
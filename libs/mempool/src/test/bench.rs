@@ -0,0 +1,104 @@
+//! Criterion benchmarks shared by all [`Mempool`] implementations.
+
+use std::{hint::black_box, time::Duration};
+
+use criterion::{BatchSize, Criterion};
+use rand::seq::SliceRandom;
+
+use crate::{Mempool, Transaction};
+
+/// Time to let asynchronous implementations process the submissions that prepare a benchmark.
+const SETTLE_TIME: Duration = Duration::from_millis(8_000);
+
+pub fn create_tx(gas_price: u64) -> Transaction {
+    Transaction::with_empty_load("", gas_price, 0)
+}
+
+/// Runs all shared benchmark scenarios against the pools created by `factory`.
+/// The factory receives the capacity the pool is created with, `name` prefixes the benchmark ids.
+pub fn bench_mempool<T: Mempool>(c: &mut Criterion, name: &str, factory: impl Fn(usize) -> T) {
+    submit_drain(c, name, factory(50_000));
+    submit_high_priority_on_large_queue(c, name, factory(500_000));
+}
+
+fn submit_drain(c: &mut Criterion, name: &str, pool: impl Mempool) {
+    c.bench_function(&format!("{name} submit_drain"), |b| {
+        b.iter(|| {
+            pool.submit(create_tx(black_box(100)));
+            let drained = pool.drain(5);
+            assert_eq!(drained.len(), 1);
+            assert_eq!(drained[0].gas_price, 100);
+        })
+    });
+}
+
+fn submit_high_priority_on_large_queue(c: &mut Criterion, name: &str, pool: impl Mempool) {
+    // -- Prepare large pool
+    let mut gas_price = 0;
+    for _ in 0..50_000 {
+        let tx = create_tx(gas_price);
+        pool.submit(black_box(tx));
+
+        gas_price += 1;
+    }
+    std::thread::sleep(SETTLE_TIME);
+    c.bench_function(
+        &format!("{name} submit_high_priority_on_large_queue"),
+        |b| {
+            b.iter(|| {
+                let tx = create_tx(black_box(gas_price));
+                pool.submit(tx);
+
+                let drained = pool.drain(1);
+                assert_eq!(drained[0].gas_price, gas_price); //<-- should equal the last one added (highest gas price)
+            });
+        },
+    );
+}
+
+/// Times [`Transaction::cmp`] for each branch of the priority comparison, as well as sorting shuffled
+/// transactions, which is dominated by the comparisons.
+pub fn bench_transaction_cmp(c: &mut Criterion) {
+    let cases = [
+        (
+            "gas_price",
+            Transaction::with_empty_load("a", 20, 0),
+            Transaction::with_empty_load("b", 10, 0),
+        ),
+        (
+            "timestamp_tie_break",
+            Transaction::with_empty_load("a", 10, 1),
+            Transaction::with_empty_load("b", 10, 2),
+        ),
+        (
+            "equal",
+            Transaction::with_empty_load("a", 10, 1),
+            Transaction::with_empty_load("b", 10, 1),
+        ),
+    ];
+    let mut group = c.benchmark_group("transaction_cmp");
+    for (case, a, b) in &cases {
+        group.bench_function(*case, |bencher| {
+            bencher.iter(|| black_box(a).cmp(black_box(b)))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("transaction_sort");
+    group.sample_size(10);
+    for len in [10_000, 1_000_000] {
+        // -- few distinct gas prices, so many comparisons fall through to the timestamp
+        let mut txs: Vec<_> = (0..len as u64)
+            .map(|i| Transaction::with_empty_load("", i % 100, i % 1_000))
+            .collect();
+        txs.shuffle(&mut rand::rng());
+        group.bench_function(format!("shuffled_{len}"), |b| {
+            b.iter_batched(
+                || txs.clone(),
+                |mut txs| txs.sort_unstable(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
@@ -0,0 +1,24 @@
+use std::{collections::BinaryHeap, sync::Mutex};
+
+use crate::{Mempool, Transaction};
+
+/// Minimal [`Mempool`] implementation used to test the generic parts of this crate.
+#[derive(Debug, Default)]
+pub(crate) struct HeapPool(Mutex<BinaryHeap<Transaction>>);
+
+impl HeapPool {
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+impl Mempool for HeapPool {
+    fn submit(&self, tx: Transaction) {
+        self.0.lock().unwrap().push(tx);
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let mut heap = self.0.lock().unwrap();
+        (0..n).map_while(|_| heap.pop()).collect()
+    }
+}
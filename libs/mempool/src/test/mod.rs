@@ -1,2 +1,8 @@
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(test)]
+pub(crate) mod heap_pool;
 pub mod stress;
 pub mod suite;
+#[cfg(feature = "recording-subscriber")]
+pub mod tracing;
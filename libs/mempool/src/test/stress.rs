@@ -1,11 +1,11 @@
-use rand::{Rng, rngs::ThreadRng};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use std::sync::{
     Arc,
     atomic::{AtomicUsize, Ordering},
 };
 use std::thread;
 use std::time::{Duration, Instant};
-use uuid::Uuid;
+use uuid::Builder;
 
 use crate::{Mempool, Transaction};
 
@@ -19,24 +19,70 @@ pub struct StressTestConfig {
     pub drain_batch_size: usize,
     pub gas_price_range: (u64, u64),
     pub run_duration_seconds: u64,
+    pub consumer_phase: ConsumerPhase,
+    /// Keep the consumers draining after all producers are done until every submitted transaction has been
+    /// drained, instead of stopping them right away. The test still ends after `run_duration_seconds` at the latest.
+    pub stop_when_drained: bool,
+    /// Seed of the transaction generators. A random seed is picked if `None`, it is reported in the
+    /// [`TestResults`] so the run can be repeated.
+    pub seed: Option<u64>,
+    /// Upper bound of `num_producers`. Every producer runs on its own OS thread, so larger configurations
+    /// are rejected instead of exhausting the resources of the machine.
+    pub max_producers: usize,
+}
+
+/// Order of the drain and the sleep within each iteration of a consumer's loop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConsumerPhase {
+    /// Poll the pool first, then wait for `drain_interval_ms`.
+    #[default]
+    DrainThenSleep,
+    /// Wait for `drain_interval_ms` first, then poll the pool.
+    SleepThenDrain,
 }
 
 impl StressTestConfig {
-    /// Creates a randomized [Transaction] within the pre-configured ranges using the passed randomizer `rng`.
-    fn randomized_tx(&self, rng: &mut ThreadRng) -> Transaction {
-        let payload_size = rng.random_range(self.payload_size_range.0..self.payload_size_range.1);
-        let gas_price = rng.random_range(self.gas_price_range.0..self.gas_price_range.1);
+    /// Default of `max_producers`.
+    pub const DEFAULT_MAX_PRODUCERS: usize = 1_024;
+
+    /// # Error
+    /// Returns an error if `num_producers` exceeds `max_producers`.
+    pub fn check_producers(&self) -> anyhow::Result<()> {
+        if self.num_producers > self.max_producers {
+            anyhow::bail!(
+                "{} producers exceed the maximum of {} producer threads",
+                self.num_producers,
+                self.max_producers
+            );
+        }
+        Ok(())
+    }
 
-        Transaction {
-            id: Uuid::new_v4().to_string(),
+    /// Creates a randomized [Transaction] within the pre-configured ranges using the passed randomizer `rng`.
+    fn randomized_tx(&self, rng: &mut impl Rng) -> Transaction {
+        let payload_size = rng.random_range(self.payload_size_range.0..=self.payload_size_range.1);
+        let gas_price = rng.random_range(self.gas_price_range.0..=self.gas_price_range.1);
+
+        Transaction::from_parts(
+            Builder::from_random_bytes(rng.random())
+                .into_uuid()
+                .to_string(),
             gas_price,
-            timestamp: Instant::now().elapsed().as_secs(),
-            payload: (0..payload_size).map(|_| rng.random::<u8>()).collect(),
-        }
+            Instant::now().elapsed().as_secs(),
+            (0..payload_size).map(|_| rng.random::<u8>()).collect(),
+        )
     }
 }
 
-pub fn run_stress_test<T: Mempool>(mempool: Arc<T>, config: StressTestConfig) -> TestResults {
+/// # Error
+/// Returns an error without spawning any thread if the `config` asks for more than `max_producers` producers.
+pub fn run_stress_test<T: Mempool>(
+    mempool: Arc<T>,
+    config: StressTestConfig,
+) -> anyhow::Result<TestResults> {
+    config.check_producers()?;
+    let seed = config.seed.unwrap_or_else(rand::random);
+    println!("Run seed: {seed:#018x}");
     println!(
         "Starting stress test with {} producer threads",
         config.num_producers
@@ -67,7 +113,8 @@ pub fn run_stress_test<T: Mempool>(mempool: Arc<T>, config: StressTestConfig) ->
         let cloned_producers_stopped = Arc::clone(&producers_stopped);
 
         let handle = thread::spawn(move || {
-            let mut rng = rand::rng();
+            // Every producer gets its own generator, derived from the run seed.
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(producer_id as u64));
             let mut local_submitted = 0;
 
             while Instant::now() < test_end_time && local_submitted < config.num_transactions {
@@ -102,15 +149,25 @@ pub fn run_stress_test<T: Mempool>(mempool: Arc<T>, config: StressTestConfig) ->
     for consumer_id in 1..=config.num_consumers {
         let cloned_pool = Arc::clone(&mempool);
         let cloned_drained_count = Arc::clone(&consumer_drained_count);
+        let cloned_submitted_count = Arc::clone(&submitted_count);
         let cloned_producers_stopped = Arc::clone(&producers_stopped);
 
         let consumer_handle = thread::spawn(move || {
             let mut total_drained = 0;
             let mut batch_stats = vec![];
 
-            while Instant::now() < test_end_time
-                && cloned_producers_stopped.load(Ordering::Relaxed) < config.num_producers
-            {
+            // -- the submitted count is final once all producers are stopped
+            let done = || {
+                cloned_producers_stopped.load(Ordering::SeqCst) == config.num_producers
+                    && (!config.stop_when_drained
+                        || cloned_drained_count.load(Ordering::Relaxed)
+                            >= cloned_submitted_count.load(Ordering::Relaxed))
+            };
+            while Instant::now() < test_end_time && !done() {
+                if config.consumer_phase == ConsumerPhase::SleepThenDrain {
+                    thread::sleep(Duration::from_millis(config.drain_interval_ms));
+                }
+
                 let drain_start = Instant::now();
                 let drained = cloned_pool.drain(config.drain_batch_size);
                 let drain_duration = drain_start.elapsed();
@@ -127,7 +184,9 @@ pub fn run_stress_test<T: Mempool>(mempool: Arc<T>, config: StressTestConfig) ->
                     });
                 }
 
-                thread::sleep(Duration::from_millis(config.drain_interval_ms));
+                if config.consumer_phase == ConsumerPhase::DrainThenSleep {
+                    thread::sleep(Duration::from_millis(config.drain_interval_ms));
+                }
             }
             println!(
                 "Consumer {:02} completed, drained {} transactions in total",
@@ -178,7 +237,8 @@ pub fn run_stress_test<T: Mempool>(mempool: Arc<T>, config: StressTestConfig) ->
         0.0
     };
 
-    TestResults {
+    Ok(TestResults {
+        seed,
         test_duration,
         total_submitted,
         total_drained,
@@ -186,7 +246,7 @@ pub fn run_stress_test<T: Mempool>(mempool: Arc<T>, config: StressTestConfig) ->
         avg_batch_size,
         avg_batch_duration_micros,
         batch_stats,
-    }
+    })
 }
 
 // Structs for storing test results
@@ -198,6 +258,7 @@ pub struct BatchStat {
 
 #[derive(Debug)]
 pub struct TestResults {
+    seed: u64,
     test_duration: Duration,
     total_submitted: usize,
     total_drained: usize,
@@ -208,8 +269,14 @@ pub struct TestResults {
 }
 
 impl TestResults {
+    /// Seed the run's transaction generators were derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn print_summary(&self) {
         println!("\n{:=^75}", " Stress Test Results ");
+        println!("Run seed: {:#018x}", self.seed);
         println!("Test duration: {:?}", self.test_duration);
         println!("Total transactions submitted: {}", self.total_submitted);
         println!("Total transactions drained: {}", self.total_drained);
@@ -252,3 +319,135 @@ impl TestResults {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::heap_pool::HeapPool;
+
+    fn config(consumer_phase: ConsumerPhase) -> StressTestConfig {
+        StressTestConfig {
+            num_producers: 1,
+            num_transactions: 50,
+            num_consumers: 1,
+            payload_size_range: (1, 2),
+            drain_interval_ms: 200,
+            drain_batch_size: 100,
+            gas_price_range: (1, 10),
+            run_duration_seconds: 5,
+            consumer_phase,
+            stop_when_drained: false,
+            seed: None,
+            max_producers: StressTestConfig::DEFAULT_MAX_PRODUCERS,
+        }
+    }
+
+    fn run_with_phase(consumer_phase: ConsumerPhase) -> (TestResults, usize) {
+        let pool = Arc::new(HeapPool::default());
+        let results = run_stress_test(Arc::clone(&pool), config(consumer_phase)).unwrap();
+        let remaining = pool.drain(usize::MAX).len();
+        (results, remaining)
+    }
+
+    fn max_batch_size(results: &TestResults) -> usize {
+        results
+            .batch_stats
+            .iter()
+            .map(|stat| stat.size)
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn consumer_phases_differ_in_batch_sizes() {
+        let (drain_first, drain_first_remaining) = run_with_phase(ConsumerPhase::DrainThenSleep);
+        let (sleep_first, sleep_first_remaining) = run_with_phase(ConsumerPhase::SleepThenDrain);
+
+        // -- every submitted transaction is either drained or still in the pool
+        assert_eq!(drain_first.total_submitted, 50);
+        assert_eq!(sleep_first.total_submitted, 50);
+        assert_eq!(drain_first.total_drained + drain_first_remaining, 50);
+        assert_eq!(sleep_first.total_drained + sleep_first_remaining, 50);
+        // -- the batches account for every drained transaction
+        for results in [&drain_first, &sleep_first] {
+            let batched: usize = results.batch_stats.iter().map(|stat| stat.size).sum();
+            assert_eq!(batched, results.total_drained);
+        }
+
+        // -- polling first only catches the transactions that are submitted right at the start,
+        // waiting first collects the whole burst in one batch
+        assert_eq!(max_batch_size(&sleep_first), 50);
+        assert!(max_batch_size(&drain_first) < max_batch_size(&sleep_first));
+    }
+
+    #[test]
+    fn stop_when_drained_drains_every_transaction() {
+        let run = |stop_when_drained| {
+            let config = StressTestConfig {
+                drain_interval_ms: 20,
+                drain_batch_size: 10,
+                stop_when_drained,
+                ..config(ConsumerPhase::SleepThenDrain)
+            };
+            let pool = Arc::new(HeapPool::default());
+            let results = run_stress_test(Arc::clone(&pool), config).unwrap();
+            (results.total_drained, pool.drain(usize::MAX).len())
+        };
+
+        assert_eq!(run(true), (50, 0));
+        // -- otherwise the consumer stops with the producer, after its first drain
+        let (drained, remaining) = run(false);
+        assert!(drained < 50);
+        assert_eq!(drained + remaining, 50);
+    }
+
+    #[test]
+    fn run_without_seed_reports_its_seed() {
+        let mut config = config(ConsumerPhase::DrainThenSleep);
+        // -- without consumers, every submitted transaction stays in the pool
+        config.num_consumers = 0;
+        let submitted_ids = |config: StressTestConfig| {
+            let pool = Arc::new(HeapPool::default());
+            let results = run_stress_test(Arc::clone(&pool), config).unwrap();
+            let mut ids: Vec<String> = pool.drain(usize::MAX).into_iter().map(|tx| tx.id).collect();
+            ids.sort();
+            (results.seed(), ids)
+        };
+        let generated_ids = move |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(1));
+            let mut ids: Vec<String> = (0..config.num_transactions)
+                .map(|_| {
+                    let id = config.randomized_tx(&mut rng).id;
+                    // -- the producer draws its delay between two submits from the same generator
+                    rng.random_range(1..100u64);
+                    id
+                })
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        // -- the reported seed reproduces the transactions the run generated
+        let (seed, ids) = submitted_ids(config);
+        assert_eq!(ids.len(), config.num_transactions);
+        assert_eq!(ids, generated_ids(seed));
+
+        config.seed = Some(42);
+        let (seed, ids) = submitted_ids(config);
+        assert_eq!(seed, 42);
+        assert_eq!(ids, generated_ids(42));
+    }
+
+    #[test]
+    fn too_many_producers_are_rejected() {
+        let mut config = config(ConsumerPhase::DrainThenSleep);
+        config.num_producers = 3;
+        config.max_producers = 2;
+
+        let err = run_stress_test(Arc::new(HeapPool::default()), config).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("3 producers exceed the maximum of 2")
+        );
+    }
+}
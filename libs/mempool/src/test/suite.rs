@@ -9,6 +9,57 @@ where
     fn create_mempool(&self) -> T;
 }
 
+/// Lets a constructor closure, e.g. `|| NaivePool::new(1_000)`, serve as [`Tester`].
+impl<T: Mempool, F: Fn() -> T> Tester<T> for F {
+    fn create_mempool(&self) -> T {
+        self()
+    }
+}
+
+/// Generates the tests of this suite that apply to every [`Mempool`] implementation. The given expression
+/// creates the pool under test, a fresh pool is created for each test.
+///
+/// ```ignore
+/// #[cfg(test)]
+/// mod tests {
+///     mempool::mempool_test_suite!(NaivePool::new(50_000));
+/// }
+/// ```
+#[macro_export]
+macro_rules! mempool_test_suite {
+    ($create_mempool:expr) => {
+        #[test]
+        fn ordering_by_gas_price() {
+            $crate::test::suite::test_ordering_by_gas_price(|| $create_mempool);
+        }
+
+        #[test]
+        fn concurrent_submit() {
+            $crate::test::suite::test_concurrent_submit(|| $create_mempool);
+        }
+
+        #[test]
+        fn concurrent_submit_and_drain() {
+            $crate::test::suite::test_concurrent_submit_and_drain(|| $create_mempool);
+        }
+
+        #[test]
+        fn drain_batch_bounds() {
+            $crate::test::suite::test_drain_batch_bounds(|| $create_mempool);
+        }
+
+        #[test]
+        fn equal_priority_drain_order() {
+            $crate::test::suite::test_equal_priority_drain_order(|| $create_mempool);
+        }
+
+        #[test]
+        fn random_operations() {
+            $crate::test::suite::test_random_operations(|| $create_mempool, None);
+        }
+    };
+}
+
 /// Test basic priority ordering of the [`Mempool`] implementation
 pub fn test_ordering_by_gas_price<T: Mempool>(tester: impl Tester<T>) {
     let mempool = tester.create_mempool();
@@ -111,3 +162,143 @@ pub fn test_concurrent_submit_and_drain<T: Mempool>(tester: impl Tester<T>) {
         handle.join().unwrap();
     }
 }
+
+/// Drains a known number of transactions in fixed batches. Every batch has to be full until the pool runs
+/// low, then the final batch holds exactly the remainder and subsequent drains are empty.
+pub fn test_drain_batch_bounds<T: Mempool>(tester: impl Tester<T>) {
+    const TOTAL: usize = 103;
+    const BATCH_SIZE: usize = 10;
+    let mempool = tester.create_mempool();
+
+    for i in 0..TOTAL {
+        mempool.submit(Transaction::with_empty_load(
+            &format!("tx{i}"),
+            i as u64 % 7,
+            i as u64,
+        ));
+    }
+    std::thread::sleep(Duration::from_millis(10)); // wait for all transactions to be harvested by the receiver thread
+
+    for batch in 0..TOTAL / BATCH_SIZE {
+        let drained = mempool.drain(BATCH_SIZE);
+        assert_eq!(drained.len(), BATCH_SIZE, "batch {batch} is not full");
+    }
+    assert_eq!(mempool.drain(BATCH_SIZE).len(), TOTAL % BATCH_SIZE);
+    assert!(mempool.drain(BATCH_SIZE).is_empty());
+}
+
+/// Transactions of equal priority are drained in the canonical order of [`Mempool::drain`], regardless of the
+/// order of their submission, so every implementation drains identical input identically.
+pub fn test_equal_priority_drain_order<T: Mempool>(tester: impl Tester<T>) {
+    const TOTAL: usize = 100;
+    let mempool = tester.create_mempool();
+
+    // -- submit in a scrambled order, 37 is coprime to `TOTAL`
+    for i in 0..TOTAL {
+        let id = format!("tx{:03}", i * 37 % TOTAL);
+        mempool.submit(Transaction::with_empty_load(&id, 10, 100));
+    }
+    std::thread::sleep(Duration::from_millis(10)); // wait for all transactions to be harvested by the receiver thread
+
+    let ids: Vec<String> = std::iter::from_fn(|| Some(mempool.drain(7)))
+        .take_while(|batch| !batch.is_empty())
+        .flatten()
+        .map(|tx| tx.id)
+        .collect();
+    let expected: Vec<String> = (0..TOTAL).map(|i| format!("tx{i:03}")).collect();
+    assert_eq!(ids, expected);
+}
+
+/// Looks up a queued transaction by its id. Only applies to implementations that support [`Mempool::get`].
+pub fn test_get_by_id<T: Mempool>(tester: impl Tester<T>) {
+    let mempool = tester.create_mempool();
+    let tx = Transaction::new("wanted", 42, 7, vec![1, 2, 3]);
+    mempool.submit(Transaction::with_empty_load("other", 10, 1));
+    mempool.submit(tx.clone());
+
+    assert_eq!(mempool.get("wanted"), Some(tx));
+    assert_eq!(mempool.get("missing"), None);
+
+    assert_eq!(mempool.drain(1)[0].id, "wanted");
+    assert_eq!(mempool.get("wanted"), None);
+    assert!(mempool.get("other").is_some());
+}
+
+/// Applies a random sequence of submits, drains, removals and clears to the pool and to a sorted [`Vec`] as
+/// reference model. After every step, the whole pool is drained, compared with the model, which checks its length
+/// and its next transaction, and submitted again. Removals and clears are done via [`Mempool::compact`]. A random
+/// seed is picked if `seed` is `None`, it is printed to repeat a failing run.
+///
+/// Only applies to implementations whose drains see all submits that returned before.
+pub fn test_random_operations<T: Mempool>(tester: impl Tester<T>, seed: Option<u64>) {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    const STEPS: usize = 1_000;
+    let seed = seed.unwrap_or_else(rand::random);
+    println!("Random operations seed: {seed:#018x}");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mempool = tester.create_mempool();
+    // -- ascending priority, so the next transaction to drain is the last one
+    let mut model: Vec<Transaction> = vec![];
+    let ids = |txs: &[Transaction]| txs.iter().map(|tx| tx.id.clone()).collect::<Vec<_>>();
+
+    for step in 0..STEPS {
+        match rng.random_range(0..100) {
+            0..50 => {
+                // -- unique timestamps give every transaction a distinct priority
+                let tx = Transaction::with_empty_load(
+                    &format!("tx{step}"),
+                    rng.random_range(1..=20),
+                    step as u64,
+                );
+                let idx = model.partition_point(|queued| *queued < tx);
+                model.insert(idx, tx.clone());
+                mempool.submit(tx);
+            }
+            50..80 => {
+                let n = rng.random_range(0..=5);
+                let mut expected = model.split_off(model.len().saturating_sub(n));
+                expected.reverse();
+                assert_eq!(
+                    ids(&mempool.drain(n)),
+                    ids(&expected),
+                    "drain of {n} at step {step}"
+                );
+            }
+            80..97 => {
+                let Some(idx) = (!model.is_empty()).then(|| rng.random_range(0..model.len()))
+                else {
+                    continue;
+                };
+                let removed = model.remove(idx);
+                assert_eq!(
+                    mempool.compact(&|tx| tx.id != removed.id),
+                    1,
+                    "removal at step {step}"
+                );
+            }
+            _ => {
+                assert_eq!(
+                    mempool.compact(&|_| false),
+                    model.len(),
+                    "clear at step {step}"
+                );
+                model.clear();
+            }
+        }
+
+        let queued = mempool.drain(usize::MAX);
+        assert_eq!(queued.len(), model.len(), "length at step {step}");
+        assert_eq!(
+            queued.first(),
+            model.last(),
+            "next transaction at step {step}"
+        );
+        let expected: Vec<Transaction> = model.iter().rev().cloned().collect();
+        assert_eq!(ids(&queued), ids(&expected), "contents at step {step}");
+        queued.into_iter().for_each(|tx| mempool.submit(tx));
+    }
+
+    let remaining: Vec<Transaction> = model.into_iter().rev().collect();
+    assert_eq!(mempool.drain(usize::MAX), remaining);
+}
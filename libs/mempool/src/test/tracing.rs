@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::{self, ThreadId},
+};
+
+use tracing::{
+    Event, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_core::span::Current;
+
+/// Span captured by the [`RecordingSubscriber`] along with all of its recorded fields.
+#[derive(Debug, Clone)]
+pub struct RecordedSpan {
+    pub name: &'static str,
+    pub fields: HashMap<&'static str, String>,
+    metadata: &'static Metadata<'static>,
+}
+
+/// Minimal [`Subscriber`] that keeps every span created while it is the default subscriber,
+/// used to test the instrumentation of mempool implementations.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSubscriber {
+    spans: Arc<Mutex<HashMap<u64, RecordedSpan>>>,
+    next_id: Arc<AtomicU64>,
+    /// Stack of the entered spans per thread, the last one is the current span.
+    entered: Arc<Mutex<HashMap<ThreadId, Vec<u64>>>>,
+}
+
+impl RecordingSubscriber {
+    /// All spans with the given `name` that have been created so far.
+    pub fn spans(&self, name: &str) -> Vec<RecordedSpan> {
+        let spans = self.spans.lock().unwrap();
+        let mut ids: Vec<_> = spans.keys().copied().collect();
+        ids.sort_unstable();
+        ids.iter()
+            .map(|id| &spans[id])
+            .filter(|span| span.name == name)
+            .cloned()
+            .collect()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+}
+
+impl Subscriber for RecordingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut fields = HashMap::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        self.spans.lock().unwrap().insert(
+            id,
+            RecordedSpan {
+                name: span.metadata().name(),
+                fields,
+                metadata: span.metadata(),
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            values.record(&mut FieldVisitor(&mut span.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        let mut entered = self.entered.lock().unwrap();
+        entered
+            .entry(thread::current().id())
+            .or_default()
+            .push(span.into_u64());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut entered = self.entered.lock().unwrap();
+        let stack = entered.entry(thread::current().id()).or_default();
+        if let Some(idx) = stack.iter().rposition(|id| *id == span.into_u64()) {
+            stack.remove(idx);
+        }
+    }
+
+    fn current_span(&self) -> Current {
+        let entered = self.entered.lock().unwrap();
+        let Some(&id) = entered
+            .get(&thread::current().id())
+            .and_then(|stack| stack.last())
+        else {
+            return Current::none();
+        };
+        match self.spans.lock().unwrap().get(&id) {
+            Some(span) => Current::new(Id::from_u64(id), span.metadata),
+            None => Current::none(),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use hdrhistogram::Histogram;
+
+use crate::{Mempool, Transaction};
+
+/// [`Mempool`] decorator that records the latency of every submit and drain of the wrapped pool, e.g. to
+/// profile an implementation in production without the stress test harness.
+///
+/// Latencies are recorded in nanoseconds with three significant figures. Latencies above one minute are
+/// recorded as one minute.
+#[derive(Debug)]
+pub struct TimedPool<T: Mempool> {
+    inner: T,
+    submit_latencies: Mutex<Histogram<u64>>,
+    drain_latencies: Mutex<Histogram<u64>>,
+}
+
+impl<T: Mempool> TimedPool<T> {
+    /// Highest latency that can be recorded, in nanoseconds.
+    const MAX_LATENCY_NS: u64 = 60 * 1_000_000_000;
+
+    pub fn new(inner: T) -> Self {
+        let histogram = || {
+            Mutex::new(
+                Histogram::new_with_bounds(1, Self::MAX_LATENCY_NS, 3)
+                    .expect("bounds and significant figures are valid"),
+            )
+        };
+        Self {
+            inner,
+            submit_latencies: histogram(),
+            drain_latencies: histogram(),
+        }
+    }
+
+    /// Returns a snapshot of the latencies of [`Mempool::submit`] and [`Mempool::try_submit`] in nanoseconds.
+    pub fn submit_histogram(&self) -> Histogram<u64> {
+        self.submit_latencies.lock().unwrap().clone()
+    }
+
+    /// Returns a snapshot of the latencies of [`Mempool::drain`] in nanoseconds.
+    pub fn drain_histogram(&self) -> Histogram<u64> {
+        self.drain_latencies.lock().unwrap().clone()
+    }
+
+    fn record(histogram: &Mutex<Histogram<u64>>, latency: Duration) {
+        let latency_ns = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        histogram.lock().unwrap().saturating_record(latency_ns);
+    }
+}
+
+impl<T: Mempool> Mempool for TimedPool<T> {
+    fn submit(&self, tx: Transaction) {
+        let start = Instant::now();
+        self.inner.submit(tx);
+        Self::record(&self.submit_latencies, start.elapsed());
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let start = Instant::now();
+        let drained = self.inner.drain(n);
+        Self::record(&self.drain_latencies, start.elapsed());
+        drained
+    }
+
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        let start = Instant::now();
+        let result = self.inner.try_submit(tx);
+        Self::record(&self.submit_latencies, start.elapsed());
+        result
+    }
+
+    fn estimated_drain_delay(&self, n: usize) -> Duration {
+        self.inner.estimated_drain_delay(n)
+    }
+
+    fn sample(&self, k: usize) -> Vec<Transaction> {
+        self.inner.sample(k)
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        self.inner.get(id)
+    }
+
+    fn reserve(&self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+
+    /// Compacts the inner pool directly, so the compaction is not recorded as submits and drains.
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        self.inner.compact(keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::heap_pool::HeapPool;
+
+    #[test]
+    fn records_every_operation() {
+        let pool = TimedPool::new(HeapPool::default());
+        for i in 0..5 {
+            pool.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+        pool.try_submit(Transaction::with_empty_load("try", 10, 10))
+            .unwrap();
+        for _ in 0..3 {
+            pool.drain(2);
+        }
+
+        assert_eq!(pool.submit_histogram().len(), 6);
+        assert_eq!(pool.drain_histogram().len(), 3);
+        assert!(pool.drain_histogram().max() > 0);
+        assert_eq!(pool.inner.len(), 0);
+    }
+}
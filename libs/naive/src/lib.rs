@@ -2,6 +2,15 @@ use std::sync::Mutex;
 
 use mempool::{Mempool, Transaction};
 
+/// Position a caller expects a submitted transaction to take in the [`NaivePool`], see [`NaivePool::submit_hint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityHint {
+    /// The transaction has a higher priority than all queued transactions.
+    Highest,
+    /// The transaction has a lower priority than all queued transactions.
+    Lowest,
+}
+
 /// Naive implementation of a memory pool that just organizes all elements linearly within a vector.
 /// No optimizations are attempted with this implementation.
 pub struct NaivePool {
@@ -15,6 +24,28 @@ impl NaivePool {
             pool: Mutex::new(Vec::with_capacity(capacity)),
         }
     }
+
+    /// Submits `tx` at the boundary of the pool given by `hint` without re-sorting, e.g. for bulk loaders
+    /// that submit pre-sorted input. The hint is verified against the neighbouring transaction; a wrong hint
+    /// falls back to the regular sorted insert of [`Mempool::submit`].
+    ///
+    /// Returns whether the hint was correct.
+    pub fn submit_hint(&self, tx: Transaction, hint: PriorityHint) -> bool {
+        let mut guard = self.pool.lock().unwrap();
+        let correct = match hint {
+            PriorityHint::Highest => guard.last().is_none_or(|highest| tx >= *highest),
+            PriorityHint::Lowest => guard.first().is_none_or(|lowest| tx <= *lowest),
+        };
+        match (correct, hint) {
+            (true, PriorityHint::Highest) => guard.push(tx),
+            (true, PriorityHint::Lowest) => guard.insert(0, tx),
+            (false, _) => {
+                guard.push(tx);
+                guard.sort();
+            }
+        }
+        correct
+    }
 }
 
 impl Mempool for NaivePool {
@@ -34,6 +65,11 @@ impl Mempool for NaivePool {
         drained.reverse(); // bring highest priority to the front
         drained
     }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        let guard = self.pool.lock().unwrap();
+        guard.iter().find(|tx| tx.id == id).cloned()
+    }
 }
 
 #[cfg(test)]
@@ -42,26 +78,58 @@ mod test_suite {
 
     use super::NaivePool;
 
-    struct NaiveTester;
-
-    impl suite::Tester<NaivePool> for NaiveTester {
-        fn create_mempool(&self) -> NaivePool {
-            NaivePool::new(50000)
-        }
-    }
+    mempool::mempool_test_suite!(NaivePool::new(50000));
 
     #[test]
-    fn ordering_by_gas_price() {
-        suite::test_ordering_by_gas_price(NaiveTester);
+    fn get_by_id() {
+        suite::test_get_by_id(|| NaivePool::new(50000));
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use mempool::{Mempool, Transaction};
+
+    use super::{NaivePool, PriorityHint};
 
     #[test]
-    fn concurrent_submit() {
-        suite::test_concurrent_submit(NaiveTester);
+    fn correct_hints_skip_the_sort() {
+        let pool = NaivePool::new(10);
+        pool.submit(Transaction::with_empty_load("mid", 50, 1));
+
+        assert!(pool.submit_hint(
+            Transaction::with_empty_load("high", 60, 1),
+            PriorityHint::Highest
+        ));
+        assert!(pool.submit_hint(
+            Transaction::with_empty_load("higher", 70, 1),
+            PriorityHint::Highest
+        ));
+        assert!(pool.submit_hint(
+            Transaction::with_empty_load("low", 40, 1),
+            PriorityHint::Lowest
+        ));
+
+        let ids: Vec<_> = pool.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["higher", "high", "mid", "low"]);
     }
 
     #[test]
-    fn concurrent_submit_and_drain() {
-        suite::test_concurrent_submit_and_drain(NaiveTester);
+    fn wrong_hint_falls_back_to_sort() {
+        let pool = NaivePool::new(10);
+        pool.submit(Transaction::with_empty_load("high", 60, 1));
+        pool.submit(Transaction::with_empty_load("low", 40, 1));
+
+        assert!(!pool.submit_hint(
+            Transaction::with_empty_load("mid", 50, 1),
+            PriorityHint::Highest
+        ));
+        assert!(!pool.submit_hint(
+            Transaction::with_empty_load("top", 70, 1),
+            PriorityHint::Lowest
+        ));
+
+        let ids: Vec<_> = pool.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["top", "high", "mid", "low"]);
     }
 }
@@ -1,53 +1,112 @@
-use std::hint::black_box;
-use std::time::Instant;
+use std::{
+    hint::black_box,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use criterion::{Criterion, criterion_group, criterion_main};
-use mempool::{Mempool, Transaction};
-use sync::LockedQueue;
-
-fn create_tx(gas_price: u64) -> Transaction {
-    Transaction {
-        id: String::new(),
-        gas_price,
-        timestamp: Instant::now().elapsed().as_millis() as u64,
-        payload: vec![],
-    }
+use mempool::{
+    Mempool,
+    test::bench::{bench_mempool, create_tx},
+};
+use sync::{DrainLockMode, LazyHeapQueue, LockedQueue, ParkingLotQueue};
+
+fn sync_locks(c: &mut Criterion) {
+    bench_mempool(c, "sync_locks", LockedQueue::new);
+    bench_mempool(c, "parking_lot", ParkingLotQueue::new);
+}
+
+fn contended_submit_drain(c: &mut Criterion) {
+    bench_contended(
+        c,
+        "sync_locks contended_submit_drain",
+        LockedQueue::new(50_000),
+    );
+    bench_contended(
+        c,
+        "parking_lot contended_submit_drain",
+        ParkingLotQueue::new(50_000),
+    );
 }
 
-fn submit_drain(c: &mut Criterion) {
-    let pool = LockedQueue::new(50_000);
+/// Submits and drains from several threads at once to measure the overhead of the lock under contention.
+fn bench_contended(c: &mut Criterion, name: &str, pool: impl Mempool) {
+    const THREADS: u64 = 4;
+    const OPS_PER_THREAD: u64 = 250;
 
-    c.bench_function("sync_locks submit_drain", |b| {
+    c.bench_function(name, |b| {
         b.iter(|| {
-            pool.submit(create_tx(black_box(100)));
-            let drained = pool.drain(5);
-            assert_eq!(drained.len(), 1);
-            assert_eq!(drained[0].gas_price, 100);
+            std::thread::scope(|s| {
+                for thread in 0..THREADS {
+                    let pool = &pool;
+                    s.spawn(move || {
+                        for i in 0..OPS_PER_THREAD {
+                            pool.submit(create_tx(black_box(thread * OPS_PER_THREAD + i)));
+                            black_box(pool.drain(1));
+                        }
+                    });
+                }
+            })
         })
     });
 }
 
-fn submit_high_priority_on_large_queue(c: &mut Criterion) {
-    let pool = LockedQueue::new(500_000);
-    // -- Prepare large pool
-    let mut gas_price = 0;
-    for _ in 0..50_000 {
-        let tx = create_tx(gas_price);
-        pool.submit(black_box(tx));
+/// Measures the latency of single submits while another thread keeps refilling and draining large batches.
+fn submit_during_large_drain(c: &mut Criterion) {
+    const DRAIN_BATCH: u64 = 10_000;
 
-        gas_price += 1;
-    }
-    std::thread::sleep(std::time::Duration::from_millis(8_000));
-    c.bench_function("sync_locks submit_high_priority_on_large_queue", |b| {
-        b.iter(|| {
-            let tx = create_tx(black_box(gas_price));
-            pool.submit(tx);
+    for mode in [DrainLockMode::Reserve, DrainLockMode::Snapshot] {
+        let pool = LockedQueue::new(2 * DRAIN_BATCH as usize).with_drain_lock_mode(mode);
+        let stop = AtomicBool::new(false);
 
-            let drained = pool.drain(1);
-            assert_eq!(drained[0].gas_price, gas_price); //<-- should equal the last one added (highest gas price)
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                while !stop.load(Ordering::Relaxed) {
+                    for i in 0..DRAIN_BATCH {
+                        pool.submit(create_tx(i));
+                    }
+                    black_box(pool.drain(DRAIN_BATCH as usize));
+                }
+            });
+
+            c.bench_function(
+                &format!("sync_locks submit_during_large_drain {mode:?}"),
+                |b| {
+                    let mut i = 0;
+                    b.iter(|| {
+                        pool.submit(create_tx(black_box(i)));
+                        i += 1;
+                    })
+                },
+            );
+            stop.store(true, Ordering::Relaxed);
         });
+    }
+}
+
+/// Submits many transactions of scrambled priority before draining them all at once.
+fn submit_heavy(c: &mut Criterion) {
+    bench_submit_heavy(c, "sync_locks submit_heavy", LockedQueue::new(10_000));
+    bench_submit_heavy(c, "lazy_heap submit_heavy", LazyHeapQueue::new(10_000));
+}
+
+fn bench_submit_heavy(c: &mut Criterion, name: &str, pool: impl Mempool) {
+    const SUBMITS: u64 = 10_000;
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            for i in 0..SUBMITS {
+                pool.submit(create_tx(black_box(i * 7_919 % SUBMITS)));
+            }
+            black_box(pool.drain(SUBMITS as usize));
+        })
     });
 }
 
-criterion_group!(benches, submit_drain, submit_high_priority_on_large_queue);
+criterion_group!(
+    benches,
+    sync_locks,
+    contended_submit_drain,
+    submit_during_large_drain,
+    submit_heavy
+);
 criterion_main!(benches);
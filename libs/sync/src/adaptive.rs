@@ -0,0 +1,83 @@
+use std::{collections::BinaryHeap, sync::Mutex};
+
+use mempool::{Mempool, Transaction};
+
+/// Internal data structure of the [`AdaptiveQueue`].
+#[derive(Debug)]
+enum Storage {
+    /// Sorted in ascending priority, so the next transaction to drain is the last one.
+    Sorted(Vec<Transaction>),
+    Heap(BinaryHeap<Transaction>),
+}
+
+/// Priority queue that keeps small pools in a sorted [`Vec`] and promotes them to a [`BinaryHeap`] as soon
+/// as they hold more than `promotion_threshold` transactions.
+///
+/// Small pools avoid the overhead of the heap: a drain is a single split of the vector. Submits require a
+/// binary search and a shift of the vector, which is cheap as long as the pool is small.
+///
+/// # Note
+/// A promoted queue stays a heap, even if it is drained below the threshold again.
+#[derive(Debug)]
+pub struct AdaptiveQueue {
+    storage: Mutex<Storage>,
+    promotion_threshold: usize,
+}
+
+impl AdaptiveQueue {
+    pub const DEFAULT_PROMOTION_THRESHOLD: usize = 64;
+
+    pub fn new(promotion_threshold: usize) -> Self {
+        Self {
+            storage: Mutex::new(Storage::Sorted(Vec::with_capacity(promotion_threshold))),
+            promotion_threshold,
+        }
+    }
+
+    /// Returns `true` once the queue has been promoted to a heap.
+    pub fn is_promoted(&self) -> bool {
+        matches!(*self.storage.lock().unwrap(), Storage::Heap(_))
+    }
+}
+
+impl Default for AdaptiveQueue {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_PROMOTION_THRESHOLD)
+    }
+}
+
+impl Mempool for AdaptiveQueue {
+    fn submit(&self, tx: Transaction) {
+        let mut storage = self.storage.lock().unwrap();
+        match &mut *storage {
+            Storage::Sorted(sorted) => {
+                let idx = sorted.partition_point(|queued| *queued <= tx);
+                sorted.insert(idx, tx);
+                if sorted.len() > self.promotion_threshold {
+                    *storage = Storage::Heap(BinaryHeap::from(std::mem::take(sorted)));
+                }
+            }
+            Storage::Heap(heap) => heap.push(tx),
+        }
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let mut storage = self.storage.lock().unwrap();
+        match &mut *storage {
+            Storage::Sorted(sorted) => {
+                let mut items = sorted.split_off(sorted.len().saturating_sub(n));
+                items.reverse();
+                items
+            }
+            Storage::Heap(heap) => (0..n).map_while(|_| heap.pop()).collect(),
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        let storage = self.storage.lock().unwrap();
+        match &*storage {
+            Storage::Sorted(sorted) => sorted.iter().find(|tx| tx.id == id).cloned(),
+            Storage::Heap(heap) => heap.iter().find(|tx| tx.id == id).cloned(),
+        }
+    }
+}
@@ -5,6 +5,7 @@ use std::{
         Arc, Condvar, Mutex,
         atomic::{AtomicBool, Ordering},
     },
+    thread::Thread,
     time::Duration,
 };
 
@@ -12,13 +13,20 @@ use anyhow::{anyhow, bail};
 use crossbeam::channel::{Receiver, Sender, TryRecvError};
 use mempool::{Mempool, Transaction};
 
+/// Name of the thread the [`Storage`] runner is spawned on, so it can be told apart in profilers.
+const RUNNER_THREAD_NAME: &str = "mempool-runner";
+
 struct StorageFactory;
 
 impl StorageFactory {
     /// Creates a new [`Storage`] instance with given `capacity` that is ready to submit and drain
     /// items from its queue.
-    fn new_queue<T: Debug + Ord + Send + 'static>(capacity: usize) -> Channels<T> {
-        Storage::start(capacity)
+    /// If `core_id` is given, the runner thread tries to pin itself to that core.
+    fn new_queue<T: Debug + Ord + Send + 'static>(
+        capacity: usize,
+        core_id: Option<usize>,
+    ) -> Channels<T> {
+        Storage::start(capacity, core_id)
     }
 }
 
@@ -34,6 +42,9 @@ struct Storage<T: Debug + Ord> {
 
     drain_command_sink: Receiver<(usize, Sender<Vec<T>>)>,
 
+    /// Commands to stop the runner and hand the remaining items back.
+    stop_command_sink: Receiver<Sender<Vec<T>>>,
+
     running: Arc<AtomicBool>,
 }
 
@@ -41,13 +52,16 @@ struct Storage<T: Debug + Ord> {
 struct Channels<T: Debug + Ord> {
     item_source: Sender<T>,
     drain_command_source: Sender<(usize, Sender<Vec<T>>)>,
+    stop_command_source: Sender<Sender<Vec<T>>>,
     queue_running: Arc<AtomicBool>,
+    runner: Thread,
 }
 
 impl<T: Debug + Ord + Send + 'static> Storage<T> {
-    fn start(capacity: usize) -> Channels<T> {
+    fn start(capacity: usize, core_id: Option<usize>) -> Channels<T> {
         let (tx, rx) = crossbeam::channel::unbounded();
         let (tx_command, rx_command) = crossbeam::channel::bounded(1);
+        let (tx_stop, rx_stop) = crossbeam::channel::bounded(1);
         let running = Arc::new(AtomicBool::new(true));
         let queue_running = Arc::clone(&running);
 
@@ -55,17 +69,26 @@ impl<T: Debug + Ord + Send + 'static> Storage<T> {
             max_heap: BinaryHeap::with_capacity(capacity),
             submitter_sink: rx,
             drain_command_sink: rx_command,
+            stop_command_sink: rx_stop,
             running,
         };
 
         let wait_for_runner = Arc::new((Mutex::new(false), Condvar::new()));
         let spun_up_notifier = Arc::clone(&wait_for_runner);
 
-        std::thread::spawn(move || {
-            if let Err(e) = storage.run(spun_up_notifier) {
-                eprintln!("Error! Queue has shut down: {e}");
-            }
-        });
+        let handle = std::thread::Builder::new()
+            .name(RUNNER_THREAD_NAME.to_string())
+            .spawn(move || {
+                if let Some(core_id) = core_id
+                    && let Err(e) = pin_to_core(core_id)
+                {
+                    eprintln!("Warn! {e}");
+                }
+                if let Err(e) = storage.run(spun_up_notifier) {
+                    eprintln!("Error! Queue has shut down: {e}");
+                }
+            })
+            .expect("The OS can spawn the mempool runner thread.");
         // Wait for the mempool runner to start up.
         let (lock, cvar) = &*wait_for_runner;
         let mut started = lock
@@ -78,7 +101,9 @@ impl<T: Debug + Ord + Send + 'static> Storage<T> {
         Channels {
             item_source: tx,
             drain_command_source: tx_command,
+            stop_command_source: tx_stop,
             queue_running,
+            runner: handle.thread().clone(),
         }
     }
 
@@ -90,6 +115,9 @@ impl<T: Debug + Ord + Send + 'static> Storage<T> {
         while self.running.load(Ordering::Relaxed) {
             self.submit_or_continue()?;
             self.drain_or_continue()?;
+            if self.stop_or_continue()? {
+                break;
+            }
 
             // Throttle thread usage. Could also test "parking" the thread.
             std::thread::sleep(Duration::from_nanos(10));
@@ -128,8 +156,10 @@ impl<T: Debug + Ord + Send + 'static> Storage<T> {
             Err(TryRecvError::Disconnected) => bail!("Drain command channel is disconnected"),
         };
 
+        // -- harvest the pending submits first, so a drain sees every submit that returned before it
+        self.max_heap.extend(self.submitter_sink.try_iter());
         // Is there a more efficient way of draining the std binary heap?
-        let mut items = Vec::with_capacity(count);
+        let mut items = Vec::with_capacity(count.min(self.max_heap.len()));
         for _ in 0..count {
             let Some(value) = self.max_heap.pop() else {
                 break;
@@ -141,6 +171,53 @@ impl<T: Debug + Ord + Send + 'static> Storage<T> {
             .send(items)
             .map_err(|_| anyhow!("Drain channel is disconnected"))
     }
+
+    /// Hands the remaining items back in priority order if a stop is requested, including the items that are
+    /// still in the submittance channel.
+    /// Returns `true` if the runner has to stop.
+    fn stop_or_continue(&mut self) -> anyhow::Result<bool> {
+        let tx_remaining = match self.stop_command_sink.try_recv() {
+            Ok(tx_remaining) => tx_remaining,
+            Err(TryRecvError::Empty) => return Ok(false),
+            Err(TryRecvError::Disconnected) => bail!("Stop command channel is disconnected"),
+        };
+
+        self.max_heap.extend(self.submitter_sink.try_iter());
+        let mut remaining = std::mem::take(&mut self.max_heap).into_sorted_vec();
+        remaining.reverse();
+        tx_remaining
+            .send(remaining)
+            .map_err(|_| anyhow!("Stop channel is disconnected"))?;
+        Ok(true)
+    }
+}
+
+/// Pins the calling thread to the core with index `core_id`.
+#[cfg(all(feature = "affinity", target_os = "linux"))]
+fn pin_to_core(core_id: usize) -> anyhow::Result<()> {
+    if core_id >= libc::CPU_SETSIZE as usize {
+        bail!("Cannot pin runner to core {core_id}: the core id is out of range");
+    }
+    // SAFETY: `cpu_set_t` is a plain bit set, for which all zeroes is the valid empty set.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    // SAFETY: `core_id` was checked against the size of the set above, and a pid of 0 targets
+    // the calling thread.
+    let rc = unsafe {
+        libc::CPU_SET(core_id, &mut set);
+        libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set)
+    };
+    if rc != 0 {
+        bail!(
+            "Cannot pin runner to core {core_id}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(all(feature = "affinity", target_os = "linux")))]
+fn pin_to_core(core_id: usize) -> anyhow::Result<()> {
+    bail!("Cannot pin runner to core {core_id}: requires the `affinity` feature on Linux")
 }
 
 #[derive(Debug)]
@@ -199,12 +276,48 @@ impl Mempool for Queue<Transaction> {
 
 impl Queue<Transaction> {
     pub fn new(capacity: usize) -> Self {
-        let channels = StorageFactory::new_queue(capacity);
+        let channels = StorageFactory::new_queue(capacity, None);
         Self { channels }
     }
 
+    /// Same as [`Queue::new`], but pins the runner thread to the core with index `core_id`
+    /// to get more consistent benchmark numbers.
+    /// Pinning needs the `affinity` feature and is only supported on Linux; otherwise, or if
+    /// pinning fails, a warning is logged and the runner is scheduled as usual.
+    pub fn with_affinity(capacity: usize, core_id: usize) -> Self {
+        let channels = StorageFactory::new_queue(capacity, Some(core_id));
+        Self { channels }
+    }
+
+    /// Handle of the thread the queue's runner is executing on, e.g. to look up its name or id.
+    pub fn runner_thread(&self) -> &Thread {
+        &self.channels.runner
+    }
+
     pub fn stop(self) {
         self.channels.queue_running.store(false, Ordering::Relaxed);
         // Could wait here until the thread is torn down.
     }
+
+    /// Stops the runner like [`Queue::stop`], but hands back the transactions that are still queued in priority
+    /// order before the runner exits, instead of dropping them. Transactions submitted before the call are
+    /// included.
+    pub fn stop_collecting(self) -> Vec<Transaction> {
+        let (tx_remaining, rx_remaining) = crossbeam::channel::bounded(1);
+        if self
+            .channels
+            .stop_command_source
+            .send(tx_remaining)
+            .is_err()
+        {
+            eprintln!("Error: Could not stop the queue, the command channel is closed!");
+            return vec![];
+        }
+        rx_remaining.recv().unwrap_or_else(|_| {
+            eprintln!(
+                "Error: Could not collect the remaining transactions, the stop channel is closed!"
+            );
+            vec![]
+        })
+    }
 }
@@ -0,0 +1,107 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use mempool::{Mempool, Transaction};
+
+/// Internal data structure of the [`IndexedQueue`]. Both indices share the same transactions.
+#[derive(Debug, Default)]
+struct Index {
+    /// Ascending priority, so the next transaction to drain is the last one.
+    by_priority: BTreeSet<Arc<Transaction>>,
+    by_id: HashMap<String, Arc<Transaction>>,
+}
+
+impl Index {
+    fn remove(&mut self, id: &str) -> Option<Transaction> {
+        let tx = self.by_id.remove(id)?;
+        self.by_priority.remove(&tx);
+        Some(Arc::unwrap_or_clone(tx))
+    }
+}
+
+/// Priority queue that keeps an additional index by transaction id, so it can drain by priority as well as look
+/// up and remove transactions by id, all in `O(log n)` or better.
+///
+/// The transactions are ordered in a [`BTreeSet`] and indexed by id in a [`HashMap`], which are updated
+/// together under a single lock. Transactions are drained in the order of their [`Ord`] implementation, so
+/// transactions of equal gas price and timestamp are drained by id.
+///
+/// # Note
+/// Ids are unique within the queue: submitting a transaction with the id of a queued one replaces it.
+#[derive(Debug)]
+pub struct IndexedQueue {
+    index: Mutex<Index>,
+}
+
+impl IndexedQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            index: Mutex::new(Index {
+                by_id: HashMap::with_capacity(capacity),
+                ..Index::default()
+            }),
+        }
+    }
+
+    /// Removes the transaction with the given `id` and returns it, if it is queued.
+    pub fn remove(&self, id: &str) -> Option<Transaction> {
+        self.index.lock().unwrap().remove(id)
+    }
+
+    /// Checks whether a transaction with the given `id` is queued.
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.lock().unwrap().by_id.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.lock().unwrap().by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Debug helper that confirms that the priority index and the id index hold exactly the same entries.
+    #[cfg(any(test, debug_assertions))]
+    pub fn verify_index_consistency(&self) -> bool {
+        let index = self.index.lock().unwrap();
+        index.by_priority.len() == index.by_id.len()
+            && index.by_id.iter().all(|(id, tx)| {
+                tx.id == *id
+                    && index
+                        .by_priority
+                        .get(tx)
+                        .is_some_and(|found| Arc::ptr_eq(found, tx))
+            })
+    }
+}
+
+impl Mempool for IndexedQueue {
+    fn submit(&self, tx: Transaction) {
+        let mut index = self.index.lock().unwrap();
+        index.remove(&tx.id);
+        let tx = Arc::new(tx);
+        index.by_id.insert(tx.id.clone(), Arc::clone(&tx));
+        index.by_priority.insert(tx);
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let mut index = self.index.lock().unwrap();
+        let mut items = Vec::with_capacity(n.min(index.by_id.len()));
+        while items.len() < n {
+            let Some(tx) = index.by_priority.pop_last() else {
+                break;
+            };
+            index.by_id.remove(&tx.id);
+            items.push(Arc::unwrap_or_clone(tx));
+        }
+        items
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        let index = self.index.lock().unwrap();
+        index.by_id.get(id).map(|tx| Transaction::clone(tx))
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+
+use mempool::{Mempool, Transaction};
+
+/// Internal data structure of the [`LazyHeapQueue`].
+#[derive(Debug)]
+struct Storage {
+    /// Sorted in ascending priority while `sorted` is `true`, so the next transaction to drain is the last one.
+    items: Vec<Transaction>,
+    sorted: bool,
+}
+
+/// Priority queue for append-heavy workloads that are drained rarely, e.g. in bulk.
+///
+/// Submits append to an unsorted [`Vec`] in `O(1)`. The vector is only sorted on the first drain after
+/// submissions, and stays sorted for subsequent drains until the next submit. Submits in ascending priority
+/// keep the vector sorted.
+///
+/// # Note
+/// Interleaving single submits with drains sorts the vector on every drain, which is slower than a heap.
+#[derive(Debug)]
+pub struct LazyHeapQueue {
+    storage: Mutex<Storage>,
+}
+
+impl LazyHeapQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            storage: Mutex::new(Storage {
+                items: Vec::with_capacity(capacity),
+                sorted: true,
+            }),
+        }
+    }
+}
+
+impl Mempool for LazyHeapQueue {
+    fn submit(&self, tx: Transaction) {
+        let mut storage = self.storage.lock().unwrap();
+        storage.sorted &= storage.items.last().is_none_or(|last| *last <= tx);
+        storage.items.push(tx);
+    }
+
+    /// Sorts the queue if transactions have been submitted since the last drain. The sort is cheap if only a
+    /// few transactions have been submitted since, as the vector is mostly sorted then.
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let mut storage = self.storage.lock().unwrap();
+        if !storage.sorted {
+            storage.items.sort();
+            storage.sorted = true;
+        }
+        let drain_start = storage.items.len().saturating_sub(n);
+        let mut items = storage.items.split_off(drain_start);
+        items.reverse(); // bring highest priority to the front
+        items
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        let storage = self.storage.lock().unwrap();
+        storage.items.iter().find(|tx| tx.id == id).cloned()
+    }
+}
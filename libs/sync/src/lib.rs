@@ -1,6 +1,16 @@
+mod adaptive;
 mod channel_based;
+mod indexed;
+mod lazy_heap;
 mod lock_based;
+mod parking_lot_based;
 mod test;
 
+pub use adaptive::AdaptiveQueue;
 pub use channel_based::Queue as ChanneledQueue;
-pub use lock_based::LockedQueue;
+pub use indexed::IndexedQueue;
+pub use lazy_heap::LazyHeapQueue;
+pub use lock_based::{
+    DrainLockMode, EvictionPolicy, LockFailure, LockedQueue, Observer, Reservation,
+};
+pub use parking_lot_based::ParkingLotQueue;
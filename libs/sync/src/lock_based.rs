@@ -1,41 +1,1530 @@
 use std::{
-    collections::BinaryHeap,
+    collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque},
     fmt::Debug,
-    sync::{Arc, Mutex},
+    fs::File,
+    io::{BufRead, Write},
+    ops::RangeInclusive,
+    path::Path,
+    sync::{
+        Arc, Mutex, MutexGuard, TryLockError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use mempool::{Mempool, Transaction};
+use anyhow::Context;
+use crossbeam::channel::Sender;
+use mempool::{DrainLatency, IdValidator, Mempool, MempoolEvent, RejectReason, Transaction};
+use rand::{Rng, seq::IteratorRandom};
+
+/// Panic message of the operations that wait for the lock without a timeout, which only fail on a poisoned lock.
+const BLOCKING_LOCK: &str = "lock of the queue should not be poisoned";
+/// First and largest sleep between two attempts of a timed operation to acquire the lock of the queue.
+const LOCK_BACKOFF_START: Duration = Duration::from_micros(10);
+const LOCK_BACKOFF_MAX: Duration = Duration::from_millis(1);
+
+/// Transaction handed back along with the error if a timed submit does not acquire the lock of the queue in time.
+pub type LockFailure = (Box<Transaction>, anyhow::Error);
+
+/// Selects how long [`Mempool::drain`] of the [`LockedQueue`] holds the lock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DrainLockMode {
+    /// Hold the lock for the whole drain, including the expiry check and the bookkeeping of every drained
+    /// transaction. Expired transactions are replaced by the next candidates, so a drain returns `n` transactions
+    /// as long as enough valid ones are queued.
+    #[default]
+    Reserve,
+    /// Only hold the lock while the top `n` candidates are taken out of the heap. A heap of at most `n`
+    /// transactions is taken as a whole in `O(1)` and sorted after the lock is released. The expiry check and the
+    /// release of the per-sender limits happen after the lock is released as well, so submitters are blocked for a
+    /// shorter time.
+    ///
+    /// In exchange, expired candidates are discarded without replacement, so a drain may return fewer than `n`
+    /// transactions although more valid ones are queued. Submits of a sender may also be rejected by the
+    /// per-sender limit until the drain has released the drained transactions of that sender.
+    ///
+    /// Queues with an inversion window or a gas price cap drain like [`DrainLockMode::Reserve`], as their drain
+    /// order depends on the bookkeeping.
+    Snapshot,
+}
+
+/// Outcome of a checked submit: the value `R` if the transaction is queued, otherwise the transaction along with
+/// the reason of its rejection.
+type Admission<R> = Result<R, (Transaction, RejectReason)>;
+
+/// Callback of [`LockedQueue::with_observer`].
+pub type Observer = Box<dyn Fn(MempoolEvent) + Send + Sync>;
+
+/// Selects the transaction that is removed when a transaction is submitted to a full queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the transaction with the lowest priority. If the submitted transaction does not have a higher
+    /// priority than all queued transactions, it is rejected instead.
+    LowestPriority,
+    /// Evict the transaction with the earliest `timestamp`.
+    Oldest,
+    /// Evict a uniformly chosen transaction.
+    Random,
+}
 
 #[derive(Debug)]
 pub struct LockedQueue<T: Debug + Ord> {
     pub storage: Arc<Mutex<BinaryHeap<T>>>,
+    /// Moving average of recent drain operations, used to estimate drain delays.
+    drain_latency: DrainLatency,
+    /// Maximum number of items in the queue. Unbounded if `None`.
+    max_len: Option<usize>,
+    /// Makes room for new items when the queue is full. Submits to a full queue are rejected if `None`.
+    eviction: Option<EvictionPolicy>,
+    /// Maximum payload size in bytes of submitted transactions. Unlimited if `None`.
+    max_payload_size: Option<usize>,
+    /// Minimum gas price of submitted transactions. Unlimited if `None`.
+    min_gas_price: Option<u64>,
+    /// Format that the ids of submitted transactions must match. Any id is accepted if `None`.
+    id_validator: Option<Box<dyn IdValidator>>,
+    /// Receives the transactions rejected by [`Mempool::submit`] along with the reason of the rejection.
+    dead_letter: Option<Sender<(Transaction, RejectReason)>>,
+    /// Number of transactions discarded on drain because their deadline has passed.
+    expired: AtomicU64,
+    /// Transactions that waited longer than this window are drained ahead of their gas tier.
+    /// Strict priority order if `None`.
+    inversion_window: Option<Duration>,
+    /// Arrival order of the queued transactions. Only tracked if there is an `inversion_window`.
+    arrivals: Mutex<Arrivals>,
+    /// Maximum number of queued transactions per sender. Unlimited if `None`.
+    max_per_sender: Option<usize>,
+    /// Number of queued transactions by sender. Only tracked if there is a `max_per_sender` limit.
+    sender_counts: Mutex<HashMap<String, usize>>,
+    /// Gas prices above this cap are treated as equal to the cap when ordering drains. Uncapped if `None`.
+    max_effective_gas_price: Option<u64>,
+    drain_lock_mode: DrainLockMode,
+    /// Receives the lifecycle events of all transactions. No events are created if `None`.
+    observer: Option<ObserverSlot>,
+    /// Number of reservations handed out by [`LockedQueue::drain_reserve`], which is the generation of the next one.
+    reservations: AtomicU64,
+    /// Generations of the reservations that are neither confirmed nor returned yet.
+    outstanding: Mutex<HashSet<u64>>,
+    /// Number of times the storage has been locked for a mutation, so a cached snapshot can tell whether it is stale.
+    mutations: AtomicU64,
+    /// Cached result of [`LockedQueue::shared_snapshot`] along with the number of `mutations` it was built at.
+    snapshot: Mutex<Option<(u64, Arc<[T]>)>>,
+}
+
+/// Holds the [`Observer`] of a [`LockedQueue`], which cannot be debug printed itself.
+struct ObserverSlot(Observer);
+
+impl Debug for ObserverSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Observer")
+    }
+}
+
+/// Priority override and effective gas price of a transaction. Transactions of the same tier only differ in their
+/// timestamp and id, so an inversion window or the gas price cap may reorder them.
+type Tier = (Option<u64>, u64);
+
+/// Arrival order of the queued transactions per [`Tier`], which lets [`LockedQueue::pop_next`] find the overdue
+/// transaction of a tier without a scan of the queue.
+#[derive(Debug, Default)]
+struct Arrivals {
+    /// Arrivals of every tier in arrival order. Arrivals at the same instant are told apart by their sequence
+    /// number.
+    by_tier: HashMap<Tier, BTreeSet<(Instant, u64, [u8; 32])>>,
+    /// Arrivals of the queued transactions by hash, earliest first, as identical transactions may be queued
+    /// several times.
+    by_hash: HashMap<[u8; 32], VecDeque<(Instant, u64)>>,
+    /// Sequence number of the next arrival.
+    next_seq: u64,
+}
+
+impl Arrivals {
+    /// Records the arrival of the transaction with the given `hash` in the `tier` at `instant`.
+    fn record(&mut self, hash: [u8; 32], tier: Tier, instant: Instant) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.by_tier
+            .entry(tier)
+            .or_default()
+            .insert((instant, seq, hash));
+        self.by_hash
+            .entry(hash)
+            .or_default()
+            .push_back((instant, seq));
+    }
+
+    /// Forgets the earliest arrival of the transaction with the given `hash` in the `tier`.
+    fn remove(&mut self, hash: [u8; 32], tier: Tier) {
+        let Some(instants) = self.by_hash.get_mut(&hash) else {
+            return;
+        };
+        let Some((instant, seq)) = instants.pop_front() else {
+            return;
+        };
+        if instants.is_empty() {
+            self.by_hash.remove(&hash);
+        }
+        if let Some(arrivals) = self.by_tier.get_mut(&tier) {
+            arrivals.remove(&(instant, seq, hash));
+            if arrivals.is_empty() {
+                self.by_tier.remove(&tier);
+            }
+        }
+    }
+
+    /// The earliest arrival in the `tier` along with the hash of its transaction.
+    fn first(&self, tier: Tier) -> Option<(Instant, [u8; 32])> {
+        let (instant, _, hash) = self.by_tier.get(&tier)?.first()?;
+        Some((*instant, *hash))
+    }
+
+    fn clear(&mut self) {
+        self.by_tier.clear();
+        self.by_hash.clear();
+    }
+}
+
+/// Transactions drained by [`LockedQueue::drain_reserve`] that are either removed for good with
+/// [`Reservation::confirm`] or handed back with [`Reservation::return_to_pool`].
+///
+/// A reservation that is dropped without being confirmed returns its transactions to the pool.
+#[derive(Debug)]
+pub struct Reservation<'a> {
+    queue: &'a LockedQueue<Transaction>,
+    transactions: Vec<Transaction>,
+    generation: u64,
+    /// Time it took to take the transactions out of the queue, recorded as drain latency on confirm.
+    drain_duration: Duration,
+}
+
+impl Reservation<'_> {
+    /// The reserved transactions in priority order.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Token that tells this reservation apart from all other reservations of its queue, see
+    /// [`LockedQueue::is_outstanding`].
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Finalizes the removal of the reserved transactions and hands them over to the caller. Only now the
+    /// transactions are reported as drained and the drain latency is recorded. Transactions whose `valid_until`
+    /// deadline passed while they were reserved are discarded as in [`Mempool::drain`].
+    pub fn confirm(mut self) -> Vec<Transaction> {
+        let now_us = unix_time_us();
+        let mut transactions = std::mem::take(&mut self.transactions);
+        transactions.retain(|tx| {
+            let expired = tx.is_expired(now_us);
+            if expired {
+                self.queue.expired.fetch_add(1, Ordering::Relaxed);
+            }
+            !expired
+        });
+
+        self.queue
+            .report_drained(self.drain_duration, &transactions);
+        transactions
+    }
+
+    /// Puts the reserved transactions back into the pool, e.g. after the executor failed to process them.
+    /// The limits of the queue are not applied, as the transactions were admitted before.
+    pub fn return_to_pool(self) {
+        // -- dropping the reservation returns the transactions
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.queue
+            .outstanding
+            .lock()
+            .unwrap()
+            .remove(&self.generation);
+        if self.transactions.is_empty() {
+            return;
+        }
+        let mut storage = self.queue.lock_storage(None).expect(BLOCKING_LOCK);
+        for tx in self.transactions.drain(..) {
+            self.queue.push_unchecked(&mut storage, tx);
+        }
+    }
 }
 
 impl<T: Debug + Ord> LockedQueue<T> {
     pub fn new(capacity: usize) -> Self {
         Self {
             storage: Arc::new(Mutex::new(BinaryHeap::with_capacity(capacity))),
+            drain_latency: DrainLatency::new(),
+            max_len: None,
+            eviction: None,
+            max_payload_size: None,
+            min_gas_price: None,
+            id_validator: None,
+            dead_letter: None,
+            expired: AtomicU64::new(0),
+            inversion_window: None,
+            arrivals: Mutex::new(Arrivals::default()),
+            max_per_sender: None,
+            sender_counts: Mutex::new(HashMap::new()),
+            max_effective_gas_price: None,
+            drain_lock_mode: DrainLockMode::Reserve,
+            observer: None,
+            reservations: AtomicU64::new(0),
+            outstanding: Mutex::new(HashSet::new()),
+            mutations: AtomicU64::new(0),
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    /// Creates a queue that holds at most `max_len` items. Submits to a full queue are rejected.
+    pub fn bounded(max_len: usize) -> Self {
+        Self {
+            max_len: Some(max_len),
+            ..Self::new(max_len)
+        }
+    }
+
+    /// Acquires the lock of the storage to mutate it, failing if it is poisoned. With a `timeout`, `try_lock` is
+    /// spun until the lock is acquired or the timeout has passed. Once the lock is held, the mutation is counted,
+    /// which invalidates the cached [`LockedQueue::shared_snapshot`].
+    fn lock_storage(
+        &self,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<MutexGuard<'_, BinaryHeap<T>>> {
+        let Some(timeout) = timeout else {
+            let storage = self
+                .storage
+                .lock()
+                .map_err(|_| anyhow::anyhow!("lock of the queue is poisoned"))?;
+            self.mutations.fetch_add(1, Ordering::Relaxed);
+            return Ok(storage);
+        };
+        let deadline = Instant::now() + timeout;
+        let mut backoff = LOCK_BACKOFF_START;
+        loop {
+            match self.storage.try_lock() {
+                Ok(storage) => {
+                    self.mutations.fetch_add(1, Ordering::Relaxed);
+                    return Ok(storage);
+                }
+                Err(TryLockError::Poisoned(_)) => anyhow::bail!("lock of the queue is poisoned"),
+                Err(TryLockError::WouldBlock) if Instant::now() >= deadline => {
+                    anyhow::bail!("could not acquire the lock of the queue within {timeout:?}")
+                }
+                // -- sleep with an exponentially growing backoff instead of spinning until the deadline
+                Err(TryLockError::WouldBlock) => {
+                    std::thread::sleep(
+                        backoff.min(deadline.saturating_duration_since(Instant::now())),
+                    );
+                    backoff = (backoff * 2).min(LOCK_BACKOFF_MAX);
+                }
+            }
         }
     }
 }
 
-impl Mempool for LockedQueue<Transaction> {
-    fn submit(&self, tx: Transaction) {
-        let mut storage = self.storage.lock().unwrap();
+impl<T: Debug + Ord> LockedQueue<T> {
+    /// Number of items the heap can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.storage.lock().unwrap().capacity()
+    }
+
+    /// Ratio of queued items to the [`capacity`](Self::capacity) of the heap, e.g. to tune the `capacity`
+    /// hint of the queue: a ratio that stays low hints at an over-allocated heap, a ratio near `1.0` at frequent
+    /// reallocations. A heap without capacity reports `0.0`.
+    pub fn capacity_utilization(&self) -> f64 {
+        let storage = self.storage.lock().unwrap();
+        match storage.capacity() {
+            0 => 0.0,
+            capacity => storage.len() as f64 / capacity as f64,
+        }
+    }
+}
+
+/// Removes the transaction of highest priority that matches `predicate` from the `storage`. The transactions ahead
+/// of it are popped and pushed back, which takes `O(k log n)` for `k` transactions ahead.
+fn take_first(
+    storage: &mut BinaryHeap<Transaction>,
+    predicate: impl Fn(&Transaction) -> bool,
+) -> Option<Transaction> {
+    let mut ahead = vec![];
+    let mut taken = None;
+    while let Some(tx) = storage.pop() {
+        if predicate(&tx) {
+            taken = Some(tx);
+            break;
+        }
+        ahead.push(tx);
+    }
+    storage.extend(ahead);
+    taken
+}
+
+/// Current unix time in microseconds, the unit of [`Transaction::valid_until`].
+fn unix_time_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time flowing forwards")
+        .as_micros() as u64
+}
+
+impl<T: Debug + Ord + Clone> LockedQueue<T> {
+    /// Returns a clone of the item of lowest priority, e.g. to decide whether a new item is worth an eviction.
+    ///
+    /// # Note
+    /// The heap only keeps track of its maximum, so the minimum is found by a scan of all queued items in `O(n)`.
+    pub fn min(&self) -> Option<T> {
+        self.storage.lock().unwrap().iter().min().cloned()
+    }
+
+    /// Calls `f` with every queued item in priority order without collecting them into a vector, e.g. for
+    /// analytics pipelines that stream over the queue. The items are visited by popping a clone of the heap.
+    ///
+    /// # Note
+    /// `f` runs while the lock of the queue is held, blocking producers and consumers in the meantime.
+    /// It must therefore be cheap and must not access the queue itself.
+    pub fn for_each_ordered(&self, mut f: impl FnMut(&T)) {
+        let storage = self.storage.lock().unwrap();
+        let mut heap = storage.clone();
+        while let Some(item) = heap.pop() {
+            f(&item);
+        }
+    }
+
+    /// Returns the queued items in priority order as a shared slice, e.g. for several readers that inspect the
+    /// same state of the queue. The slice is built once and cached, so further calls hand out the same
+    /// allocation until the queue is mutated.
+    ///
+    /// # Note
+    /// Building the slice sorts a clone of the heap in `O(n log n)` while the lock of the queue is held.
+    /// Mutations through the public `storage` field bypass the cache, which then keeps returning the stale slice.
+    pub fn shared_snapshot(&self) -> Arc<[T]> {
+        let storage = self.storage.lock().unwrap();
+        // -- mutations are only counted while the lock of the storage is held
+        let mutations = self.mutations.load(Ordering::Relaxed);
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if let Some((built_at, sorted)) = snapshot.as_ref()
+            && *built_at == mutations
+        {
+            return Arc::clone(sorted);
+        }
+        // `into_sorted_vec` sorts ascending, so the highest priority is moved to the front.
+        let mut sorted = storage.clone().into_sorted_vec();
+        sorted.reverse();
+        let sorted: Arc<[T]> = sorted.into();
+        *snapshot = Some((mutations, Arc::clone(&sorted)));
+        sorted
+    }
+
+    /// Debug helper that pops a clone of the heap and confirms that every popped element has at
+    /// least the priority of its successor according to `T`'s [`Ord`] implementation.
+    ///
+    /// Returns `false` if the ordering is violated, e.g. due to an inconsistent [`Ord`] implementation.
+    #[cfg(debug_assertions)]
+    pub fn verify_heap_invariant(&self) -> bool {
+        let mut heap = self.storage.lock().unwrap().clone();
+
+        let Some(mut previous) = heap.pop() else {
+            return true;
+        };
+        while let Some(next) = heap.pop() {
+            if previous < next {
+                return false;
+            }
+            previous = next;
+        }
+        true
+    }
+}
+
+impl LockedQueue<Transaction> {
+    /// Creates a queue that holds at most `max_len` transactions. Submits to a full queue evict a
+    /// transaction according to the given `policy`.
+    pub fn with_eviction(max_len: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            eviction: Some(policy),
+            ..Self::bounded(max_len)
+        }
+    }
+
+    /// Creates a queue that ships every transaction rejected by [`Mempool::submit`] to the dead-letter queue
+    /// `dlq` instead of dropping it, e.g. for inspection or a later retry.
+    pub fn with_dead_letter(capacity: usize, dlq: Sender<(Transaction, RejectReason)>) -> Self {
+        Self {
+            dead_letter: Some(dlq),
+            ..Self::new(capacity)
+        }
+    }
+
+    /// Creates a queue that reports every submit, drain, eviction and rejection to the `observer`, e.g. to feed
+    /// metrics. The observer is called after the lock of the queue has been released, so it may take its time.
+    ///
+    /// Every transaction that leaves the queue is reported. Transactions handed to a consumer, e.g. by
+    /// [`Mempool::drain`], [`LockedQueue::drain_streaming`], [`LockedQueue::drain_to_file`],
+    /// [`LockedQueue::split_at_gas_price`] or [`Reservation::confirm`], are reported as drained, and their drain
+    /// latency is recorded. Transactions that are discarded or moved elsewhere, e.g. by [`LockedQueue::remove_many`],
+    /// [`LockedQueue::demote_lowest`], [`Mempool::compact`] or an [`EvictionPolicy`], are reported as evicted.
+    pub fn with_observer(capacity: usize, observer: Observer) -> Self {
+        Self {
+            observer: Some(ObserverSlot(observer)),
+            ..Self::new(capacity)
+        }
+    }
+
+    /// Creates a queue that holds at most `max_per_sender` transactions of the same sender, which prevents a single
+    /// account from flooding the pool. Submits of a sender at its limit are rejected until some of its
+    /// transactions leave the queue. Transactions without a sender are not limited.
+    pub fn with_per_sender_limit(capacity: usize, max_per_sender: usize) -> Self {
+        Self::new(capacity).with_max_per_sender(max_per_sender)
+    }
+
+    /// Limits the number of queued transactions per sender to `max_per_sender`, see
+    /// [`LockedQueue::with_per_sender_limit`].
+    pub fn with_max_per_sender(self, max_per_sender: usize) -> Self {
+        Self {
+            max_per_sender: Some(max_per_sender),
+            ..self
+        }
+    }
+
+    /// Limits the number of queued transactions to `max_len`. Submits to a full queue are rejected.
+    pub fn with_max_len(self, max_len: usize) -> Self {
+        Self {
+            max_len: Some(max_len),
+            ..self
+        }
+    }
+
+    /// Rejects transactions whose payload is larger than `max_payload_size` bytes.
+    pub fn with_max_payload_size(self, max_payload_size: usize) -> Self {
+        Self {
+            max_payload_size: Some(max_payload_size),
+            ..self
+        }
+    }
+
+    /// Rejects transactions whose gas price is below `min_gas_price`.
+    pub fn with_min_gas_price(self, min_gas_price: u64) -> Self {
+        Self {
+            min_gas_price: Some(min_gas_price),
+            ..self
+        }
+    }
+
+    /// Prevents starvation within a gas tier: transactions that have waited in the queue for longer than
+    /// `window` are drained ahead of all transactions with the same gas price, regardless of their timestamp.
+    /// Among several overdue transactions, the one that arrived first is drained first.
+    /// Transactions with a higher gas price or a priority override still take precedence, and overdue transactions
+    /// with an override only move ahead of transactions with the same override.
+    ///
+    /// # Note
+    /// Draining an overdue transaction pops and pushes back the transactions of its tier that are ahead of it.
+    /// Only the drains based on [`Mempool::drain`] honor the window, the other drain methods keep the strict
+    /// priority order.
+    pub fn with_inversion_window(self, window: Duration) -> Self {
+        Self {
+            inversion_window: Some(window),
+            ..self
+        }
+    }
+
+    /// Levels the top gas tier: transactions with a gas price above `max_effective_gas_price` are drained as if
+    /// their gas price was equal to the cap, so they are ordered by timestamp along with the transactions at the
+    /// cap. The gas price of the transactions themselves is not modified.
+    ///
+    /// # Note
+    /// While transactions above the cap are queued, draining from the top tier requires a scan of the queue.
+    /// Only [`Mempool::drain`] honors the cap, the other drain methods keep the strict priority order.
+    pub fn with_max_effective_gas_price(self, max_effective_gas_price: u64) -> Self {
+        Self {
+            max_effective_gas_price: Some(max_effective_gas_price),
+            ..self
+        }
+    }
+
+    /// Selects how long [`Mempool::drain`] holds the lock, see [`DrainLockMode`].
+    pub fn with_drain_lock_mode(self, drain_lock_mode: DrainLockMode) -> Self {
+        Self {
+            drain_lock_mode,
+            ..self
+        }
+    }
+
+    /// Rejects transactions whose id is not accepted by `validator`, e.g. a [`mempool::HexValidator`] for
+    /// transaction hashes.
+    pub fn with_id_validator(self, validator: impl IdValidator + 'static) -> Self {
+        Self {
+            id_validator: Some(Box::new(validator)),
+            ..self
+        }
+    }
+
+    /// Number of transactions that have been discarded on drain because their `valid_until` deadline has passed.
+    pub fn expired_count(&self) -> u64 {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    /// Number of queued transactions with a gas price of at least `threshold`, e.g. to estimate how many
+    /// pending transactions would clear a given fee level.
+    ///
+    /// # Note
+    /// Counting requires a scan of all queued transactions in `O(n)`.
+    pub fn count_above(&self, threshold: u64) -> usize {
+        self.storage
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|tx| tx.gas_price >= threshold)
+            .count()
+    }
+
+    /// Reports the event created by `event` to the observer, if there is one.
+    fn notify(&self, event: impl FnOnce() -> MempoolEvent) {
+        if let Some(ObserverSlot(observer)) = &self.observer {
+            observer(event());
+        }
+    }
+
+    /// Records the `duration` of a drain in the drain latency and reports the `drained` transactions to the observer.
+    fn report_drained(&self, duration: Duration, drained: &[Transaction]) {
+        self.drain_latency.record(duration, drained.len());
+        for tx in drained {
+            self.notify(|| MempoolEvent::Drained(tx.id.clone()));
+        }
+    }
+
+    /// Reports transactions that left the queue without being drained to the observer.
+    fn report_evicted(&self, evicted: &[Transaction]) {
+        for tx in evicted {
+            self.notify(|| MempoolEvent::Evicted(tx.id.clone()));
+        }
+    }
+
+    /// Queues `tx` or returns it along with the reason of its rejection, and reports the outcome to the observer.
+    /// Fails if the lock is not acquired within `timeout`, see [`LockedQueue::lock_storage`].
+    fn insert(
+        &self,
+        tx: Transaction,
+        timeout: Option<Duration>,
+    ) -> Result<Admission<()>, LockFailure> {
+        self.insert_inspecting(tx, timeout, |_, _| ())
+    }
+
+    /// Inserts like [`LockedQueue::insert`] and returns the result of `inspect`, which is called with the queued
+    /// transactions and `tx` right before `tx` is queued.
+    fn insert_inspecting<R>(
+        &self,
+        tx: Transaction,
+        timeout: Option<Duration>,
+        inspect: impl FnOnce(&BinaryHeap<Transaction>, &Transaction) -> R,
+    ) -> Result<Admission<R>, LockFailure> {
+        let id = self.observer.as_ref().map(|_| tx.id.clone());
+        Ok(match self.push_checked(tx, timeout, inspect)? {
+            Ok((evicted, inspected)) => {
+                if let Some(victim) = evicted {
+                    self.notify(|| MempoolEvent::Evicted(victim.id));
+                }
+                if let Some(id) = id {
+                    self.notify(|| MempoolEvent::Submitted(id));
+                }
+                Ok(inspected)
+            }
+            Err((tx, reason)) => {
+                self.notify(|| MempoolEvent::Rejected(tx.id.clone(), reason));
+                Err((tx, reason))
+            }
+        })
+    }
+
+    /// Queues `tx` and returns the transaction evicted to make room for it, if any, along with the result of
+    /// `inspect`, which is called under the same lock right before `tx` is queued. Returns `tx` along with the
+    /// reason of its rejection if it is not queued. Returns `tx` along with the error if the lock of the queue is
+    /// not acquired within `timeout`.
+    fn push_checked<R>(
+        &self,
+        tx: Transaction,
+        timeout: Option<Duration>,
+        inspect: impl FnOnce(&BinaryHeap<Transaction>, &Transaction) -> R,
+    ) -> Result<Admission<(Option<Transaction>, R)>, LockFailure> {
+        if self
+            .id_validator
+            .as_ref()
+            .is_some_and(|validator| !validator.is_valid(&tx.id))
+        {
+            return Ok(Err((tx, RejectReason::InvalidId)));
+        }
+        if self
+            .max_payload_size
+            .is_some_and(|max_payload_size| tx.payload.len() > max_payload_size)
+        {
+            return Ok(Err((tx, RejectReason::PayloadTooLarge)));
+        }
+        if self
+            .min_gas_price
+            .is_some_and(|min_gas_price| tx.gas_price < min_gas_price)
+        {
+            return Ok(Err((tx, RejectReason::BelowFeeFloor)));
+        }
+
+        let mut evicted = None;
+        let mut storage = match self.lock_storage(timeout) {
+            Ok(storage) => storage,
+            Err(e) => return Err((Box::new(tx), e)),
+        };
+        // -- the slot of the sender is reserved first, so no transaction is evicted for a rejected one
+        if let (Some(max_per_sender), Some(sender)) = (self.max_per_sender, &tx.sender) {
+            let mut sender_counts = self.sender_counts.lock().unwrap();
+            let count = sender_counts.entry(sender.clone()).or_default();
+            if *count >= max_per_sender {
+                return Ok(Err((tx, RejectReason::SenderLimit)));
+            }
+            *count += 1;
+        }
+        if self.max_len.is_some_and(|max_len| storage.len() >= max_len) {
+            let victim = self
+                .eviction
+                .and_then(|policy| Self::evict(&mut storage, policy, &tx));
+            let Some(victim) = victim else {
+                if let (Some(_), Some(sender)) = (self.max_per_sender, &tx.sender) {
+                    self.release_sender(sender);
+                }
+                return Ok(Err((tx, RejectReason::PoolFull)));
+            };
+            self.release(&victim);
+            evicted = Some(victim);
+        }
+        if self.inversion_window.is_some() {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.record(tx.hash, self.tier(&tx), Instant::now());
+        }
+        let inspected = inspect(&storage, &tx);
         storage.push(tx);
+        Ok(Ok((evicted, inspected)))
     }
 
-    fn drain(&self, n: usize) -> Vec<Transaction> {
-        let mut storage = self.storage.lock().unwrap();
+    /// Sends a rejected `tx` to the dead-letter queue if there is one, otherwise it is dropped.
+    fn dispose(&self, tx: Transaction, reason: RejectReason) {
+        match &self.dead_letter {
+            Some(dlq) => {
+                if dlq.send((tx, reason)).is_err() {
+                    eprintln!("Warn! Dead-letter queue is disconnected, transaction is dropped.");
+                }
+            }
+            None => eprintln!("Warn! Transaction is rejected ({reason:?}) and dropped."),
+        }
+    }
+
+    /// Submits like [`Mempool::submit`], but gives up with an error if the lock of the queue is not acquired
+    /// within `timeout`, so latency-sensitive callers are not blocked indefinitely by a stuck lock holder. The
+    /// transaction is returned along with the error in that case, so the caller can retry it.
+    pub fn submit_timeout(&self, tx: Transaction, timeout: Duration) -> Result<(), LockFailure> {
+        if let Err((tx, reason)) = self.insert(tx, Some(timeout))? {
+            self.dispose(tx, reason);
+        }
+        Ok(())
+    }
+
+    /// Drains like [`Mempool::drain`], but gives up with an error if the lock of the queue is not acquired
+    /// within `timeout`.
+    pub fn drain_timeout(&self, n: usize, timeout: Duration) -> anyhow::Result<Vec<Transaction>> {
+        self.drain_within(n, Some(timeout))
+    }
+
+    /// Submits `tx` like [`Mempool::try_submit`] and returns its position in the queue, where `1` means that it is
+    /// the next transaction to be drained. Queued transactions of equal priority are not counted as being ahead of
+    /// `tx`. Rejected transactions are returned to the caller.
+    ///
+    /// # Note
+    /// The position is a point-in-time estimate. It changes as soon as transactions of higher priority
+    /// are submitted or transactions are drained.
+    pub fn submit_ranked(&self, tx: Transaction) -> Result<usize, Box<Transaction>> {
+        let ahead = |storage: &BinaryHeap<Transaction>, tx: &Transaction| {
+            storage.iter().filter(|queued| *queued > tx).count()
+        };
+        self.insert_inspecting(tx, None, ahead)
+            .map_err(|(_, e)| e)
+            .expect(BLOCKING_LOCK)
+            .map(|ahead| ahead + 1)
+            .map_err(|(tx, _)| Box::new(tx))
+    }
+
+    /// Drains up to `n` transactions like [`Mempool::drain`], but only if the queue holds at least `min`
+    /// transactions at the time of the call. Otherwise nothing is drained and an empty vector is returned.
+    /// The check and the drain happen under the same lock, so no other consumer can interfere in between.
+    pub fn drain_if_at_least(&self, min: usize, n: usize) -> Vec<Transaction> {
+        let drain_start = Instant::now();
+        let now_us = unix_time_us();
+
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        if storage.len() < min {
+            return vec![];
+        }
+        let drained: Vec<_> =
+            std::iter::from_fn(|| self.pop_valid(&mut storage, drain_start, now_us))
+                .take(n)
+                .collect();
+        drop(storage);
+
+        self.report_drained(drain_start.elapsed(), &drained);
+        drained
+    }
+
+    /// Number of drained transactions that may be buffered in the channel of [`LockedQueue::drain_streaming`].
+    const STREAM_BUFFER: usize = 1_024;
+
+    /// Drains up to `n` transactions like [`Mempool::drain`] and streams them over a bounded channel, so that
+    /// large drains do not have to be collected in one big vector. The transactions are popped on a background
+    /// thread, which shares the queue.
+    ///
+    /// # Note
+    /// The lock of the queue is held until the stream is complete, blocking other producers and consumers
+    /// in the meantime. Consumers should therefore receive the transactions promptly.
+    /// Should the receiver be dropped before the stream is complete, the remaining transactions stay in the queue.
+    pub fn drain_streaming(
+        self: &Arc<Self>,
+        n: usize,
+    ) -> crossbeam::channel::Receiver<Transaction> {
+        let (source, sink) = crossbeam::channel::bounded(Self::STREAM_BUFFER);
+        let queue = Arc::clone(self);
+
+        std::thread::spawn(move || {
+            let drain_start = Instant::now();
+            let now_us = unix_time_us();
+            let mut streamed = vec![];
+            let mut storage = queue.lock_storage(None).expect(BLOCKING_LOCK);
+            for _ in 0..n {
+                let Some(tx) = queue.pop_valid(&mut storage, drain_start, now_us) else {
+                    break;
+                };
+                let id = tx.id.clone();
+                if let Err(returned) = source.send(tx) {
+                    queue.push_unchecked(&mut storage, returned.into_inner());
+                    break;
+                }
+                streamed.push(id);
+            }
+            drop(storage);
+
+            queue
+                .drain_latency
+                .record(drain_start.elapsed(), streamed.len());
+            for id in streamed {
+                queue.notify(|| MempoolEvent::Drained(id));
+            }
+        });
+        sink
+    }
+
+    /// Drains like [`Mempool::drain`]. Fails if the lock is not acquired within `timeout`, see
+    /// [`LockedQueue::lock_storage`].
+    fn drain_within(
+        &self,
+        n: usize,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Vec<Transaction>> {
+        let drain_start = Instant::now();
+        let ordered_by_bookkeeping =
+            self.inversion_window.is_some() || self.max_effective_gas_price.is_some();
+        let items = match self.drain_lock_mode {
+            DrainLockMode::Snapshot if !ordered_by_bookkeeping => self.pop_snapshot(n, timeout)?,
+            _ => self.pop_batch(n, timeout)?,
+        };
+
+        self.report_drained(drain_start.elapsed(), &items);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("batch_size", items.len())
+            .record("duration_us", drain_start.elapsed().as_micros() as u64);
+        Ok(items)
+    }
+
+    /// Drops the bookkeeping of `tx` after it has left the queue: its earliest arrival time and its
+    /// contribution to the count of its sender.
+    fn release(&self, tx: &Transaction) {
+        if self.inversion_window.is_some() {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.remove(tx.hash, self.tier(tx));
+        }
+        if let (Some(_), Some(sender)) = (self.max_per_sender, &tx.sender) {
+            self.release_sender(sender);
+        }
+    }
+
+    /// Frees up one transaction of the limit of `sender`.
+    fn release_sender(&self, sender: &str) {
+        let mut sender_counts = self.sender_counts.lock().unwrap();
+        if let Some(count) = sender_counts.get_mut(sender) {
+            *count -= 1;
+            if *count == 0 {
+                sender_counts.remove(sender);
+            }
+        }
+    }
+
+    /// Pops up to `n` transactions to drain without reporting them. Fails if the lock is not acquired within
+    /// `timeout`, see [`LockedQueue::lock_storage`].
+    fn pop_batch(&self, n: usize, timeout: Option<Duration>) -> anyhow::Result<Vec<Transaction>> {
+        let now = Instant::now();
+        let now_us = unix_time_us();
+
+        let mut storage = self.lock_storage(timeout)?;
+        let mut items = Vec::with_capacity(n.min(storage.len()));
+        while items.len() < n {
+            let Some(tx) = self.pop_valid(&mut storage, now, now_us) else {
+                break;
+            };
+            items.push(tx);
+        }
+        Ok(items)
+    }
+
+    /// Takes up to `n` candidates out of the heap in priority order and only checks their expiry and releases their
+    /// bookkeeping after the lock is released, see [`DrainLockMode::Snapshot`].
+    fn pop_snapshot(
+        &self,
+        n: usize,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Vec<Transaction>> {
+        let now_us = unix_time_us();
+
+        let mut storage = self.lock_storage(timeout)?;
+        let whole_heap = n >= storage.len();
+        let mut candidates = if whole_heap {
+            let capacity = storage.capacity();
+            std::mem::replace(&mut *storage, BinaryHeap::with_capacity(capacity)).into_vec()
+        } else {
+            std::iter::from_fn(|| storage.pop()).take(n).collect()
+        };
+        drop(storage);
+
+        if whole_heap {
+            candidates.sort_unstable_by(|a, b| b.cmp(a));
+        }
+        candidates.retain(|tx| {
+            self.release(tx);
+            let expired = tx.is_expired(now_us);
+            if expired {
+                self.expired.fetch_add(1, Ordering::Relaxed);
+            }
+            !expired
+        });
+        Ok(candidates)
+    }
+
+    /// Queues `tx` along with its bookkeeping without applying the limits of the queue, e.g. to put back a drained
+    /// transaction that was admitted before. `tx` counts as a new arrival.
+    fn push_unchecked(&self, storage: &mut BinaryHeap<Transaction>, tx: Transaction) {
+        if let (Some(_), Some(sender)) = (self.max_per_sender, &tx.sender) {
+            let mut sender_counts = self.sender_counts.lock().unwrap();
+            *sender_counts.entry(sender.clone()).or_default() += 1;
+        }
+        if self.inversion_window.is_some() {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.record(tx.hash, self.tier(&tx), Instant::now());
+        }
+        storage.push(tx);
+    }
+
+    /// Pops the next transaction to drain like [`LockedQueue::pop_next`], but discards the transactions whose
+    /// `valid_until` deadline has passed at `now_us` and adds them to the [`LockedQueue::expired_count`].
+    fn pop_valid(
+        &self,
+        storage: &mut BinaryHeap<Transaction>,
+        now: Instant,
+        now_us: u64,
+    ) -> Option<Transaction> {
+        loop {
+            let tx = self.pop_next(storage, now)?;
+            if !tx.is_expired(now_us) {
+                return Some(tx);
+            }
+            self.expired.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Gas price of `tx` with the `max_effective_gas_price` cap applied.
+    fn effective_gas_price(&self, tx: &Transaction) -> u64 {
+        self.max_effective_gas_price
+            .map_or(tx.gas_price, |cap| tx.gas_price.min(cap))
+    }
+
+    /// [`Tier`] of `tx`, within which the inversion window and the gas price cap apply.
+    fn tier(&self, tx: &Transaction) -> Tier {
+        (tx.priority_override, self.effective_gas_price(tx))
+    }
+
+    /// Pops the next transaction to drain. Without an inversion window and without transactions above the gas
+    /// price cap, this is the transaction of highest priority. Otherwise, the overdue transaction of the [`Tier`] of
+    /// the top that arrived first is taken, or else the earliest transaction of that tier, ties broken by id.
+    fn pop_next(&self, storage: &mut BinaryHeap<Transaction>, now: Instant) -> Option<Transaction> {
+        let top = storage.peek()?;
+        let tier = self.tier(top);
+        let capped = top.gas_price > tier.1;
+
+        let overdue = self.inversion_window.and_then(|window| {
+            let (arrival, hash) = self.arrivals.lock().unwrap().first(tier)?;
+            (now.saturating_duration_since(arrival) > window).then_some(hash)
+        });
+        let chosen = overdue.or_else(|| {
+            capped
+                .then(|| {
+                    storage
+                        .iter()
+                        // -- an override outranks the cap, so only transactions of the same override compete
+                        .filter(|tx| self.tier(tx) == tier)
+                        // -- the canonical tie-break of `Transaction`'s `Ord` among equal timestamps
+                        .min_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)))
+                })
+                .flatten()
+                .map(|tx| tx.hash)
+        });
+
+        let next = match chosen.and_then(|hash| take_first(storage, |tx| tx.hash == hash)) {
+            Some(next) => next,
+            None => storage.pop()?,
+        };
+        self.release(&next);
+        Some(next)
+    }
+
+    /// Replaces the contents of the queue with `txs` under a single lock, so other users never observe an
+    /// empty queue in between. The limits of the queue are not applied to the replacement set.
+    pub fn replace_all(&self, txs: Vec<Transaction>) {
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        if self.max_per_sender.is_some() {
+            let mut sender_counts = self.sender_counts.lock().unwrap();
+            sender_counts.clear();
+            for sender in txs.iter().filter_map(|tx| tx.sender.as_ref()) {
+                *sender_counts.entry(sender.clone()).or_default() += 1;
+            }
+        }
+        if self.inversion_window.is_some() {
+            let now = Instant::now();
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.clear();
+            for tx in &txs {
+                arrivals.record(tx.hash, self.tier(tx), now);
+            }
+        }
+        *storage = BinaryHeap::from(txs);
+    }
+
+    /// Removes all queued transactions whose id is contained in `ids`, e.g. to prune the transactions of a
+    /// block that reached the node via another path. The heap is rebuilt once, regardless of how many
+    /// transactions are removed. The removed transactions are returned in no particular order.
+    pub fn remove_many(&self, ids: &HashSet<String>) -> Vec<Transaction> {
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut *storage)
+            .into_vec()
+            .into_iter()
+            .partition(|tx| ids.contains(&tx.id));
+        *storage = BinaryHeap::from(kept);
+        drop(storage);
+
+        for tx in &removed {
+            self.release(tx);
+        }
+        self.report_evicted(&removed);
+        removed
+    }
+
+    /// Moves all transactions with a gas price of at least `threshold` into a new queue and returns it, e.g.
+    /// to separate "ready" from "queued" transactions. The remaining transactions stay in `self`. The pool is
+    /// partitioned in a single pass under one lock and both heaps are rebuilt once.
+    ///
+    /// # Note
+    /// The returned queue is created by [`LockedQueue::new`] and does not inherit the limits or settings of
+    /// `self`.
+    pub fn split_at_gas_price(&self, threshold: u64) -> LockedQueue<Transaction> {
+        let drain_start = Instant::now();
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        let (moved, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut *storage)
+            .into_vec()
+            .into_iter()
+            .partition(|tx| tx.gas_price >= threshold);
+        *storage = BinaryHeap::from(kept);
+        drop(storage);
+
+        for tx in &moved {
+            self.release(tx);
+        }
+        self.report_drained(drain_start.elapsed(), &moved);
+
+        let split = LockedQueue::new(moved.len());
+        *split.storage.lock().unwrap() = BinaryHeap::from(moved);
+        split
+    }
+
+    /// Removes and returns the transaction of lowest priority, e.g. to evict the worst transaction when the pool
+    /// runs short of capacity. It is the counterpart of draining a single transaction, which yields the highest
+    /// priority.
+    ///
+    /// # Note
+    /// The heap only keeps track of its maximum, so the minimum is found by a scan of all queued transactions and
+    /// the heap is rebuilt afterwards, both in `O(n)`.
+    pub fn drain_lowest(&self) -> Option<Transaction> {
+        let drain_start = Instant::now();
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        let mut txs = std::mem::take(&mut *storage).into_vec();
+        let lowest = txs
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(index, _)| index)
+            .map(|index| txs.swap_remove(index));
+        *storage = BinaryHeap::from(txs);
+        drop(storage);
+
+        if let Some(tx) = &lowest {
+            self.release(tx);
+            self.report_drained(drain_start.elapsed(), std::slice::from_ref(tx));
+        }
+        lowest
+    }
+
+    /// Moves the `k` transactions of lowest priority into `cold` and returns how many were moved, e.g. to keep
+    /// a small hot pool in front of a larger cold tier. The moved transactions are submitted to `cold` via
+    /// [`Mempool::try_submit`] after the lock of `self` is released, so the limits of `cold` apply to them like to
+    /// any other submit. Transactions rejected by `cold` are queued in `self` again and not counted as moved.
+    ///
+    /// # Note
+    /// The heap only keeps track of its maximum, so all queued transactions are sorted in `O(n log n)` to find
+    /// the lowest `k`, and the heap is rebuilt afterwards.
+    pub fn demote_lowest(&self, k: usize, cold: &dyn Mempool) -> usize {
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        // `into_sorted_vec` sorts ascending, so the lowest priorities are at the front.
+        let mut kept = std::mem::take(&mut *storage).into_sorted_vec();
+        let demoted: Vec<_> = kept.drain(..k.min(kept.len())).collect();
+        *storage = BinaryHeap::from(kept);
+        drop(storage);
 
+        for tx in &demoted {
+            self.release(tx);
+        }
+        let mut moved_ids = vec![];
+        let mut rejected = vec![];
+        for tx in demoted {
+            let id = tx.id.clone();
+            match cold.try_submit(tx) {
+                Ok(()) => moved_ids.push(id),
+                Err(tx) => rejected.push(tx),
+            }
+        }
+        let moved = moved_ids.len();
+        for id in moved_ids {
+            self.notify(|| MempoolEvent::Evicted(id));
+        }
+        if !rejected.is_empty() {
+            let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+            for tx in rejected {
+                self.push_unchecked(&mut storage, *tx);
+            }
+        }
+        moved
+    }
+
+    /// Drains up to `n` transactions as a [`Reservation`], for consumers that may fail to process them.
+    /// The consumer either confirms the removal or returns the transactions to the pool. Several
+    /// reservations can be outstanding at the same time.
+    pub fn drain_reserve(&self, n: usize) -> Reservation<'_> {
+        let drain_start = Instant::now();
+        let transactions = self.pop_batch(n, None).expect(BLOCKING_LOCK);
+        let generation = self.reservations.fetch_add(1, Ordering::Relaxed);
+        self.outstanding.lock().unwrap().insert(generation);
+        Reservation {
+            queue: self,
+            transactions,
+            generation,
+            drain_duration: drain_start.elapsed(),
+        }
+    }
+
+    /// Whether the reservation with the given `generation` token is neither confirmed nor returned yet, e.g. for an
+    /// executor that only keeps the token of a [`Reservation`].
+    pub fn is_outstanding(&self, generation: u64) -> bool {
+        self.outstanding.lock().unwrap().contains(&generation)
+    }
+
+    /// Drains up to `n` transactions like [`Mempool::drain`] and returns them along with clones of the next
+    /// `peek_ahead` transactions, which stay in the queue, e.g. for schedulers that plan ahead. Both happen under
+    /// a single lock, so no submit can interleave between the drain and the peek.
+    ///
+    /// # Note
+    /// The peeked transactions follow the strict priority order and skip expired transactions. Neither an
+    /// inversion window nor a gas price cap are taken into account, so a later drain may differ from the peek.
+    pub fn drain_and_peek(
+        &self,
+        n: usize,
+        peek_ahead: usize,
+    ) -> (Vec<Transaction>, Vec<Transaction>) {
+        let drain_start = Instant::now();
+        let now_us = unix_time_us();
+
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        let mut drained = Vec::with_capacity(n.min(storage.len()));
+        while drained.len() < n {
+            let Some(tx) = self.pop_valid(&mut storage, drain_start, now_us) else {
+                break;
+            };
+            drained.push(tx);
+        }
+        // -- the peeked transactions are popped and pushed back, which spares a clone of the whole heap
+        let mut popped = vec![];
+        let mut peeked = Vec::with_capacity(peek_ahead.min(storage.len()));
+        while peeked.len() < peek_ahead {
+            let Some(tx) = storage.pop() else {
+                break;
+            };
+            if !tx.is_expired(now_us) {
+                peeked.push(tx.clone());
+            }
+            popped.push(tx);
+        }
+        storage.extend(popped);
+        drop(storage);
+
+        self.report_drained(drain_start.elapsed(), &drained);
+        (drained, peeked)
+    }
+
+    /// Drains up to `n` transactions and splits the slots across gas price tiers proportionally to their weights,
+    /// so low fee transactions are not starved by a steady stream of high fee ones. Each entry of `weights` pairs
+    /// the gas price range of a tier with its weight; a transaction belongs to the first tier whose range contains
+    /// its gas price. Transactions outside of every tier are never drained.
+    /// Within a tier, transactions are drained in priority order. Slots a tier cannot fill because it holds too
+    /// few transactions are given to the remaining transactions of all tiers in priority order.
+    /// The drained batch is returned in priority order. Expired transactions are discarded as in
+    /// [`Mempool::drain`], but the inversion window is not applied.
+    ///
+    /// # Note
+    /// Transactions are popped in priority order until every tier has filled its slots, and the popped transactions
+    /// that are not drained are pushed back. A tier that holds too few transactions therefore pops the whole queue.
+    pub fn drain_weighted(
+        &self,
+        n: usize,
+        weights: &[(RangeInclusive<u64>, f64)],
+    ) -> Vec<Transaction> {
+        let drain_start = Instant::now();
+        let now_us = unix_time_us();
+
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        let n = n.min(storage.len());
+        let mut quotas = Self::tier_quotas(n, weights);
         let mut items = Vec::with_capacity(n);
-        for _ in 0..n {
-            let Some(value) = storage.pop() else {
+        // -- popped transactions of tiers without free slots, which may fill the slots of other tiers
+        let mut leftover = VecDeque::new();
+        let mut untiered = vec![];
+        let pop_valid = |storage: &mut BinaryHeap<Transaction>| {
+            while let Some(tx) = storage.pop() {
+                if !tx.is_expired(now_us) {
+                    return Some(tx);
+                }
+                self.release(&tx);
+                self.expired.fetch_add(1, Ordering::Relaxed);
+            }
+            None
+        };
+
+        while quotas.iter().any(|quota| *quota > 0) {
+            let Some(tx) = pop_valid(&mut storage) else {
                 break;
             };
-            items.push(value);
+            match weights
+                .iter()
+                .position(|(range, _)| range.contains(&tx.gas_price))
+            {
+                Some(tier) if quotas[tier] > 0 => {
+                    quotas[tier] -= 1;
+                    items.push(tx);
+                }
+                Some(_) => leftover.push_back(tx),
+                None => untiered.push(tx),
+            }
+        }
+        // -- the leftovers were popped in priority order, so they precede all transactions still in the heap
+        while items.len() < n {
+            let tx = match leftover.pop_front() {
+                Some(tx) => tx,
+                None => match pop_valid(&mut storage) {
+                    Some(tx)
+                        if weights
+                            .iter()
+                            .any(|(range, _)| range.contains(&tx.gas_price)) =>
+                    {
+                        tx
+                    }
+                    Some(tx) => {
+                        untiered.push(tx);
+                        continue;
+                    }
+                    None => break,
+                },
+            };
+            items.push(tx);
         }
 
+        storage.extend(leftover);
+        storage.extend(untiered);
+        drop(storage);
+
+        items.iter().for_each(|tx| self.release(tx));
+        items.sort_unstable_by(|a, b| b.cmp(a));
+        self.report_drained(drain_start.elapsed(), &items);
         items
     }
+
+    /// Splits `n` slots across the tiers proportionally to their weights. Slots lost to rounding down are
+    /// assigned to the tiers with the largest remainders. Non-positive weights receive no slots.
+    fn tier_quotas(n: usize, weights: &[(RangeInclusive<u64>, f64)]) -> Vec<usize> {
+        let weights: Vec<f64> = weights.iter().map(|(_, w)| w.max(0.0)).collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return vec![0; weights.len()];
+        }
+
+        let exact: Vec<f64> = weights.iter().map(|w| n as f64 * w / total).collect();
+        let mut quotas: Vec<usize> = exact.iter().map(|e| e.floor() as usize).collect();
+        let mut by_remainder: Vec<usize> =
+            (0..weights.len()).filter(|&i| weights[i] > 0.0).collect();
+        by_remainder.sort_by(|&a, &b| {
+            (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor()))
+        });
+        let missing = n.saturating_sub(quotas.iter().sum());
+        for &tier in by_remainder.iter().cycle().take(missing) {
+            quotas[tier] += 1;
+        }
+        quotas
+    }
+
+    /// Removes the victim of the eviction `policy` from the `storage` to make room for `incoming`.
+    /// Returns `None` if there is no transaction that `incoming` may replace.
+    fn evict(
+        storage: &mut BinaryHeap<Transaction>,
+        policy: EvictionPolicy,
+        incoming: &Transaction,
+    ) -> Option<Transaction> {
+        let mut items = std::mem::take(storage).into_vec();
+        let victim_idx = match policy {
+            EvictionPolicy::LowestPriority => items
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.cmp(b))
+                .filter(|(_, lowest)| *lowest < incoming)
+                .map(|(idx, _)| idx),
+            EvictionPolicy::Oldest => items
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, tx)| tx.timestamp)
+                .map(|(idx, _)| idx),
+            EvictionPolicy::Random => {
+                (!items.is_empty()).then(|| rand::rng().random_range(0..items.len()))
+            }
+        };
+        let victim = victim_idx.map(|idx| items.swap_remove(idx));
+        *storage = BinaryHeap::from(items);
+        victim
+    }
+
+    /// Creates a queue with the given `capacity` and fills it with the transactions read from `reader`.
+    /// Every line of the input is expected to hold one JSON encoded [`Transaction`], blank lines are skipped.
+    /// # Error
+    /// Returns an error containing the line number if a line cannot be read or parsed.
+    pub fn from_json_lines(reader: impl BufRead, capacity: usize) -> anyhow::Result<Self> {
+        let queue = Self::new(capacity);
+        for (idx, line) in reader.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.with_context(|| format!("could not read line {line_number}"))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let tx: Transaction = serde_json::from_str(&line)
+                .with_context(|| format!("invalid transaction on line {line_number}"))?;
+            queue.submit(tx);
+        }
+        Ok(queue)
+    }
+
+    /// Writes all queued transactions to `writer` in priority order, one JSON encoded [`Transaction`] per line.
+    /// The dump is written from a snapshot of the heap, so the queue's contents are not consumed.
+    pub fn dump_json_lines(&self, mut writer: impl Write) -> anyhow::Result<()> {
+        let snapshot = self.storage.lock().unwrap().clone();
+
+        for tx in snapshot.into_sorted_vec().iter().rev() {
+            serde_json::to_writer(&mut writer, tx).context("could not serialize transaction")?;
+            writeln!(writer).context("could not write line break")?;
+        }
+        writer.flush().context("could not flush writer")
+    }
+
+    /// Number of bytes of serialized transactions that [`LockedQueue::drain_to_file`] writes at once.
+    const FILE_CHUNK_SIZE: usize = 64 * 1_024;
+
+    /// Empties the queue and writes all transactions to the file at `path` in priority order, one JSON
+    /// encoded [`Transaction`] per line. The file is created or truncated. Returns the number of written transactions.
+    /// Expired transactions are discarded and added to the [`LockedQueue::expired_count`] instead, like on a drain.
+    /// Reload the file via [`LockedQueue::from_json_lines`] to restore the queue, e.g. after a restart.
+    /// The lock of the queue is only held to take out its transactions, not while the file is written.
+    /// # Error
+    /// Returns an error stating how many transactions were written before the failure. The file is cut back to
+    /// the written transactions, and the transactions that could not be written are put back into the queue.
+    pub fn drain_to_file(&self, path: impl AsRef<Path>) -> anyhow::Result<usize> {
+        let path = path.as_ref();
+        let mut file = File::create(path)
+            .with_context(|| format!("could not create file {}", path.display()))?;
+        let drain_start = Instant::now();
+        let now_us = unix_time_us();
+
+        let drained = std::mem::take(&mut *self.lock_storage(None).expect(BLOCKING_LOCK));
+        drained.iter().for_each(|tx| self.release(tx));
+        // `into_sorted_vec` sorts ascending, so the transactions are written from the back.
+        let mut unwritten = drained.into_sorted_vec();
+        let len = unwritten.len();
+        unwritten.retain(|tx| !tx.is_expired(now_us));
+        self.expired
+            .fetch_add((len - unwritten.len()) as u64, Ordering::Relaxed);
+        let total = unwritten.len();
+
+        // -- the ids in the order of writing, to report the written transactions once they are gone
+        let ids: Vec<_> = unwritten.iter().rev().map(|tx| tx.id.clone()).collect();
+        let result = Self::write_json_chunks(&mut file, &mut unwritten);
+        let written = total - unwritten.len();
+        self.drain_latency.record(drain_start.elapsed(), written);
+        for id in ids.into_iter().take(written) {
+            self.notify(|| MempoolEvent::Drained(id));
+        }
+
+        if let Err(e) = result {
+            let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+            for tx in unwritten {
+                self.push_unchecked(&mut storage, tx);
+            }
+            return Err(e.context(format!(
+                "could not write transaction after writing {written} transactions to {}",
+                path.display()
+            )));
+        }
+        Ok(total)
+    }
+
+    /// Writes the transactions of `unwritten` to `file` from the back, one JSON encoded [`Transaction`] per line,
+    /// and removes every transaction from `unwritten` once it is written. Should a write fail, the `file` is cut
+    /// back to the transactions that were written completely.
+    fn write_json_chunks(file: &mut File, unwritten: &mut Vec<Transaction>) -> anyhow::Result<()> {
+        let mut chunk = Vec::with_capacity(Self::FILE_CHUNK_SIZE);
+        let mut written_bytes = 0;
+        while !unwritten.is_empty() {
+            chunk.clear();
+            let mut remaining = unwritten.len();
+            while remaining > 0 && chunk.len() < Self::FILE_CHUNK_SIZE {
+                remaining -= 1;
+                serde_json::to_writer(&mut chunk, &unwritten[remaining])
+                    .context("could not serialize transaction")?;
+                chunk.push(b'\n');
+            }
+
+            if let Err(e) = file.write_all(&chunk) {
+                // -- a partially written chunk would leave an incomplete line at the end of the file
+                if let Err(truncate) = file.set_len(written_bytes) {
+                    eprintln!(
+                        "Warn! Could not remove the partially written transactions: {truncate}"
+                    );
+                }
+                return Err(anyhow::Error::from(e).context("could not write transactions"));
+            }
+            written_bytes += chunk.len() as u64;
+            unwritten.truncate(remaining);
+        }
+        Ok(())
+    }
+}
+
+impl Mempool for LockedQueue<Transaction> {
+    /// Submits `tx` unless it is rejected. Rejected transactions are sent to the dead-letter queue if
+    /// there is one, otherwise they are dropped.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(id = %tx.id, duration_us = tracing::field::Empty))
+    )]
+    fn submit(&self, tx: Transaction) {
+        #[cfg(feature = "tracing")]
+        let submit_start = Instant::now();
+        let result = self
+            .insert(tx, None)
+            .map_err(|(_, e)| e)
+            .expect(BLOCKING_LOCK);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("duration_us", submit_start.elapsed().as_micros() as u64);
+        if let Err((tx, reason)) = result {
+            self.dispose(tx, reason);
+        }
+    }
+
+    /// Rejects `tx` if it violates the limits of the queue or if the queue is bounded and full, unless an
+    /// [`EvictionPolicy`] makes room for it. Rejected transactions are returned to the caller and not sent
+    /// to the dead-letter queue.
+    fn try_submit(&self, tx: Transaction) -> Result<(), Box<Transaction>> {
+        self.insert(tx, None)
+            .map_err(|(_, e)| e)
+            .expect(BLOCKING_LOCK)
+            .map_err(|(tx, _)| Box::new(tx))
+    }
+
+    /// Transactions whose `valid_until` deadline has passed are discarded instead of drained. They do not count
+    /// towards `n`, unless the queue uses [`DrainLockMode::Snapshot`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            skip(self),
+            fields(batch_size = tracing::field::Empty, duration_us = tracing::field::Empty)
+        )
+    )]
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        self.drain_within(n, None).expect(BLOCKING_LOCK)
+    }
+
+    /// Estimates the delay based on a moving average of the duration and size of recent drains.
+    fn estimated_drain_delay(&self, n: usize) -> Duration {
+        self.drain_latency.estimate(n)
+    }
+
+    fn reserve(&self, additional: usize) {
+        self.storage.lock().unwrap().reserve(additional);
+    }
+
+    /// Filters the heap under a single lock, so no other user observes the compaction in progress. The removed
+    /// transactions are reported as evicted.
+    fn compact(&self, keep: &dyn Fn(&Transaction) -> bool) -> usize {
+        let mut storage = self.lock_storage(None).expect(BLOCKING_LOCK);
+        let (kept, removed): (Vec<_>, Vec<_>) = std::mem::take(&mut *storage)
+            .into_vec()
+            .into_iter()
+            .partition(|tx| keep(tx));
+        *storage = BinaryHeap::from(kept);
+        drop(storage);
+
+        for tx in &removed {
+            self.release(tx);
+        }
+        self.report_evicted(&removed);
+        removed.len()
+    }
+
+    /// Reservoir sampling over the heap's backing storage.
+    fn sample(&self, k: usize) -> Vec<Transaction> {
+        let storage = self.storage.lock().unwrap();
+        storage
+            .iter()
+            .choose_multiple(&mut rand::rng(), k)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Linear scan over the heap's backing storage. An index by id would make lookups `O(1)`, but it would
+    /// have to be maintained on every submit and drain.
+    fn get(&self, id: &str) -> Option<Transaction> {
+        let storage = self.storage.lock().unwrap();
+        storage.iter().find(|tx| tx.id == id).cloned()
+    }
 }
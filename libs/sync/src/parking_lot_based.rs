@@ -0,0 +1,58 @@
+use std::collections::BinaryHeap;
+
+use mempool::{Mempool, Transaction};
+use parking_lot::Mutex;
+use rand::seq::IteratorRandom;
+
+/// Priority queue guarded by a [`parking_lot::Mutex`], which has less locking overhead than the
+/// [`std::sync::Mutex`] of the [`LockedQueue`](crate::LockedQueue), especially under contention.
+///
+/// # Note
+/// The lock is not poisoned if a thread panics while holding it. Other threads keep operating on the
+/// heap in whatever state the panicking thread left it in.
+#[derive(Debug)]
+pub struct ParkingLotQueue {
+    storage: Mutex<BinaryHeap<Transaction>>,
+}
+
+impl ParkingLotQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            storage: Mutex::new(BinaryHeap::with_capacity(capacity)),
+        }
+    }
+}
+
+impl Mempool for ParkingLotQueue {
+    fn submit(&self, tx: Transaction) {
+        self.storage.lock().push(tx);
+    }
+
+    fn drain(&self, n: usize) -> Vec<Transaction> {
+        let mut storage = self.storage.lock();
+        let mut items = Vec::with_capacity(n.min(storage.len()));
+        for _ in 0..n {
+            let Some(value) = storage.pop() else {
+                break;
+            };
+            items.push(value);
+        }
+        items
+    }
+
+    /// Reservoir sampling over the heap's backing storage.
+    fn sample(&self, k: usize) -> Vec<Transaction> {
+        let storage = self.storage.lock();
+        storage
+            .iter()
+            .choose_multiple(&mut rand::rng(), k)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+
+    fn get(&self, id: &str) -> Option<Transaction> {
+        let storage = self.storage.lock();
+        storage.iter().find(|tx| tx.id == id).cloned()
+    }
+}
@@ -1,59 +1,1465 @@
 #[cfg(test)]
 mod channel_based_tests {
-    use mempool::{Transaction, test::suite};
+    use mempool::Transaction;
 
     use crate::ChanneledQueue;
 
-    struct SyncTester;
+    mempool::mempool_test_suite!(ChanneledQueue::new(500_000));
 
-    impl suite::Tester<ChanneledQueue<Transaction>> for SyncTester {
-        fn create_mempool(&self) -> ChanneledQueue<Transaction> {
-            ChanneledQueue::new(500_000)
+    #[test]
+    fn compact_drains_the_runner_in_bounded_chunks() {
+        use mempool::Mempool;
+
+        let queue = ChanneledQueue::new(16);
+        for gas_price in 0..6 {
+            queue.submit(Transaction::with_empty_load("tx", gas_price, 0));
+        }
+        // -- wait for the runner to queue all submits, so the compaction sees them
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        assert_eq!(queue.compact(&|tx| tx.gas_price % 2 == 0), 3);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let gas_prices: Vec<u64> = queue.drain(10).iter().map(|tx| tx.gas_price).collect();
+        assert_eq!(gas_prices, [4, 2, 0]);
+    }
+
+    #[test]
+    fn stop_collecting_returns_remaining_in_order() {
+        use mempool::Mempool;
+
+        let queue = ChanneledQueue::new(16);
+        for (id, gas_price) in [("low", 1), ("high", 3), ("mid", 2), ("top", 4)] {
+            queue.submit(Transaction::with_empty_load(id, gas_price, 0));
+        }
+        // -- wait for the runner to queue all submits, so the drain sees them
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(queue.drain(1)[0].id, "top");
+
+        let ids: Vec<String> = queue
+            .stop_collecting()
+            .into_iter()
+            .map(|tx| tx.id)
+            .collect();
+        assert_eq!(ids, vec!["high", "mid", "low"]);
+    }
+
+    #[test]
+    fn runner_thread_is_named() {
+        use mempool::Mempool;
+
+        let queue = ChanneledQueue::new(16);
+        assert_eq!(queue.runner_thread().name(), Some("mempool-runner"));
+
+        let pinned = ChanneledQueue::with_affinity(16, 0);
+        assert_eq!(pinned.runner_thread().name(), Some("mempool-runner"));
+        pinned.submit(Transaction::with_empty_load("a", 1, 1));
+        assert_eq!(pinned.drain(1).len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod lock_based_tests {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use mempool::{
+        FallbackPool, HexValidator, Mempool, RejectReason, Transaction, UuidValidator, test::suite,
+    };
+
+    use crate::{DrainLockMode, EvictionPolicy, LockedQueue};
+
+    mempool::mempool_test_suite!(LockedQueue::new(500_000));
+
+    #[test]
+    fn get_by_id() {
+        suite::test_get_by_id(|| LockedQueue::new(500_000));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn heap_invariant_after_concurrent_submit() {
+        let queue = Arc::new(LockedQueue::new(10_000));
+
+        let handles: Vec<_> = (0..10)
+            .map(|producer| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    for i in 0..1_000u64 {
+                        queue.submit(Transaction::with_empty_load(
+                            &format!("tx-{producer}-{i}"),
+                            (i * 7 + producer) % 100,
+                            i,
+                        ));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(queue.verify_heap_invariant());
+        assert_eq!(queue.drain(10_000).len(), 10_000);
+    }
+
+    #[test]
+    fn estimated_drain_delay_after_drains() {
+        let queue = LockedQueue::new(1_000);
+        assert_eq!(queue.estimated_drain_delay(10), Duration::ZERO);
+
+        for i in 0..100 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+        for _ in 0..10 {
+            assert_eq!(queue.drain(10).len(), 10);
+        }
+
+        let single_batch = queue.estimated_drain_delay(10);
+        assert!(single_batch > Duration::ZERO);
+        assert!(single_batch < Duration::from_millis(100));
+        let three_batches = queue.estimated_drain_delay(30);
+        assert!(three_batches.abs_diff(single_batch * 3) <= Duration::from_nanos(3));
+    }
+
+    const NDJSON_FIXTURE: &str = r#"{"id":"tx1","gas_price":10,"timestamp":100,"payload":[]}
+{"id":"tx2","gas_price":30,"timestamp":300,"payload":[1,2,3]}
+
+{"id":"tx3","gas_price":30,"timestamp":200,"payload":[]}
+{"id":"tx4","gas_price":20,"timestamp":50,"payload":[4]}
+"#;
+
+    #[test]
+    fn from_json_lines_drains_by_priority() {
+        let queue = LockedQueue::from_json_lines(NDJSON_FIXTURE.as_bytes(), 10).unwrap();
+
+        let ids: Vec<String> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx3", "tx2", "tx4", "tx1"]);
+    }
+
+    #[test]
+    fn from_json_lines_reports_line_number() {
+        let input =
+            "{\"id\":\"tx1\",\"gas_price\":10,\"timestamp\":100,\"payload\":[]}\nnot json\n";
+
+        let err = LockedQueue::from_json_lines(input.as_bytes(), 10).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn dump_json_lines_round_trip() {
+        let original = LockedQueue::from_json_lines(NDJSON_FIXTURE.as_bytes(), 10).unwrap();
+
+        let mut dump = vec![];
+        original.dump_json_lines(&mut dump).unwrap();
+        assert_eq!(String::from_utf8_lossy(&dump).lines().count(), 4);
+
+        let reloaded = LockedQueue::from_json_lines(dump.as_slice(), 10).unwrap();
+        assert_eq!(reloaded.drain(10), original.drain(10));
+    }
+
+    #[test]
+    fn submit_ranked_returns_position() {
+        let queue = LockedQueue::new(10);
+
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx1", 50, 100)),
+            Ok(1)
+        );
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx2", 10, 100)),
+            Ok(2)
+        );
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx3", 90, 100)),
+            Ok(1)
+        );
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx4", 50, 50)),
+            Ok(2)
+        );
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx5", 50, 200)),
+            Ok(4)
+        );
+
+        let ids: Vec<String> = queue.drain(5).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx3", "tx4", "tx1", "tx5", "tx2"]);
+    }
+
+    #[test]
+    fn submit_ranked_applies_the_limits() {
+        let queue = LockedQueue::bounded(2).with_max_per_sender(1);
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx1", 50, 1).with_sender("alice")),
+            Ok(1)
+        );
+        let rejected = queue
+            .submit_ranked(Transaction::with_empty_load("tx2", 90, 1).with_sender("alice"))
+            .unwrap_err();
+        assert_eq!(rejected.id, "tx2");
+
+        assert_eq!(
+            queue.submit_ranked(Transaction::with_empty_load("tx3", 10, 1)),
+            Ok(2)
+        );
+        // -- the queue is full and has no eviction policy
+        assert!(
+            queue
+                .submit_ranked(Transaction::with_empty_load("tx4", 90, 1))
+                .is_err()
+        );
+        assert_eq!(queue.drain(10).len(), 2);
+    }
+
+    #[test]
+    fn bounded_queue_rejects_when_full() {
+        let queue = LockedQueue::bounded(2);
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("tx1", 1, 1))
+                .is_ok()
+        );
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("tx2", 2, 2))
+                .is_ok()
+        );
+
+        let rejected = queue.try_submit(Transaction::with_empty_load("tx3", 3, 3));
+        assert_eq!(rejected.unwrap_err().id, "tx3");
+        assert_eq!(queue.drain(10).len(), 2);
+    }
+
+    #[test]
+    fn fallback_pool_spills_and_drains_by_priority() {
+        let pool = FallbackPool::new(LockedQueue::bounded(3), LockedQueue::new(10));
+        for (id, gas_price) in [
+            ("tx1", 10),
+            ("tx2", 50),
+            ("tx3", 20),
+            ("tx4", 40),
+            ("tx5", 5),
+        ] {
+            pool.submit(Transaction::with_empty_load(id, gas_price, 1));
         }
+
+        assert_eq!(pool.primary().storage.lock().unwrap().len(), 3);
+        let mut overflow: Vec<String> = pool
+            .secondary()
+            .storage
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|tx| tx.id.clone())
+            .collect();
+        overflow.sort();
+        assert_eq!(overflow, ["tx4", "tx5"]);
+
+        let ids: Vec<String> = pool.drain(4).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx2", "tx4", "tx3", "tx1"]);
+        assert_eq!(pool.drain(4)[0].id, "tx5");
     }
 
     #[test]
-    fn ordering_by_gas_price() {
-        suite::test_ordering_by_gas_price(SyncTester)
+    fn drain_if_at_least_respects_minimum() {
+        let queue = LockedQueue::new(10);
+        for i in 0..3 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+
+        assert!(queue.drain_if_at_least(4, 2).is_empty());
+        assert_eq!(queue.storage.lock().unwrap().len(), 3);
+
+        let drained = queue.drain_if_at_least(3, 2);
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["tx2", "tx1"]);
+        assert_eq!(queue.storage.lock().unwrap().len(), 1);
     }
 
     #[test]
-    fn concurrent_submit() {
-        suite::test_concurrent_submit(SyncTester);
+    fn drain_lock_modes_drain_in_priority_order() {
+        for mode in [DrainLockMode::Reserve, DrainLockMode::Snapshot] {
+            let queue = LockedQueue::new(10)
+                .with_drain_lock_mode(mode)
+                .with_max_per_sender(4);
+            queue.submit(Transaction::with_empty_load("expired", 30, 1).with_valid_until(1));
+            for (id, gas_price) in [("mid", 10), ("high", 20), ("low", 5), ("lowest", 1)] {
+                queue.submit(Transaction::with_empty_load(id, gas_price, 1).with_sender("alice"));
+            }
+
+            let ids = |txs: Vec<Transaction>| txs.into_iter().map(|tx| tx.id).collect::<Vec<_>>();
+            let expected: &[&str] = match mode {
+                DrainLockMode::Reserve => &["high", "mid"],
+                // -- the expired transaction takes up one of the candidate slots
+                DrainLockMode::Snapshot => &["high"],
+            };
+            assert_eq!(ids(queue.drain(2)), expected, "{mode:?}");
+            assert_eq!(queue.expired_count(), 1, "{mode:?}");
+
+            // -- the drained transactions free up the per-sender limit in both modes
+            assert!(
+                queue
+                    .try_submit(Transaction::with_empty_load("next", 15, 1).with_sender("alice"))
+                    .is_ok()
+            );
+
+            // -- draining more than is queued takes the whole heap, still in priority order
+            let expected: &[&str] = match mode {
+                DrainLockMode::Reserve => &["next", "low", "lowest"],
+                DrainLockMode::Snapshot => &["next", "mid", "low", "lowest"],
+            };
+            assert_eq!(ids(queue.drain(10)), expected, "{mode:?}");
+            assert!(queue.drain(10).is_empty(), "{mode:?}");
+        }
     }
 
     #[test]
-    fn concurrent_submit_and_drain() {
-        suite::test_concurrent_submit_and_drain(SyncTester);
+    fn every_drain_path_discards_expired_transactions() {
+        let queue = Arc::new(LockedQueue::new(10));
+        let submit_pair = || {
+            queue.submit(Transaction::with_empty_load("expired", 30, 1).with_valid_until(1));
+            queue.submit(Transaction::with_empty_load("valid", 20, 2));
+        };
+
+        submit_pair();
+        let drained = queue.drain_if_at_least(2, 1);
+        assert_eq!(drained[0].id, "valid");
+        assert_eq!(queue.expired_count(), 1);
+
+        submit_pair();
+        let streamed: Vec<Transaction> = queue.drain_streaming(1).iter().collect();
+        assert_eq!(streamed[0].id, "valid");
+        assert_eq!(queue.expired_count(), 2);
+
+        let now_us = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        queue.submit(
+            Transaction::with_empty_load("short-lived", 30, 1).with_valid_until(now_us + 20_000),
+        );
+        let reservation = queue.drain_reserve(1);
+        assert_eq!(reservation.transactions().len(), 1);
+        // -- the deadline passes while the transaction is reserved
+        thread::sleep(Duration::from_millis(30));
+        assert!(reservation.confirm().is_empty());
+        assert_eq!(queue.expired_count(), 3);
+        assert!(queue.drain(10).is_empty());
+    }
+
+    fn full_queue(policy: EvictionPolicy) -> LockedQueue<Transaction> {
+        let queue = LockedQueue::with_eviction(3, policy);
+        queue.submit(Transaction::with_empty_load("mid", 20, 100));
+        queue.submit(Transaction::with_empty_load("low", 10, 300));
+        queue.submit(Transaction::with_empty_load("high", 30, 200));
+        queue
+    }
+
+    fn queued_ids(queue: &LockedQueue<Transaction>) -> Vec<String> {
+        let mut ids: Vec<String> = queue
+            .storage
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|tx| tx.id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn evict_lowest_priority() {
+        let queue = full_queue(EvictionPolicy::LowestPriority);
+
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("new", 15, 400))
+                .is_ok()
+        );
+        assert_eq!(queued_ids(&queue), ["high", "mid", "new"]);
+
+        // -- a transaction below the lowest priority is rejected
+        let rejected = queue.try_submit(Transaction::with_empty_load("lowest", 1, 400));
+        assert_eq!(rejected.unwrap_err().id, "lowest");
+        assert_eq!(queued_ids(&queue), ["high", "mid", "new"]);
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let queue = full_queue(EvictionPolicy::Oldest);
+
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("new", 1, 400))
+                .is_ok()
+        );
+        assert_eq!(queued_ids(&queue), ["high", "low", "new"]);
+    }
+
+    #[test]
+    fn evict_random() {
+        let queue = full_queue(EvictionPolicy::Random);
+
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("new", 1, 400))
+                .is_ok()
+        );
+        let ids = queued_ids(&queue);
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&"new".to_string()));
+        #[cfg(debug_assertions)]
+        assert!(queue.verify_heap_invariant());
+    }
+
+    #[test]
+    fn drain_streaming_yields_transactions_in_priority_order() {
+        let queue = Arc::new(LockedQueue::new(10_000));
+        for i in 0..10_000u64 {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx-{i}"),
+                (i * 7) % 1_000,
+                i,
+            ));
+        }
+
+        let drained: Vec<Transaction> = queue.drain_streaming(10_000).iter().collect();
+        assert_eq!(drained.len(), 10_000);
+        assert!(drained.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(queue.drain(1).is_empty());
+    }
+
+    #[test]
+    fn dead_letter_receives_rejections_with_reason() {
+        let (dlq, rejected) = crossbeam::channel::unbounded();
+        let queue = LockedQueue::with_dead_letter(2, dlq)
+            .with_max_len(2)
+            .with_max_payload_size(4)
+            .with_min_gas_price(10);
+
+        queue.submit(Transaction::new("large", 20, 1, vec![0; 5]));
+        queue.submit(Transaction::with_empty_load("cheap", 9, 2));
+        queue.submit(Transaction::with_empty_load("tx1", 10, 3));
+        queue.submit(Transaction::with_empty_load("tx2", 10, 4));
+        queue.submit(Transaction::with_empty_load("overflow", 30, 5));
+
+        let received: Vec<(String, RejectReason)> = rejected
+            .try_iter()
+            .map(|(tx, reason)| (tx.id, reason))
+            .collect();
+        assert_eq!(
+            received,
+            [
+                ("large".to_string(), RejectReason::PayloadTooLarge),
+                ("cheap".to_string(), RejectReason::BelowFeeFloor),
+                ("overflow".to_string(), RejectReason::PoolFull),
+            ]
+        );
+        assert_eq!(queue.drain(10).len(), 2);
+    }
+
+    #[test]
+    fn drain_discards_expired_transactions() {
+        let queue = LockedQueue::new(10);
+        queue.submit(Transaction::with_empty_load("expired", 30, 1).with_valid_until(1));
+        queue.submit(Transaction::with_empty_load("valid", 20, 2).with_valid_until(u64::MAX));
+        queue.submit(Transaction::with_empty_load("no_deadline", 10, 3));
+
+        let drained = queue.drain(10);
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["valid", "no_deadline"]);
+        assert_eq!(queue.expired_count(), 1);
+    }
+
+    #[test]
+    fn inversion_window_prevents_starvation_within_gas_tier() {
+        let queue = LockedQueue::new(10).with_inversion_window(Duration::from_millis(20));
+        queue.submit(Transaction::with_empty_load("waiting", 10, 1_000));
+        thread::sleep(Duration::from_millis(30));
+
+        // -- newer transactions with earlier timestamps would starve the waiting one
+        queue.submit(Transaction::with_empty_load("early", 10, 1));
+        queue.submit(Transaction::with_empty_load("high", 20, 2_000));
+
+        let drained = queue.drain(3);
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["high", "waiting", "early"]);
+    }
+
+    #[test]
+    fn inversion_window_drains_overdue_transactions_in_arrival_order() {
+        let queue = LockedQueue::new(10).with_inversion_window(Duration::from_millis(20));
+        queue.submit(Transaction::with_empty_load("first", 10, 300));
+        queue.submit(Transaction::with_empty_load("second", 10, 200));
+        thread::sleep(Duration::from_millis(30));
+        queue.submit(Transaction::with_empty_load("early", 10, 1));
+
+        let ids: Vec<String> = queue.drain(3).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["first", "second", "early"]);
+
+        // -- the drained arrivals are forgotten, so fresh transactions follow the strict order
+        queue.submit(Transaction::with_empty_load("late", 10, 1_000));
+        queue.submit(Transaction::with_empty_load("soon", 10, 2));
+        let ids: Vec<String> = queue
+            .drain_if_at_least(2, 2)
+            .into_iter()
+            .map(|tx| tx.id)
+            .collect();
+        assert_eq!(ids, ["soon", "late"]);
+    }
+
+    #[test]
+    fn inversion_window_keeps_overrides_ahead() {
+        let queue = LockedQueue::new(10).with_inversion_window(Duration::from_millis(20));
+        queue.submit(Transaction::with_empty_load("overdue", 10, 300));
+        thread::sleep(Duration::from_millis(30));
+        queue.submit(Transaction::with_empty_load("override", 10, 1).with_priority_override(1));
+
+        let ids: Vec<String> = queue.drain(2).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["override", "overdue"]);
+    }
+
+    #[test]
+    fn drain_to_file_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("drain_to_file_{}.ndjson", std::process::id()));
+        let original = LockedQueue::from_json_lines(NDJSON_FIXTURE.as_bytes(), 10).unwrap();
+
+        assert_eq!(original.drain_to_file(&path).unwrap(), 4);
+        assert!(original.drain(10).is_empty());
+
+        let file = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+        let reloaded = LockedQueue::from_json_lines(file, 10).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let ids: Vec<String> = reloaded.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx3", "tx2", "tx4", "tx1"]);
+    }
+
+    #[test]
+    fn drain_to_file_discards_expired_transactions() {
+        let path = std::env::temp_dir().join(format!(
+            "drain_to_file_expired_{}.ndjson",
+            std::process::id()
+        ));
+        let queue = LockedQueue::new(10);
+        queue.submit(Transaction::with_empty_load("valid", 10, 0));
+        queue.submit(Transaction::with_empty_load("expired", 20, 1).with_valid_until(1));
+
+        assert_eq!(queue.drain_to_file(&path).unwrap(), 1);
+        assert_eq!(queue.expired_count(), 1);
+
+        let file = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+        let reloaded = LockedQueue::from_json_lines(file, 10).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let ids: Vec<String> = reloaded.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["valid"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn drain_to_file_puts_back_unwritten_transactions() {
+        let queue = LockedQueue::from_json_lines(NDJSON_FIXTURE.as_bytes(), 10).unwrap();
+
+        // -- every write to /dev/full fails with "no space left on device"
+        assert!(queue.drain_to_file("/dev/full").is_err());
+        let ids: Vec<String> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx3", "tx2", "tx4", "tx1"]);
+    }
+
+    #[test]
+    fn sample_returns_random_cross_section() {
+        let queue = LockedQueue::new(100);
+        for i in 0..100 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+
+        assert_eq!(queue.sample(10).len(), 10);
+        assert_eq!(queue.sample(1_000).len(), 100);
+
+        let mut sampled_gas_prices = std::collections::HashSet::new();
+        for _ in 0..20 {
+            sampled_gas_prices.extend(queue.sample(5).iter().map(|tx| tx.gas_price));
+        }
+        // -- the top of the queue would always yield the same 5 gas prices
+        assert!(sampled_gas_prices.len() > 20);
+        assert!(sampled_gas_prices.iter().any(|gas_price| *gas_price < 50));
+        assert_eq!(queue.storage.lock().unwrap().len(), 100);
+    }
+
+    #[test]
+    fn per_sender_limit_rejects_flooding_sender() {
+        let queue = LockedQueue::with_per_sender_limit(10, 2);
+        for i in 0..2 {
+            let tx = Transaction::with_empty_load(&format!("alice{i}"), 10, i).with_sender("alice");
+            assert!(queue.try_submit(tx).is_ok());
+        }
+
+        let rejected =
+            queue.try_submit(Transaction::with_empty_load("alice2", 50, 2).with_sender("alice"));
+        assert_eq!(rejected.unwrap_err().id, "alice2");
+        // -- other senders and transactions without sender are not affected
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("bob0", 1, 0).with_sender("bob"))
+                .is_ok()
+        );
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("anon", 1, 0))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn per_sender_limit_frees_up_after_drain() {
+        let queue = LockedQueue::with_per_sender_limit(10, 1);
+        queue.submit(Transaction::with_empty_load("alice0", 10, 0).with_sender("alice"));
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("alice1", 10, 1).with_sender("alice"))
+                .is_err()
+        );
+
+        assert_eq!(queue.drain(1)[0].id, "alice0");
+        assert!(
+            queue
+                .try_submit(Transaction::with_empty_load("alice1", 10, 1).with_sender("alice"))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn per_sender_limit_is_checked_before_eviction() {
+        let queue =
+            LockedQueue::with_eviction(2, EvictionPolicy::LowestPriority).with_max_per_sender(1);
+        queue.submit(Transaction::with_empty_load("alice0", 10, 0).with_sender("alice"));
+        queue.submit(Transaction::with_empty_load("bob0", 5, 0).with_sender("bob"));
+
+        // -- alice is at her limit, so bob's transaction must not be evicted for her new one
+        let rejected =
+            queue.try_submit(Transaction::with_empty_load("alice1", 50, 1).with_sender("alice"));
+        assert_eq!(rejected.unwrap_err().id, "alice1");
+        assert_eq!(queued_ids(&queue), ["alice0", "bob0"]);
+    }
+
+    #[test]
+    fn every_drain_path_frees_up_the_sender_limit() {
+        let queue = Arc::new(LockedQueue::with_per_sender_limit(10, 1));
+        let alice =
+            |i: u64| Transaction::with_empty_load(&format!("alice{i}"), 10, i).with_sender("alice");
+
+        queue.submit(alice(0));
+        assert_eq!(queue.drain_if_at_least(1, 1).len(), 1);
+        assert!(queue.try_submit(alice(1)).is_ok());
+        assert_eq!(queue.drain_streaming(1).iter().count(), 1);
+        assert!(queue.try_submit(alice(2)).is_ok());
+        queue.drain_reserve(1).confirm();
+        assert!(queue.try_submit(alice(3)).is_ok());
+        // -- a returned reservation takes up the slot again
+        queue.drain_reserve(1).return_to_pool();
+        assert!(queue.try_submit(alice(4)).is_err());
+    }
+
+    #[test]
+    fn drain_weighted_splits_slots_across_tiers() {
+        let queue = LockedQueue::new(100);
+        for i in 0..20 {
+            queue.submit(Transaction::with_empty_load(
+                &format!("high{i}"),
+                100 + i,
+                i,
+            ));
+            queue.submit(Transaction::with_empty_load(&format!("low{i}"), i, i));
+        }
+
+        let weights = [(100..=u64::MAX, 0.8), (0..=99, 0.2)];
+        let drained = queue.drain_weighted(10, &weights);
+        assert_eq!(drained.len(), 10);
+        let high: Vec<_> = drained.iter().filter(|tx| tx.gas_price >= 100).collect();
+        let low: Vec<_> = drained.iter().filter(|tx| tx.gas_price < 100).collect();
+        assert_eq!(high.len(), 8);
+        assert_eq!(low.len(), 2);
+        // Within a tier, the transactions of highest priority are drained.
+        assert_eq!(low[0].gas_price, 19);
+        assert_eq!(low[1].gas_price, 18);
+        assert!(drained.windows(2).all(|w| w[0] >= w[1]));
+
+        // Slots of an exhausted tier go to the remaining transactions.
+        let drained = queue.drain_weighted(40, &weights);
+        assert_eq!(drained.len(), 30);
+        assert!(queue.drain(1).is_empty());
+    }
+
+    #[test]
+    fn drain_weighted_accepts_any_n_and_keeps_untiered_transactions() {
+        let queue = LockedQueue::new(10);
+        for (id, gas_price) in [("high", 200), ("outside", 150), ("low", 10), ("mid", 50)] {
+            queue.submit(Transaction::with_empty_load(id, gas_price, 0));
+        }
+
+        let weights = [(100..=199, 0.5), (200..=u64::MAX, 0.0), (0..=99, 0.5)];
+        let ids: Vec<String> = queue
+            .drain_weighted(usize::MAX, &weights)
+            .into_iter()
+            .map(|tx| tx.id)
+            .collect();
+        assert_eq!(ids, ["high", "outside", "mid", "low"]);
+
+        queue.submit(Transaction::with_empty_load("untiered", 5, 0));
+        assert!(
+            queue
+                .drain_weighted(usize::MAX, &[(100..=u64::MAX, 1.0)])
+                .is_empty()
+        );
+        assert_eq!(queue.drain(1)[0].id, "untiered");
+    }
+
+    #[test]
+    fn replace_all_swaps_contents() {
+        let queue = LockedQueue::new(10);
+        for i in 0..5 {
+            queue.submit(Transaction::with_empty_load(&format!("old{i}"), 100 + i, i));
+        }
+
+        queue.replace_all(vec![
+            Transaction::with_empty_load("new1", 1, 1),
+            Transaction::with_empty_load("new3", 3, 3),
+            Transaction::with_empty_load("new2", 2, 2),
+        ]);
+
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["new3", "new2", "new1"]);
+    }
+
+    #[test]
+    fn max_effective_gas_price_levels_top_tier() {
+        let queue = LockedQueue::new(10).with_max_effective_gas_price(100);
+        queue.submit(Transaction::with_empty_load("capped1", 100, 1));
+        queue.submit(Transaction::with_empty_load("whale", u64::MAX, 2));
+        queue.submit(Transaction::with_empty_load("capped3", 100, 3));
+        queue.submit(Transaction::with_empty_load("capped0", 150, 0));
+        queue.submit(Transaction::with_empty_load("low", 50, 0));
+
+        let drained = queue.drain(10);
+        let ids: Vec<_> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["capped0", "capped1", "whale", "capped3", "low"]);
+        // -- the real gas price is preserved
+        assert_eq!(drained[2].gas_price, u64::MAX);
+
+        // -- within the top tier, equal timestamps are ordered by id like uncapped transactions
+        let queue = LockedQueue::new(10).with_max_effective_gas_price(100);
+        for (id, gas_price) in [("tie-a", 100), ("tie-c", 300), ("tie-b", 200)] {
+            queue.submit(Transaction::with_empty_load(id, gas_price, 5));
+        }
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tie-a", "tie-b", "tie-c"]);
+    }
+
+    #[test]
+    fn max_effective_gas_price_keeps_overrides_ahead() {
+        let queue = LockedQueue::new(10).with_max_effective_gas_price(100);
+        queue.submit(Transaction::with_empty_load("early", 100, 1));
+        queue.submit(Transaction::with_empty_load("override", 500, 2).with_priority_override(1));
+        queue.submit(
+            Transaction::with_empty_load("override-early", 100, 1).with_priority_override(1),
+        );
+
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        // -- within the override tier the cap applies, but no transaction without an override jumps ahead
+        assert_eq!(ids, ["override-early", "override", "early"]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn submit_and_drain_emit_spans() {
+        use mempool::test::tracing::RecordingSubscriber;
+
+        let subscriber = RecordingSubscriber::default();
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            let queue = LockedQueue::new(10);
+            queue.submit(Transaction::with_empty_load("tx", 10, 1));
+            assert_eq!(queue.drain(3).len(), 1);
+        });
+
+        let submits = subscriber.spans("submit");
+        assert_eq!(submits.len(), 1);
+        assert_eq!(submits[0].fields["id"], "tx");
+        assert!(submits[0].fields.contains_key("duration_us"));
+
+        let drains = subscriber.spans("drain");
+        assert_eq!(drains.len(), 1);
+        assert_eq!(drains[0].fields["n"], "3");
+        assert_eq!(drains[0].fields["batch_size"], "1");
+        assert!(drains[0].fields.contains_key("duration_us"));
+    }
+
+    #[test]
+    fn min_returns_lowest_priority() {
+        let queue = LockedQueue::new(10);
+        assert_eq!(queue.min(), None);
+
+        queue.submit(Transaction::with_empty_load("high", 20, 1));
+        queue.submit(Transaction::with_empty_load("low_late", 5, 3));
+        queue.submit(Transaction::with_empty_load("low_early", 5, 2));
+        queue.submit(Transaction::with_empty_load("mid", 10, 1));
+
+        assert_eq!(queue.min().unwrap().id, "low_late");
+        assert_eq!(queue.drain(10).len(), 4);
+        assert_eq!(queue.min(), None);
+    }
+
+    #[test]
+    fn count_above_threshold() {
+        let queue = LockedQueue::new(10);
+        for (i, gas_price) in [10, 20, 20, 50, 100].into_iter().enumerate() {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{i}"),
+                gas_price,
+                0,
+            ));
+        }
+
+        assert_eq!(queue.count_above(0), 5);
+        assert_eq!(queue.count_above(10), 5);
+        assert_eq!(queue.count_above(11), 4);
+        assert_eq!(queue.count_above(20), 4);
+        assert_eq!(queue.count_above(100), 1);
+        assert_eq!(queue.count_above(101), 0);
+        // -- counting does not consume the queue
+        assert_eq!(queue.drain(10).len(), 5);
+    }
+
+    #[test]
+    fn compact_drops_rejected_transactions() {
+        let queue = LockedQueue::new(10);
+        for gas_price in [3, 8, 5, 2, 7, 4] {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{gas_price}"),
+                gas_price,
+                0,
+            ));
+        }
+
+        assert_eq!(queue.compact(&|tx| tx.gas_price % 2 == 0), 3);
+        let gas_prices: Vec<_> = queue.drain(10).iter().map(|tx| tx.gas_price).collect();
+        assert_eq!(gas_prices, [8, 4, 2]);
+        assert_eq!(queue.compact(&|_| false), 0);
+    }
+
+    #[test]
+    fn observer_receives_lifecycle_events() {
+        use mempool::MempoolEvent;
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let queue = LockedQueue::with_observer(
+            10,
+            Box::new(move |event| recorded.lock().unwrap().push(event)),
+        )
+        .with_max_len(2)
+        .with_min_gas_price(5);
+
+        queue.submit(Transaction::with_empty_load("a", 10, 1));
+        queue.submit(Transaction::with_empty_load("cheap", 1, 2));
+        queue.submit(Transaction::with_empty_load("b", 20, 3));
+        queue.submit(Transaction::with_empty_load("c", 30, 4));
+        assert_eq!(queue.drain(1).len(), 1);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                MempoolEvent::Submitted("a".to_string()),
+                MempoolEvent::Rejected("cheap".to_string(), RejectReason::BelowFeeFloor),
+                MempoolEvent::Submitted("b".to_string()),
+                MempoolEvent::Rejected("c".to_string(), RejectReason::PoolFull),
+                MempoolEvent::Drained("b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn observer_sees_every_removal_path() {
+        use mempool::MempoolEvent;
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let queue = Arc::new(LockedQueue::with_observer(
+            20,
+            Box::new(move |event| recorded.lock().unwrap().push(event)),
+        ));
+        let ids = [
+            "if", "stream", "split", "removed", "dropped", "file", "demoted", "lowest",
+        ];
+        for (i, id) in ids.into_iter().enumerate() {
+            queue.submit(Transaction::with_empty_load(
+                id,
+                80 - 10 * i as u64,
+                i as u64,
+            ));
+        }
+        events.lock().unwrap().clear();
+
+        assert_eq!(queue.drain_if_at_least(1, 1).len(), 1);
+        assert_eq!(queue.drain_streaming(1).iter().count(), 1);
+        assert_eq!(queue.split_at_gas_price(60).drain(10).len(), 1);
+        let removed = std::collections::HashSet::from(["removed".to_string()]);
+        assert_eq!(queue.remove_many(&removed).len(), 1);
+        assert_eq!(queue.compact(&|tx| tx.id != "dropped"), 1);
+        assert_eq!(queue.drain_lowest().unwrap().id, "lowest");
+        assert_eq!(queue.demote_lowest(1, &LockedQueue::new(10)), 1);
+        let path = std::env::temp_dir().join(format!(
+            "observer_sees_every_removal_path_{}.ndjson",
+            std::process::id()
+        ));
+        assert_eq!(queue.drain_to_file(&path).unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+
+        let drained = |id: &str| MempoolEvent::Drained(id.to_string());
+        let evicted = |id: &str| MempoolEvent::Evicted(id.to_string());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                drained("if"),
+                drained("stream"),
+                drained("split"),
+                evicted("removed"),
+                evicted("dropped"),
+                drained("lowest"),
+                evicted("demoted"),
+                drained("file"),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_many_removes_exactly_the_given_ids() {
+        let queue = LockedQueue::new(200);
+        for i in 0..200 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+        let ids: std::collections::HashSet<String> =
+            (0..200).step_by(4).map(|i| format!("tx{i}")).collect();
+        assert_eq!(ids.len(), 50);
+
+        let removed = queue.remove_many(&ids);
+        assert_eq!(removed.len(), 50);
+        assert!(removed.iter().all(|tx| ids.contains(&tx.id)));
+
+        let remaining = queue.drain(200);
+        assert_eq!(remaining.len(), 150);
+        assert!(remaining.iter().all(|tx| !ids.contains(&tx.id)));
+        assert!(remaining.is_sorted_by(|a, b| a >= b));
+    }
+
+    #[test]
+    fn capacity_utilization_follows_len() {
+        let queue = LockedQueue::new(100);
+        let capacity = queue.capacity();
+        assert!(capacity >= 100);
+        assert_eq!(queue.capacity_utilization(), 0.0);
+
+        let mut previous = 0.0;
+        for i in 0..capacity as u64 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+            let utilization = queue.capacity_utilization();
+            assert!(utilization > previous);
+            previous = utilization;
+        }
+        assert_eq!(queue.capacity_utilization(), 1.0);
+
+        queue.drain(capacity / 2);
+        assert_eq!(queue.capacity(), capacity);
+        assert!(queue.capacity_utilization() <= 0.5);
+    }
+
+    #[test]
+    fn confirmed_reservation_removes_transactions() {
+        let queue = LockedQueue::new(10);
+        for i in 0..5 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+
+        let reservation = queue.drain_reserve(3);
+        let ids: Vec<_> = reservation.transactions().iter().map(|tx| &tx.id).collect();
+        assert_eq!(ids, ["tx4", "tx3", "tx2"]);
+        assert_eq!(reservation.confirm().len(), 3);
+
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx1", "tx0"]);
+    }
+
+    #[test]
+    fn returned_reservation_restores_priority_order() {
+        let queue = LockedQueue::new(10);
+        for i in 0..5 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+
+        let first = queue.drain_reserve(2);
+        let second = queue.drain_reserve(2);
+        assert_ne!(first.generation(), second.generation());
+        queue.submit(Transaction::with_empty_load("late", 3, 10));
+
+        let generation = first.generation();
+        assert!(queue.is_outstanding(generation));
+        first.return_to_pool();
+        assert!(!queue.is_outstanding(generation));
+        drop(second);
+
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx4", "tx3", "late", "tx2", "tx1", "tx0"]);
+    }
+
+    #[test]
+    fn returned_reservation_bypasses_admission_limits() {
+        let (dlq, rejected) = crossbeam::channel::unbounded();
+        let queue = LockedQueue::with_dead_letter(2, dlq).with_max_len(2);
+        queue.submit(Transaction::with_empty_load("a", 10, 1));
+        queue.submit(Transaction::with_empty_load("b", 20, 1));
+
+        let reservation = queue.drain_reserve(2);
+        queue.submit(Transaction::with_empty_load("c", 30, 1));
+        queue.submit(Transaction::with_empty_load("d", 40, 1));
+        // -- the pool is full again, yet the reserved transactions are put back
+        reservation.return_to_pool();
+
+        assert!(rejected.try_recv().is_err());
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn reservation_reports_drains_on_confirm_only() {
+        use mempool::MempoolEvent;
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let queue = LockedQueue::with_observer(
+            10,
+            Box::new(move |event| {
+                if let MempoolEvent::Drained(id) = event {
+                    recorded.lock().unwrap().push(id);
+                }
+            }),
+        );
+        queue.submit(Transaction::with_empty_load("returned", 10, 1));
+        queue.submit(Transaction::with_empty_load("confirmed", 20, 1));
+
+        queue.drain_reserve(1).confirm();
+        queue.drain_reserve(1).return_to_pool();
+        assert_eq!(*events.lock().unwrap(), ["confirmed"]);
+        assert_eq!(queue.drain(1)[0].id, "returned");
+    }
+
+    #[test]
+    fn for_each_ordered_visits_in_priority_order() {
+        let queue = LockedQueue::new(10);
+        for gas_price in [30, 10, 50, 20, 40] {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{gas_price}"),
+                gas_price,
+                0,
+            ));
+        }
+
+        let mut gas_prices = vec![];
+        queue.for_each_ordered(|tx| gas_prices.push(tx.gas_price));
+        assert_eq!(gas_prices, [50, 40, 30, 20, 10]);
+        assert_eq!(queue.storage.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn drain_and_peek_removes_only_the_drained_batch() {
+        let queue = LockedQueue::new(10);
+        for i in 0..10 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, i));
+        }
+
+        let (drained, peeked) = queue.drain_and_peek(3, 4);
+        let ids: Vec<_> = drained
+            .iter()
+            .chain(&peeked)
+            .map(|tx| tx.id.as_str())
+            .collect();
+        assert_eq!(ids, ["tx9", "tx8", "tx7", "tx6", "tx5", "tx4", "tx3"]);
+
+        let remaining = queue.drain(10);
+        assert_eq!(remaining.len(), 7);
+        assert_eq!(&remaining[..4], peeked.as_slice());
+    }
+
+    #[test]
+    fn drain_lowest_removes_lowest_priority() {
+        let queue = LockedQueue::new(10);
+        for (id, gas_price) in [("a", 30), ("b", 10), ("c", 50), ("d", 20), ("e", 40)] {
+            queue.submit(Transaction::with_empty_load(id, gas_price, 0));
+        }
+
+        let lowest = queue.drain_lowest().unwrap();
+        assert_eq!(lowest.id, "b");
+
+        // -- the remaining transactions are intact and still drained in priority order
+        let ids: Vec<String> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec!["c", "e", "a", "d"]);
+        assert!(queue.drain_lowest().is_none());
+    }
+
+    #[test]
+    fn hex_id_validator_rejects_malformed_ids() {
+        let queue = LockedQueue::new(10).with_id_validator(HexValidator::default());
+        let hash = "ab".repeat(32);
+
+        let malformed = Transaction::with_empty_load("not-a-hash", 10, 0);
+        assert_eq!(
+            queue.try_submit(malformed.clone()),
+            Err(Box::new(malformed))
+        );
+        assert_eq!(
+            queue.try_submit(Transaction::with_empty_load(&hash, 10, 0)),
+            Ok(())
+        );
+
+        let ids: Vec<String> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec![hash]);
+    }
+
+    #[test]
+    fn uuid_id_validator_rejects_malformed_ids() {
+        let (dlq, rejected) = crossbeam::channel::unbounded();
+        let queue = LockedQueue::with_dead_letter(10, dlq).with_id_validator(UuidValidator);
+
+        queue.submit(Transaction::with_empty_load(
+            "67e55044-10b1-426f-9247",
+            10,
+            0,
+        ));
+        queue.submit(Transaction::with_empty_load(
+            "67e55044-10b1-426f-9247-bb680e5fe0c8",
+            10,
+            0,
+        ));
+
+        let (tx, reason) = rejected.try_recv().unwrap();
+        assert_eq!(tx.id, "67e55044-10b1-426f-9247");
+        assert_eq!(reason, RejectReason::InvalidId);
+        assert!(rejected.try_recv().is_err());
+        assert_eq!(queue.drain(10).len(), 1);
+    }
+
+    #[test]
+    fn demote_lowest_moves_bottom_to_cold_pool() {
+        let hot = LockedQueue::new(10);
+        let cold = LockedQueue::new(10);
+        for gas_price in 1..=10 {
+            hot.submit(Transaction::with_empty_load(
+                &format!("tx{gas_price}"),
+                gas_price,
+                0,
+            ));
+        }
+
+        assert_eq!(hot.demote_lowest(3, &cold), 3);
+
+        let ids = |pool: &LockedQueue<Transaction>| -> Vec<String> {
+            pool.drain(10).into_iter().map(|tx| tx.id).collect()
+        };
+        assert_eq!(
+            ids(&hot),
+            vec!["tx10", "tx9", "tx8", "tx7", "tx6", "tx5", "tx4"]
+        );
+        assert_eq!(ids(&cold), vec!["tx3", "tx2", "tx1"]);
+
+        // -- demoting more than is queued moves everything
+        hot.submit(Transaction::with_empty_load("last", 1, 0));
+        assert_eq!(hot.demote_lowest(5, &cold), 1);
+        assert_eq!(ids(&cold), vec!["last"]);
+
+        // -- transactions rejected by a full cold pool stay in the hot pool
+        let hot = LockedQueue::new(10);
+        let cold = LockedQueue::bounded(1);
+        for gas_price in 1..=3 {
+            hot.submit(Transaction::with_empty_load(
+                &format!("tx{gas_price}"),
+                gas_price,
+                0,
+            ));
+        }
+        assert_eq!(hot.demote_lowest(2, &cold), 1);
+        assert_eq!(ids(&hot), vec!["tx3", "tx2"]);
+        assert_eq!(ids(&cold), vec!["tx1"]);
+    }
+
+    #[test]
+    fn split_at_gas_price_partitions_pool() {
+        let queue = LockedQueue::new(10);
+        for (timestamp, (id, gas_price)) in [
+            ("a", 5),
+            ("b", 50),
+            ("c", 10),
+            ("d", 30),
+            ("e", 9),
+            ("f", 10),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            queue.submit(Transaction::with_empty_load(
+                id,
+                gas_price,
+                timestamp as u64,
+            ));
+        }
+
+        let ready = queue.split_at_gas_price(10);
+
+        let ids = |queue: &LockedQueue<Transaction>| -> Vec<String> {
+            queue.drain(10).into_iter().map(|tx| tx.id).collect()
+        };
+        // -- on equal gas prices, the earlier submit is drained first
+        assert_eq!(ids(&ready), vec!["b", "d", "c", "f"]);
+        assert_eq!(ids(&queue), vec!["e", "a"]);
+    }
+
+    #[test]
+    fn timed_operations_fail_on_held_lock() {
+        let queue = LockedQueue::new(10);
+        queue.submit(Transaction::with_empty_load("held", 1, 0));
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+
+        let tx = thread::scope(|s| {
+            // -- `for_each_ordered` holds the lock while its callback runs
+            s.spawn(|| {
+                queue.for_each_ordered(|_| {
+                    locked_tx.send(()).unwrap();
+                    thread::sleep(Duration::from_millis(200));
+                })
+            });
+            locked_rx.recv().unwrap();
+
+            let timeout = Duration::from_millis(10);
+            let tx = Transaction::with_empty_load("timed", 2, 0);
+            let (tx, _) = queue.submit_timeout(tx, timeout).unwrap_err();
+            assert_eq!(tx.id, "timed");
+            assert!(queue.drain_timeout(1, timeout).is_err());
+            tx
+        });
+
+        // -- once the lock is released, the timed operations succeed
+        let timeout = Duration::from_secs(1);
+        // -- the transaction handed back by the failed submit is retried
+        queue.submit_timeout(*tx, timeout).unwrap();
+        let ids: Vec<String> = queue
+            .drain_timeout(10, timeout)
+            .unwrap()
+            .into_iter()
+            .map(|tx| tx.id)
+            .collect();
+        assert_eq!(ids, vec!["timed", "held"]);
+    }
+
+    #[test]
+    fn reserve_avoids_growth_during_burst() {
+        let queue = LockedQueue::new(0);
+        queue.reserve(1_000);
+        let capacity = queue.capacity();
+        assert!(capacity >= 1_000);
+
+        for i in 0..1_000 {
+            queue.submit(Transaction::with_empty_load(&format!("tx{i}"), i, 0));
+        }
+        assert_eq!(queue.capacity(), capacity);
+    }
+
+    #[test]
+    fn shared_snapshot_is_cached_until_mutation() {
+        let queue = LockedQueue::new(10);
+        for (id, gas_price) in [("a", 30), ("b", 10), ("c", 50)] {
+            queue.submit(Transaction::with_empty_load(id, gas_price, 0));
+        }
+
+        let first = queue.shared_snapshot();
+        let second = queue.shared_snapshot();
+        assert!(Arc::ptr_eq(&first, &second));
+        let ids: Vec<&str> = first.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+
+        // -- a submit invalidates the cache, the old snapshot stays intact
+        queue.submit(Transaction::with_empty_load("d", 40, 0));
+        let third = queue.shared_snapshot();
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(first.len(), 3);
+        let ids: Vec<&str> = third.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "d", "a", "b"]);
     }
 }
 
 #[cfg(test)]
-mod lock_based_tests {
-    use mempool::{Transaction, test::suite};
+mod parking_lot_based_tests {
+    use mempool::test::suite;
+
+    use crate::ParkingLotQueue;
+
+    mempool::mempool_test_suite!(ParkingLotQueue::new(500_000));
+
+    #[test]
+    fn get_by_id() {
+        suite::test_get_by_id(|| ParkingLotQueue::new(500_000));
+    }
+}
+
+#[cfg(test)]
+mod adaptive_tests {
+    use mempool::{Mempool, Transaction, test::suite};
 
-    use crate::LockedQueue;
+    use crate::AdaptiveQueue;
 
-    struct SyncTester;
+    mempool::mempool_test_suite!(AdaptiveQueue::default());
+
+    #[test]
+    fn get_by_id() {
+        suite::test_get_by_id(AdaptiveQueue::default);
+    }
 
-    impl suite::Tester<LockedQueue<Transaction>> for SyncTester {
-        fn create_mempool(&self) -> LockedQueue<Transaction> {
-            LockedQueue::new(500_000)
+    #[test]
+    fn promotes_past_threshold_and_keeps_order() {
+        let queue = AdaptiveQueue::new(4);
+        let gas_prices = [5, 1, 9, 3, 7, 2, 8];
+        for (i, gas_price) in gas_prices.into_iter().enumerate() {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{i}"),
+                gas_price,
+                0,
+            ));
+        }
+        assert!(queue.is_promoted());
+        let drained: Vec<_> = queue.drain(3).iter().map(|tx| tx.gas_price).collect();
+        assert_eq!(drained, [9, 8, 7]);
+
+        let queue = AdaptiveQueue::new(4);
+        for (i, gas_price) in gas_prices.into_iter().take(4).enumerate() {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{i}"),
+                gas_price,
+                0,
+            ));
         }
+        assert!(!queue.is_promoted());
+        let drained: Vec<_> = queue.drain(3).iter().map(|tx| tx.gas_price).collect();
+        assert_eq!(drained, [9, 5, 3]);
+
+        queue.submit(Transaction::with_empty_load("late", 4, 0));
+        let drained: Vec<_> = queue.drain(10).iter().map(|tx| tx.gas_price).collect();
+        assert_eq!(drained, [4, 1]);
     }
+}
+
+#[cfg(test)]
+mod lazy_heap_tests {
+    use mempool::{Mempool, Transaction, test::suite};
+
+    use crate::LazyHeapQueue;
+
+    mempool::mempool_test_suite!(LazyHeapQueue::new(500_000));
 
     #[test]
-    fn ordering_by_gas_price() {
-        suite::test_ordering_by_gas_price(SyncTester)
+    fn get_by_id() {
+        suite::test_get_by_id(|| LazyHeapQueue::new(500_000));
     }
 
     #[test]
-    fn concurrent_submit() {
-        suite::test_concurrent_submit(SyncTester);
+    fn interleaved_submits_and_drains_keep_order() {
+        let queue = LazyHeapQueue::new(16);
+        for gas_price in [30, 10, 50] {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{gas_price}"),
+                gas_price,
+                0,
+            ));
+        }
+        let ids: Vec<_> = queue.drain(1).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx50"]);
+
+        for gas_price in [40, 5, 20] {
+            queue.submit(Transaction::with_empty_load(
+                &format!("tx{gas_price}"),
+                gas_price,
+                0,
+            ));
+        }
+        let ids: Vec<_> = queue.drain(2).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx40", "tx30"]);
+
+        queue.submit(Transaction::with_empty_load("tx25", 25, 0));
+        let ids: Vec<_> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, ["tx25", "tx20", "tx10", "tx5"]);
+    }
+}
+
+#[cfg(test)]
+mod indexed_tests {
+    use mempool::{Mempool, Transaction, test::suite};
+
+    use crate::IndexedQueue;
+
+    mempool::mempool_test_suite!(IndexedQueue::new(500_000));
+
+    #[test]
+    fn get_by_id() {
+        suite::test_get_by_id(|| IndexedQueue::new(500_000));
+    }
+
+    #[test]
+    fn remove_and_contains_keep_indices_in_sync() {
+        let queue = IndexedQueue::new(16);
+        for (id, gas_price) in [("a", 30), ("b", 10), ("c", 50), ("d", 20), ("e", 40)] {
+            queue.submit(Transaction::with_empty_load(id, gas_price, 0));
+        }
+        assert!(queue.verify_index_consistency());
+
+        assert_eq!(queue.remove("e").map(|tx| tx.id), Some("e".to_string()));
+        assert!(queue.remove("e").is_none());
+        assert!(!queue.contains("e"));
+        assert!(queue.contains("a"));
+        assert_eq!(queue.len(), 4);
+        assert!(queue.verify_index_consistency());
+
+        let ids: Vec<String> = queue.drain(2).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec!["c", "a"]);
+        assert!(!queue.contains("c"));
+        assert!(queue.verify_index_consistency());
+
+        let ids: Vec<String> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec!["d", "b"]);
+        assert!(queue.is_empty());
+        assert!(queue.verify_index_consistency());
     }
 
     #[test]
-    fn concurrent_submit_and_drain() {
-        suite::test_concurrent_submit_and_drain(SyncTester);
+    fn resubmitted_id_replaces_queued_transaction() {
+        let queue = IndexedQueue::new(16);
+        queue.submit(Transaction::with_empty_load("a", 10, 0));
+        queue.submit(Transaction::with_empty_load("b", 20, 0));
+        queue.submit(Transaction::with_empty_load("a", 30, 0));
+        assert_eq!(queue.len(), 2);
+        assert!(queue.verify_index_consistency());
+
+        let drained: Vec<(String, u64)> = queue
+            .drain(10)
+            .into_iter()
+            .map(|tx| (tx.id, tx.gas_price))
+            .collect();
+        assert_eq!(drained, vec![("a".to_string(), 30), ("b".to_string(), 20)]);
+    }
+
+    #[test]
+    fn equal_priorities_drain_by_id() {
+        let queue = IndexedQueue::new(16);
+        for id in ["tx-c", "tx-a", "tx-b"] {
+            queue.submit(Transaction::with_empty_load(id, 10, 0));
+        }
+        let ids: Vec<String> = queue.drain(10).into_iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec!["tx-a", "tx-b", "tx-c"]);
     }
 }
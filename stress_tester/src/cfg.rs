@@ -8,6 +8,10 @@ pub struct Cfg {
     /// Number of transactions each producer will submit to the memory pool during the test.
     #[arg(short, long)]
     pub transaction_num: usize,
+    /// Upper bound of `producer_num`, larger values are rejected before any producer is spawned. Every
+    /// producer of the naive and sync implementations runs on its own OS thread.
+    #[arg(long, default_value_t = 1_024)]
+    pub max_producers: usize,
     /// Number of Consumers that will drain transactions from the memory pool.
     #[arg(short, long, default_value_t = 1)]
     pub consumer_num: usize,
@@ -17,6 +21,18 @@ pub struct Cfg {
     /// Number of transactions that will be drained per batch.
     #[arg(short = 'b', long, default_value_t = 100)]
     pub drain_batch_size: usize,
+    /// Smallest payload size of the generated transactions in bytes.
+    #[arg(long, default_value_t = 256)]
+    pub payload_min: usize,
+    /// Largest payload size of the generated transactions in bytes.
+    #[arg(long, default_value_t = 1_024)]
+    pub payload_max: usize,
+    /// Lowest gas price of the generated transactions.
+    #[arg(long, default_value_t = 1)]
+    pub gas_min: u64,
+    /// Highest gas price of the generated transactions.
+    #[arg(long, default_value_t = 1_000)]
+    pub gas_max: u64,
     // Hard cap on the test's execution time
     #[arg(long, default_value_t = 10)]
     pub run_duration_seconds: u64,
@@ -24,6 +40,130 @@ pub struct Cfg {
     /// via http requests.
     #[arg(long)]
     pub http_port: Option<u16>,
+    /// Keep the `timestamp` that clients send along with their transactions. If set to `false`, the HTTP
+    /// server stamps every transaction with its own time of receipt.
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub trust_client_timestamp: bool,
+    /// Submit timeout the HTTP server uses when a client requests a timeout of 0.
+    #[arg(long, default_value_t = 50_000)]
+    pub default_submit_timeout_us: u64,
+    /// Maximum submit timeout the HTTP server grants a client.
+    #[arg(long, default_value_t = 1_000_000)]
+    pub max_submit_timeout_us: u64,
+    /// Time the HTTP server keeps the payloads of transactions drained via `/drain_headers` available.
+    #[arg(long, default_value_t = 60_000)]
+    pub payload_ttl_ms: u64,
+    /// Maximum size in bytes of a transaction submitted to the HTTP server.
+    #[arg(long, default_value_t = 1024 * 1024)]
+    pub max_body_bytes: usize,
+    /// Maximum number of transactions a client can request with a single HTTP drain request.
+    #[arg(long, default_value_t = 1_000_000)]
+    pub max_drain_n: usize,
+    /// Async consumers skip their drain while the queue holds fewer transactions than this
+    /// (async implementations only).
+    #[arg(long, default_value_t = 0)]
+    pub min_drain_batch: usize,
+    /// Number of submitted transactions the HTTP server buffers when the queue does not accept them in time.
+    /// Buffered submits are answered with 202 Accepted. Without a buffer, such submits are rejected with 503.
+    #[arg(long)]
+    pub submit_overflow_buffer: Option<usize>,
+    /// Maximum number of transactions the async queue returns for a single drain request.
+    #[arg(long, default_value_t = 10_000)]
+    pub max_drain_batch: usize,
+    /// Number of drain requests the async queue buffers before blocking further requesters. Must be at least 1.
+    #[arg(long, default_value_t = 10, value_parser = parse_non_zero)]
+    pub drain_request_buffer: usize,
+    /// Delay before the async queue re-checks a waiting drain request for the first time.
+    #[arg(long, default_value_t = 50)]
+    pub drain_retry_delay_us: u64,
+    /// Cap of the exponentially growing delay between re-checks of a waiting drain request.
+    #[arg(long, default_value_t = 1_000)]
+    pub max_drain_retry_delay_us: u64,
+    /// Reuse one pre-generated buffer of random bytes per producer to build transaction payloads
+    /// (async implementations only).
+    #[arg(long)]
+    pub reuse_payloads: bool,
+    /// End the test as soon as all transactions have been submitted and drained instead of running for
+    /// `run_duration_seconds`. The consumers of the naive and sync implementations otherwise stop together
+    /// with the producers and may leave transactions in the pool.
+    #[arg(long)]
+    pub stop_when_drained: bool,
+    /// Instead of one test, run one sub-trial of `run_duration_seconds` per consumer count (1, 2, 4, ...
+    /// up to `auto_tune_max_consumers`) and recommend the count with the highest drain throughput whose
+    /// P99 latency stays below `auto_tune_target_p99_us` (async implementations without HTTP only).
+    #[arg(long)]
+    pub auto_tune: bool,
+    /// Largest consumer count tried by `--auto-tune`.
+    #[arg(long, default_value_t = 16)]
+    pub auto_tune_max_consumers: usize,
+    /// P99 submission-to-drain latency target of `--auto-tune` in microseconds.
+    #[arg(long, default_value_t = 10_000)]
+    pub auto_tune_target_p99_us: u64,
+    /// Significant figures of the latency histogram (async implementations only). More figures increase
+    /// the precision of the reported latencies at the cost of memory.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(0..=5))]
+    pub histogram_sigfigs: u8,
+    /// Seed of the transaction generators, as decimal or `0x` prefixed hex number. A random seed is used if
+    /// omitted, it is printed to repeat the run.
+    #[arg(long, value_parser = parse_seed)]
+    pub seed: Option<u64>,
+}
+
+impl Cfg {
+    /// Parses the command line like [`clap::Parser::try_parse_from`] and additionally rejects ranges
+    /// whose minimum exceeds their maximum.
+    pub fn try_parse_validated_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        use clap::{CommandFactory, Parser};
+
+        let cfg = Self::try_parse_from(args)?;
+        cfg.check_ranges()
+            .map_err(|e| Self::command().error(clap::error::ErrorKind::ValueValidation, e))?;
+        Ok(cfg)
+    }
+
+    fn check_ranges(&self) -> Result<(), String> {
+        if self.payload_min > self.payload_max {
+            return Err(format!(
+                "--payload-min ({}) must not exceed --payload-max ({})",
+                self.payload_min, self.payload_max
+            ));
+        }
+        if self.gas_min > self.gas_max {
+            return Err(format!(
+                "--gas-min ({}) must not exceed --gas-max ({})",
+                self.gas_min, self.gas_max
+            ));
+        }
+        Ok(())
+    }
+
+    /// Payload size range shared by all implementations, so their results stay comparable.
+    pub fn payload_size_range(&self) -> (usize, usize) {
+        (self.payload_min, self.payload_max)
+    }
+
+    /// Gas price range shared by all implementations, so their results stay comparable.
+    pub fn gas_price_range(&self) -> (u64, u64) {
+        (self.gas_min, self.gas_max)
+    }
+}
+
+fn parse_non_zero(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>().map_err(|e| e.to_string())? {
+        0 => Err("must be at least 1".to_string()),
+        value => Ok(value),
+    }
+}
+
+fn parse_seed(seed: &str) -> Result<u64, std::num::ParseIntError> {
+    match seed.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => seed.parse(),
+    }
 }
 
 #[derive(Debug, Clone, strum::EnumString, clap::ValueEnum)]
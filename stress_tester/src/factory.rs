@@ -0,0 +1,52 @@
+use anyhow::bail;
+use mempool::Mempool;
+use naive::NaivePool;
+use sync::{ChanneledQueue, LockedQueue};
+
+use crate::cfg::Implementation;
+
+/// Builds the sync memory pool `impl_kind` with the given `capacity`, so the stress test can be written
+/// once for all sync implementations.
+/// # Error
+/// Returns an error for the async implementations, which implement the async `Mempool` trait instead.
+pub fn build(impl_kind: Implementation, capacity: usize) -> anyhow::Result<Box<dyn Mempool>> {
+    Ok(match impl_kind {
+        Implementation::Naive => Box::new(NaivePool::new(capacity)),
+        Implementation::SyncChannels => Box::new(ChanneledQueue::new(capacity)),
+        Implementation::SyncLocks => Box::new(LockedQueue::new(capacity)),
+        Implementation::Async | Implementation::AsyncLocks => {
+            bail!("{impl_kind:?} is an async implementation and cannot be built as a sync mempool")
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use mempool::Transaction;
+
+    use super::*;
+
+    #[test]
+    fn builds_working_sync_pools() {
+        for impl_kind in [
+            Implementation::Naive,
+            Implementation::SyncChannels,
+            Implementation::SyncLocks,
+        ] {
+            let pool = build(impl_kind, 10).unwrap();
+            for (id, gas_price) in [("low", 1), ("high", 3), ("mid", 2)] {
+                pool.submit(Transaction::with_empty_load(id, gas_price, 0));
+            }
+            // -- the channel based pool queues submits asynchronously
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            let ids: Vec<_> = pool.drain(10).into_iter().map(|tx| tx.id).collect();
+            assert_eq!(ids, ["high", "mid", "low"]);
+        }
+    }
+
+    #[test]
+    fn rejects_async_implementations() {
+        assert!(build(Implementation::Async, 10).is_err());
+        assert!(build(Implementation::AsyncLocks, 10).is_err());
+    }
+}
@@ -1,29 +1,137 @@
-use std::time::Duration;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Context;
-use async_impl::drain_strategy::DrainRequest;
+use async_impl::{drain_strategy::DrainRequest, worker::RemoveRequest};
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{DefaultBodyLimit, Path, State},
     http::StatusCode,
-    response::IntoResponse,
-    routing::{get, post},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
 };
 use mempool::Transaction;
-use tokio::{select, sync::mpsc::Sender, task::JoinHandle};
+use tokio::{
+    select,
+    sync::mpsc::{Sender, error::SendTimeoutError},
+    task::JoinHandle,
+};
+
+/// Server side settings of the HTTP interface.
+#[derive(Debug, Clone)]
+pub struct ServerCfg {
+    pub port: u16,
+    /// If `false`, the `timestamp` of every submitted [`Transaction`] is overwritten with the
+    /// server's unix time (in microseconds) at the moment the request is received.
+    pub trust_client_timestamp: bool,
+    /// Submit timeout that is used when a client requests a timeout of `0`.
+    pub default_submit_timeout_us: u64,
+    /// Upper bound for the submit timeout a client can request.
+    pub max_submit_timeout_us: u64,
+    /// Time the payloads of transactions drained via `/drain_headers` stay available for fetching.
+    pub payload_ttl: Duration,
+    /// Maximum size of a submit request body. Larger bodies are rejected with `413 Payload Too Large`
+    /// before they are deserialized.
+    pub max_body_bytes: usize,
+    /// Upper bound for the number of transactions a client can request per drain. Larger requests are
+    /// rejected with `400 Bad Request` before they reach the queue.
+    pub max_drain_n: usize,
+    /// What happens to a submitted transaction when the queue does not accept it within the submit timeout.
+    pub submit_overflow: SubmitOverflowPolicy,
+}
+
+/// Handling of submitted transactions that the queue does not accept within the submit timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitOverflowPolicy {
+    /// The transaction is dropped and the client receives `503 Service Unavailable`.
+    Reject503,
+    /// The transaction is stashed in a buffer that holds up to `max_buffer` transactions and that is fed into
+    /// the queue in the background. The client receives `202 Accepted`, or `503 Service Unavailable` if the
+    /// buffer is full as well. The buffer holds at least one transaction.
+    Accept202WithBuffer { max_buffer: usize },
+}
+
+impl ServerCfg {
+    /// Clamps the submit timeout requested by a client into `[1, max_submit_timeout_us]`.
+    /// A requested timeout of `0` is replaced by the `default_submit_timeout_us`.
+    fn submit_timeout(&self, requested_us: u64) -> Duration {
+        let timeout_us = match requested_us {
+            0 => self.default_submit_timeout_us,
+            t => t,
+        };
+        Duration::from_micros(timeout_us.clamp(1, self.max_submit_timeout_us.max(1)))
+    }
+}
+
+/// Rejects drain requests for more than `max_drain_n` transactions, so a client cannot make the queue
+/// allocate for an absurd number of transactions.
+/// # Error
+/// Returns the `400 Bad Request` status and message to send to the client.
+fn check_drain_n(n: usize, max_drain_n: usize) -> Result<(), (StatusCode, String)> {
+    if n > max_drain_n {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("cannot drain more than {max_drain_n} transactions per request"),
+        ));
+    }
+    Ok(())
+}
 
 #[derive(Clone)]
-pub struct SubmittanceSource(Sender<Transaction>);
+pub struct SubmittanceSource {
+    submitter: Sender<Transaction>,
+    /// Buffer for transactions the queue does not accept in time, see [`SubmitOverflowPolicy`].
+    overflow: Option<Sender<Transaction>>,
+    cfg: ServerCfg,
+}
+
+impl SubmittanceSource {
+    /// Creates the submittance state of the server. Under [`SubmitOverflowPolicy::Accept202WithBuffer`], a
+    /// background task is spawned that feeds the overflow buffer into the `submitter`. It stops once the
+    /// buffer or the queue is closed.
+    fn new(submitter: Sender<Transaction>, cfg: ServerCfg) -> Self {
+        let overflow = match cfg.submit_overflow {
+            SubmitOverflowPolicy::Reject503 => None,
+            SubmitOverflowPolicy::Accept202WithBuffer { max_buffer } => {
+                let (overflow, mut buffered) = tokio::sync::mpsc::channel(max_buffer.max(1));
+                let submitter = submitter.clone();
+                tokio::spawn(async move {
+                    while let Some(tx) = buffered.recv().await {
+                        if let Err(e) = submitter.send(tx).await {
+                            eprintln!("Logging submittance error: {e}");
+                            break;
+                        }
+                    }
+                });
+                Some(overflow)
+            }
+        };
+        Self {
+            submitter,
+            overflow,
+            cfg,
+        }
+    }
+}
 
 pub async fn start_server(
-    port: u16,
+    cfg: ServerCfg,
     submittance_source: Sender<Transaction>,
     drain_request_source: Sender<DrainRequest>,
+    remove_request_source: Sender<RemoveRequest>,
 ) -> anyhow::Result<JoinHandle<anyhow::Result<()>>> {
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", cfg.port)).await?;
     println!("HTTP server listening on {}", listener.local_addr()?);
 
-    let app = build_router(submittance_source, drain_request_source);
+    let app = build_router(
+        &cfg,
+        submittance_source,
+        drain_request_source,
+        remove_request_source,
+    );
 
     Ok(tokio::spawn(async move {
         axum::serve(listener, app)
@@ -34,18 +142,37 @@ pub async fn start_server(
 
 /// Submit the transaction transmitted in the request body to the managed priority queue.
 /// The submitter waits at maximum for `timeout_us` before cancelling the operation and returning
-/// the HTTP code 503 "busy".
+/// the HTTP code 503 "busy", unless the server buffers overflowing transactions and answers 202 "accepted"
+/// (see [`SubmitOverflowPolicy`]). The timeout is capped by the server configuration, a `timeout_us` of `0`
+/// selects the server's default timeout.
+///
+/// Unless the server is configured to trust client timestamps, the transaction's `timestamp` is
+/// replaced by the time of receipt to guarantee consistent tie-breaking across clients.
 #[axum::debug_handler]
 async fn submit_transaction(
-    State(SubmittanceSource(submitter)): State<SubmittanceSource>,
+    State(SubmittanceSource {
+        submitter,
+        overflow,
+        cfg,
+    }): State<SubmittanceSource>,
     Path(timeout_us): Path<u64>,
-    Json(transaction): Json<Transaction>,
+    Json(mut transaction): Json<Transaction>,
 ) -> impl IntoResponse {
+    if !cfg.trust_client_timestamp {
+        transaction.timestamp = unix_micros();
+        transaction.hash = transaction.compute_hash();
+    }
+
     if let Err(e) = submitter
-        .send_timeout(transaction, Duration::from_micros(timeout_us))
+        .send_timeout(transaction, cfg.submit_timeout(timeout_us))
         .await
     {
         eprintln!("Logging submittance error: {e}");
+        if let (SendTimeoutError::Timeout(transaction), Some(overflow)) = (e, overflow)
+            && overflow.try_send(transaction).is_ok()
+        {
+            return StatusCode::ACCEPTED.into_response();
+        }
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             "queue is under heavy load, could not add transaction",
@@ -56,9 +183,22 @@ async fn submit_transaction(
     StatusCode::OK.into_response()
 }
 
+/// Current unix time in microseconds.
+fn unix_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time flowing forwards")
+        .as_micros()
+        .try_into()
+        .expect("conversion okay for the next few years")
+}
+
 /// Return type of drain request.
 #[derive(Clone)]
-pub struct DrainRequestSource(Sender<DrainRequest>);
+pub struct DrainRequestSource {
+    drainage_requester: Sender<DrainRequest>,
+    max_drain_n: usize,
+}
 
 #[derive(Debug, serde::Serialize)]
 pub struct Drainage(Vec<Transaction>);
@@ -66,48 +206,648 @@ pub struct Drainage(Vec<Transaction>);
 /// Tries to drain `n` elements from the queue with an timeout of `timeout_us` microseconds.
 /// Should the timeout be reached without there being `n` elements to drain, all remaining elements are drained and
 /// returned.
+///
+/// If the request times out before the queue answers, the queue feeds the drained elements back into its storage,
+/// so no transactions are lost. Requests for more than the configured `max_drain_n` transactions are rejected.
 async fn drain_transactions(
-    State(DrainRequestSource(drainage_requester)): State<DrainRequestSource>,
+    State(DrainRequestSource {
+        drainage_requester,
+        max_drain_n,
+    }): State<DrainRequestSource>,
     Path((n, timeout_us)): Path<(usize, u64)>,
 ) -> impl IntoResponse {
-    let (req, rx) = DrainRequest::new_with_timeout(n, timeout_us);
+    if let Err(rejection) = check_drain_n(n, max_drain_n) {
+        return rejection.into_response();
+    }
+    match request_drainage(&drainage_requester, n, timeout_us).await {
+        Ok(v) => Json(Drainage(v)).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Sends a drain request to the queue and waits at maximum `timeout_us` for its result.
+/// # Error
+/// Returns the error response to send to the client if the drain fails or times out.
+/// Time a drain request waits for the queue's answer beyond its timeout, which covers the resolution of the timer.
+const DRAIN_TIMEOUT_GRACE: Duration = Duration::from_millis(5);
+
+async fn request_drainage(
+    drainage_requester: &Sender<DrainRequest>,
+    n: usize,
+    timeout_us: u64,
+) -> Result<Vec<Transaction>, Response> {
+    let (req, mut rx) = DrainRequest::new_with_timeout(n, timeout_us);
     let timeout = Duration::from_micros(timeout_us);
 
+    // a request without timeout is answered right away with whatever the queue holds
+    if timeout.is_zero() {
+        if let Err(e) = drainage_requester.send(req).await {
+            eprintln!("Logging drainage error: {e}");
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "could not drain").into_response());
+        }
+        return rx.await.map_err(|e| {
+            eprintln!("Logging drainage error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "could not drain").into_response()
+        });
+    }
+
     // use interval to keep track of overall request duration and cancel it when `timeout` is reached.
-    let mut interval = tokio::time::interval(timeout);
+    // The queue answers at the timeout itself, the grace period keeps that answer from racing the cancellation.
+    let mut interval = tokio::time::interval(timeout + DRAIN_TIMEOUT_GRACE);
     interval.tick().await; // resolves immediately
 
     if let Err(e) = drainage_requester.send_timeout(req, timeout).await {
         eprintln!("Logging drainage error: {e}");
-        return (StatusCode::INTERNAL_SERVER_ERROR, "could not drain").into_response();
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "could not drain").into_response());
     };
 
     select! {
-     res = rx => {
-        match res {
-            Ok(v) => Json(Drainage(v)).into_response(),
-            Err(e) => {
-                 eprintln!("Logging drainage error: {e}");
-                 (StatusCode::INTERNAL_SERVER_ERROR, "could not drain").into_response()
-        }
-    }
+     res = &mut rx => {
+        res.map_err(|e| {
+            eprintln!("Logging drainage error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "could not drain").into_response()
+        })
      }
      _= interval.tick() => {
-         StatusCode::REQUEST_TIMEOUT.into_response()
+         // Closing the receiver makes the queue re-queue the drained elements. Elements that have been sent
+         // before the receiver was closed are still delivered.
+         rx.close();
+         rx.try_recv().map_err(|_| StatusCode::REQUEST_TIMEOUT.into_response())
      }
     }
 }
 
+/// Metadata of a drained [`Transaction`] without its payload.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct TransactionHeader {
+    pub id: String,
+    pub gas_price: u64,
+    pub timestamp: u64,
+    pub hash: String,
+    pub payload_size: usize,
+}
+
+/// Payload of a drained transaction along with the instant it expires.
+type ExpiringPayload = (Instant, Vec<u8>);
+
+/// Payloads of transactions that have been drained via `/drain_headers`, keyed by transaction id.
+/// Payloads expire after the configured time to live.
+#[derive(Clone)]
+pub struct PayloadStore {
+    payloads: Arc<Mutex<Payloads>>,
+    ttl: Duration,
+}
+
+#[derive(Default)]
+struct Payloads {
+    by_id: HashMap<String, ExpiringPayload>,
+    /// Expiry and id of every insert in insertion order, which is also the order of expiry as all payloads
+    /// share the same time to live.
+    expiries: VecDeque<(Instant, String)>,
+}
+
+impl PayloadStore {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            payloads: Arc::new(Mutex::new(Payloads::default())),
+            ttl,
+        }
+    }
+
+    /// Stores the `payload` of the transaction `id` and drops the payloads that have expired since the last
+    /// insert, which takes amortized constant time.
+    fn insert(&self, id: String, payload: Vec<u8>) {
+        let now = Instant::now();
+        let mut payloads = self.payloads.lock().unwrap();
+        let Payloads { by_id, expiries } = &mut *payloads;
+        while expiries.front().is_some_and(|(expiry, _)| *expiry <= now) {
+            let (expiry, expired_id) = expiries.pop_front().expect("front has just been checked");
+            // -- the id may have been re-inserted with a later expiry in the meantime
+            if by_id
+                .get(&expired_id)
+                .is_some_and(|(current, _)| *current == expiry)
+            {
+                by_id.remove(&expired_id);
+            }
+        }
+        let expiry = now + self.ttl;
+        expiries.push_back((expiry, id.clone()));
+        by_id.insert(id, (expiry, payload));
+    }
+
+    fn get(&self, id: &str) -> Option<Vec<u8>> {
+        let payloads = self.payloads.lock().unwrap();
+        payloads
+            .by_id
+            .get(id)
+            .filter(|(expiry, _)| *expiry > Instant::now())
+            .map(|(_, payload)| payload.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct HeaderDrainState {
+    drain_request_source: Sender<DrainRequest>,
+    payloads: PayloadStore,
+    max_drain_n: usize,
+}
+
+/// Drains like `/drain`, but returns only the [`TransactionHeader`]s of the drained transactions.
+/// Their payloads are kept on the server and can be fetched via `/payload/{id}` until they expire.
+async fn drain_headers(
+    State(HeaderDrainState {
+        drain_request_source,
+        payloads,
+        max_drain_n,
+    }): State<HeaderDrainState>,
+    Path((n, timeout_us)): Path<(usize, u64)>,
+) -> impl IntoResponse {
+    if let Err(rejection) = check_drain_n(n, max_drain_n) {
+        return rejection.into_response();
+    }
+    let drained = match request_drainage(&drain_request_source, n, timeout_us).await {
+        Ok(v) => v,
+        Err(response) => return response,
+    };
+
+    let headers: Vec<TransactionHeader> = drained
+        .into_iter()
+        .map(|tx| {
+            let header = TransactionHeader {
+                hash: hex::encode(tx.hash),
+                payload_size: tx.payload.len(),
+                gas_price: tx.gas_price,
+                timestamp: tx.timestamp,
+                id: tx.id,
+            };
+            payloads.insert(header.id.clone(), tx.payload);
+            header
+        })
+        .collect();
+    Json(headers).into_response()
+}
+
+/// Returns the raw payload of a transaction that has been drained via `/drain_headers`.
+async fn fetch_payload(
+    State(payloads): State<PayloadStore>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match payloads.get(&id) {
+        Some(payload) => payload.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Clone)]
+pub struct RemoveRequestSource(Sender<RemoveRequest>);
+
+/// Removes the pending transaction `id` from the queue, e.g. to cancel a known-bad transaction.
+/// Responds with the removed transaction or with 404 if the queue does not hold a transaction with this id.
+async fn remove_transaction(
+    State(RemoveRequestSource(remover)): State<RemoveRequestSource>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let (reply, rx) = tokio::sync::oneshot::channel();
+    if let Err(e) = remover.send((id, reply)).await {
+        eprintln!("Logging removal error: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "could not remove").into_response();
+    }
+    match rx.await {
+        Ok(Some(tx)) => Json(tx).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            eprintln!("Logging removal error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "could not remove").into_response()
+        }
+    }
+}
+
 fn build_router(
+    cfg: &ServerCfg,
     submittance_source: Sender<Transaction>,
     drain_request_source: Sender<DrainRequest>,
+    remove_request_source: Sender<RemoveRequest>,
 ) -> axum::Router {
-    let submittance_source = SubmittanceSource(submittance_source);
-    let drain_request_source = DrainRequestSource(drain_request_source);
+    let submittance_source = SubmittanceSource::new(submittance_source, cfg.clone());
+    let payloads = PayloadStore::new(cfg.payload_ttl);
+    let header_drain_state = HeaderDrainState {
+        drain_request_source: drain_request_source.clone(),
+        payloads: payloads.clone(),
+        max_drain_n: cfg.max_drain_n,
+    };
+    let drain_request_source = DrainRequestSource {
+        drainage_requester: drain_request_source,
+        max_drain_n: cfg.max_drain_n,
+    };
 
     axum::Router::new()
-        .route("/submit/{timeout_us}", post(submit_transaction))
+        .route(
+            "/submit/{timeout_us}",
+            post(submit_transaction).layer(DefaultBodyLimit::max(cfg.max_body_bytes)),
+        )
         .with_state(submittance_source)
         .route("/drain/{n}/{timeout_us}", get(drain_transactions))
         .with_state(drain_request_source)
+        .route("/drain_headers/{n}/{timeout_us}", get(drain_headers))
+        .with_state(header_drain_state)
+        .route("/payload/{id}", get(fetch_payload))
+        .with_state(payloads)
+        .route("/transaction/{id}", delete(remove_transaction))
+        .with_state(RemoveRequestSource(remove_request_source))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{sync::mpsc, time};
+
+    use super::*;
+
+    fn server_cfg(trust_client_timestamp: bool) -> ServerCfg {
+        ServerCfg {
+            port: 8080,
+            trust_client_timestamp,
+            default_submit_timeout_us: 50_000,
+            max_submit_timeout_us: 1_000_000,
+            payload_ttl: Duration::from_secs(60),
+            max_body_bytes: 1024,
+            max_drain_n: 1_000,
+            submit_overflow: SubmitOverflowPolicy::Reject503,
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_overwrites_untrusted_timestamp() {
+        let queue = async_impl::worker::Queue::start(async_impl::worker::Cfg {
+            capacity: 10,
+            submittance_back_pressure: 10,
+            max_drain_batch: 100,
+            drain_request_buffer: 10,
+            drain_retry_delay: async_impl::worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: async_impl::worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
+        });
+        let (channels, runner_handle) = queue.detach_channels();
+        let (submitter, drain_request_source) = channels.into_parts();
+        // -- the server keeps its state alive, the worker stops once all submitters are dropped
+        let source = SubmittanceSource::new(submitter.clone(), server_cfg(false));
+        let before = unix_micros();
+
+        let response = submit_transaction(
+            State(source),
+            Path(1_000),
+            Json(Transaction::with_empty_load("bogus", 10, 42)),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (req, rx) = DrainRequest::new_with_timeout(1, 100_000);
+        drain_request_source.send(req).await.unwrap();
+        let drained = rx.await.unwrap();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].id, "bogus");
+        assert!(drained[0].timestamp >= before);
+        assert!(drained[0].timestamp <= unix_micros());
+        assert_eq!(drained[0].hash, drained[0].compute_hash());
+
+        runner_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn submit_keeps_trusted_timestamp() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let source = SubmittanceSource::new(tx, server_cfg(true));
+
+        let response = submit_transaction(
+            State(source),
+            Path(1_000),
+            Json(Transaction::with_empty_load("trusted", 10, 42)),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        assert_eq!(rx.recv().await.unwrap().timestamp, 42);
+    }
+
+    #[test]
+    fn submit_timeout_is_capped() {
+        let cfg = server_cfg(true);
+        assert_eq!(
+            cfg.submit_timeout(u64::MAX),
+            Duration::from_micros(1_000_000)
+        );
+        assert_eq!(cfg.submit_timeout(20), Duration::from_micros(20));
+    }
+
+    #[test]
+    fn submit_timeout_zero_uses_default() {
+        let cfg = server_cfg(true);
+        assert_eq!(cfg.submit_timeout(0), Duration::from_micros(50_000));
+    }
+
+    #[tokio::test]
+    async fn timed_out_drains_lose_no_transactions() {
+        let queue = async_impl::worker::Queue::start(async_impl::worker::Cfg {
+            capacity: 10,
+            submittance_back_pressure: 10,
+            max_drain_batch: 100,
+            drain_request_buffer: 10,
+            drain_retry_delay: async_impl::worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: async_impl::worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
+        });
+        let (channels, runner_handle) = queue.detach_channels();
+        let (submitter, drain_request_source) = channels.into_parts();
+        for i in 0..5 {
+            submitter
+                .send(Transaction::with_empty_load(&format!("tx{i}"), i, i))
+                .await
+                .unwrap();
+        }
+
+        // -- requesting more elements than available makes the queue wait until the request times out,
+        // transactions of requests that beat the timeout are put back for the next round
+        for _ in 0..20 {
+            let response = drain_transactions(
+                State(DrainRequestSource {
+                    drainage_requester: drain_request_source.clone(),
+                    max_drain_n: 1_000,
+                }),
+                Path((10, 1_000)),
+            )
+            .await
+            .into_response();
+            if response.status() == StatusCode::OK {
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                for tx in serde_json::from_slice::<Vec<Transaction>>(&body).unwrap() {
+                    submitter.send(tx).await.unwrap();
+                }
+            } else {
+                assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+            }
+        }
+
+        // -- let the queue settle pending resubmits and timed out requests
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let (req, rx) = DrainRequest::new_with_timeout(10, 0);
+        drain_request_source.send(req).await.unwrap();
+        assert_eq!(rx.await.unwrap().len(), 5);
+
+        runner_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn drain_headers_omits_payloads() {
+        let queue = async_impl::worker::Queue::start(async_impl::worker::Cfg {
+            capacity: 10,
+            submittance_back_pressure: 10,
+            max_drain_batch: 100,
+            drain_request_buffer: 10,
+            drain_retry_delay: async_impl::worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: async_impl::worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
+        });
+        let (channels, runner_handle) = queue.detach_channels();
+        let (submitter, drain_request_source) = channels.into_parts();
+        let tx = Transaction::new("tx", 10, 1, vec![7; 300]);
+        submitter.send(tx.clone()).await.unwrap();
+
+        let state = HeaderDrainState {
+            drain_request_source,
+            payloads: PayloadStore::new(Duration::from_secs(60)),
+            max_drain_n: 1_000,
+        };
+        let response = drain_headers(State(state.clone()), Path((1, 100_000)))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.len() < 300);
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json[0].get("payload").is_none());
+        let headers: Vec<TransactionHeader> = serde_json::from_value(json).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].id, "tx");
+        assert_eq!(headers[0].payload_size, 300);
+        assert_eq!(headers[0].hash, hex::encode(tx.hash));
+
+        let response = fetch_payload(State(state.payloads.clone()), Path("tx".to_string()))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body.as_ref(), tx.payload.as_slice());
+
+        let response = fetch_payload(State(state.payloads), Path("unknown".to_string()))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        runner_handle.abort();
+    }
+
+    #[test]
+    fn payloads_expire() {
+        let payloads = PayloadStore::new(Duration::ZERO);
+        payloads.insert("tx".to_string(), vec![1, 2, 3]);
+        assert_eq!(payloads.get("tx"), None);
+
+        // -- expired payloads are dropped by the next insert
+        payloads.insert("other".to_string(), vec![4]);
+        let stored = payloads.payloads.lock().unwrap();
+        assert_eq!(stored.by_id.len(), 1);
+        assert_eq!(stored.expiries.len(), 1);
+    }
+
+    #[test]
+    fn reinserted_payload_outlives_its_first_expiry() {
+        let payloads = PayloadStore::new(Duration::from_millis(20));
+        payloads.insert("tx".to_string(), vec![1]);
+        std::thread::sleep(Duration::from_millis(15));
+        payloads.insert("tx".to_string(), vec![2]);
+        std::thread::sleep(Duration::from_millis(10));
+
+        // -- the first expiry has passed, but the re-insert keeps the payload alive
+        payloads.insert("other".to_string(), vec![3]);
+        assert_eq!(payloads.get("tx"), Some(vec![2]));
+    }
+
+    #[tokio::test]
+    async fn delete_removes_pending_transaction() {
+        let queue = async_impl::worker::Queue::start(async_impl::worker::Cfg {
+            capacity: 10,
+            submittance_back_pressure: 10,
+            max_drain_batch: 100,
+            drain_request_buffer: 10,
+            drain_retry_delay: async_impl::worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: async_impl::worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
+        });
+        let (channels, runner_handle) = queue.detach_channels();
+        let remove_request_source = channels.remove_request_source();
+        let (submitter, drain_request_source) = channels.into_parts();
+        let bad = Transaction::with_empty_load("bad", 100, 1);
+        submitter.send(bad.clone()).await.unwrap();
+        submitter
+            .send(Transaction::with_empty_load("good", 10, 2))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        let response = remove_transaction(
+            State(RemoveRequestSource(remove_request_source.clone())),
+            Path("bad".to_string()),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(serde_json::from_slice::<Transaction>(&body).unwrap(), bad);
+
+        let response = remove_transaction(
+            State(RemoveRequestSource(remove_request_source)),
+            Path("bad".to_string()),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let drained = request_drainage(&drain_request_source, 10, 1_000)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = drained.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, ["good"]);
+
+        runner_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn zero_timeout_drain_returns_available_transactions() {
+        let queue = async_impl::worker::Queue::start(async_impl::worker::Cfg {
+            capacity: 10,
+            submittance_back_pressure: 10,
+            max_drain_batch: 100,
+            drain_request_buffer: 10,
+            drain_retry_delay: async_impl::worker::Cfg::DEFAULT_DRAIN_RETRY_DELAY,
+            max_drain_retry_delay: async_impl::worker::Cfg::DEFAULT_MAX_DRAIN_RETRY_DELAY,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
+        });
+        let (channels, runner_handle) = queue.detach_channels();
+        let (submitter, drain_request_source) = channels.into_parts();
+        submitter
+            .send(Transaction::with_empty_load("tx", 10, 1))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        let drained = request_drainage(&drain_request_source, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(drained.len(), 1);
+
+        runner_handle.abort();
+    }
+
+    #[tokio::test]
+    async fn oversized_submit_body_is_rejected() {
+        use tower::ServiceExt;
+
+        let (submitter, mut submitted) = mpsc::channel(1);
+        let (drain_request_source, _) = mpsc::channel(1);
+        let (remove_request_source, _) = mpsc::channel(1);
+        let cfg = server_cfg(true);
+        let router = build_router(&cfg, submitter, drain_request_source, remove_request_source);
+
+        let tx = Transaction::new("big", 10, 1, vec![0; cfg.max_body_bytes]);
+        let request = axum::http::Request::post("/submit/1000")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(serde_json::to_vec(&tx).unwrap()))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(submitted.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn enormous_drain_n_is_rejected() {
+        use tower::ServiceExt;
+
+        let (submitter, _) = mpsc::channel(1);
+        let (drain_request_source, mut drain_requests) = mpsc::channel(1);
+        let (remove_request_source, _) = mpsc::channel(1);
+        let router = build_router(
+            &server_cfg(true),
+            submitter,
+            drain_request_source,
+            remove_request_source,
+        );
+
+        for route in ["/drain/99999999999999/0", "/drain_headers/99999999999999/0"] {
+            let request = axum::http::Request::get(route)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(drain_requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn saturated_queue_rejects_or_buffers_submits() {
+        use tower::ServiceExt;
+
+        async fn submit_to_saturated_queue(submit_overflow: SubmitOverflowPolicy) -> StatusCode {
+            let (submitter, mut submitted) = mpsc::channel(1);
+            submitter
+                .send(Transaction::with_empty_load("queued", 10, 1))
+                .await
+                .unwrap();
+            let (drain_request_source, _) = mpsc::channel(1);
+            let (remove_request_source, _) = mpsc::channel(1);
+            let cfg = ServerCfg {
+                submit_overflow,
+                ..server_cfg(true)
+            };
+            let router = build_router(&cfg, submitter, drain_request_source, remove_request_source);
+
+            let tx = Transaction::with_empty_load("overflow", 10, 2);
+            let request = axum::http::Request::post("/submit/1000")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(serde_json::to_vec(&tx).unwrap()))
+                .unwrap();
+            let status = router.oneshot(request).await.unwrap().status();
+
+            assert_eq!(submitted.recv().await.unwrap().id, "queued");
+            if status == StatusCode::ACCEPTED {
+                // -- the buffered transaction is fed into the queue once there is room
+                let buffered = time::timeout(Duration::from_secs(1), submitted.recv()).await;
+                assert_eq!(buffered.unwrap().unwrap().id, "overflow");
+            }
+            status
+        }
+
+        assert_eq!(
+            submit_to_saturated_queue(SubmitOverflowPolicy::Reject503).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            submit_to_saturated_queue(SubmitOverflowPolicy::Accept202WithBuffer { max_buffer: 8 })
+                .await,
+            StatusCode::ACCEPTED
+        );
+    }
 }
@@ -1,20 +1,18 @@
 use async_impl::HttpFacade;
 use cfg::Cfg;
-use clap::Parser;
-use naive::NaivePool;
-use sync::{ChanneledQueue, LockedQueue};
 
 mod cfg;
+mod factory;
 mod http;
 
 fn main() {
-    let cfg = cfg::Cfg::parse();
+    let cfg = Cfg::try_parse_validated_from(std::env::args_os()).unwrap_or_else(|e| e.exit());
     println!("Running configuration:\n{cfg:#?}");
 
     let res = match cfg.implementation {
-        cfg::Implementation::Naive => run_naive(cfg),
-        cfg::Implementation::SyncChannels => run_sync_channels(cfg),
-        cfg::Implementation::SyncLocks => run_sync_lock_based(cfg),
+        cfg::Implementation::Naive
+        | cfg::Implementation::SyncChannels
+        | cfg::Implementation::SyncLocks => run_sync(cfg),
         cfg::Implementation::Async => run_async(cfg),
         cfg::Implementation::AsyncLocks => run_async_locks(cfg),
     };
@@ -23,8 +21,8 @@ fn main() {
     }
 }
 
-fn run_naive(cfg: Cfg) -> anyhow::Result<()> {
-    use mempool::test::stress::{StressTestConfig, run_stress_test};
+fn run_sync(cfg: Cfg) -> anyhow::Result<()> {
+    use mempool::test::stress::run_stress_test;
     use std::sync::Arc;
 
     let capacity = cfg
@@ -32,130 +30,154 @@ fn run_naive(cfg: Cfg) -> anyhow::Result<()> {
         .checked_mul(cfg.producer_num)
         .ok_or_else(|| anyhow::anyhow!("Overflow while calculating mempool capacity"))?;
 
-    let mempool = Arc::new(NaivePool::new(capacity));
-    let config = StressTestConfig {
-        num_producers: cfg.producer_num,
-        num_transactions: cfg.transaction_num,
-        num_consumers: 1,
-        payload_size_range: (256, 1_024),
-        drain_interval_ms: cfg.drain_interval_us / 1_000,
-        drain_batch_size: cfg.drain_batch_size,
-        gas_price_range: (142, 654),
-        run_duration_seconds: cfg.run_duration_seconds,
-    };
-    let results = run_stress_test(mempool, config);
+    let mempool = Arc::new(factory::build(cfg.implementation.clone(), capacity)?);
+    let config = sync_stress_cfg(&cfg);
+    let results = run_stress_test(mempool, config)?;
     results.print_summary();
-
     Ok(())
 }
 
-fn run_sync_channels(cfg: Cfg) -> anyhow::Result<()> {
-    use mempool::test::stress::{StressTestConfig, run_stress_test};
-    use std::sync::Arc;
+/// Stress test config of the naive and sync implementations.
+fn sync_stress_cfg(cfg: &Cfg) -> mempool::test::stress::StressTestConfig {
+    use mempool::test::stress::{ConsumerPhase, StressTestConfig};
 
-    let capacity = cfg
-        .transaction_num
-        .checked_mul(cfg.producer_num)
-        .ok_or_else(|| anyhow::anyhow!("Overflow while calculating mempool capacity"))?;
-
-    let mempool = Arc::new(ChanneledQueue::new(capacity));
-    let config = StressTestConfig {
+    StressTestConfig {
         num_producers: cfg.producer_num,
         num_transactions: cfg.transaction_num,
-        num_consumers: cfg.consumer_num,
-        payload_size_range: (256, 1_024),
+        // The naive pool is drained by a single consumer only.
+        num_consumers: match cfg.implementation {
+            cfg::Implementation::Naive => 1,
+            _ => cfg.consumer_num,
+        },
+        payload_size_range: cfg.payload_size_range(),
         drain_interval_ms: cfg.drain_interval_us / 1_000,
         drain_batch_size: cfg.drain_batch_size,
-        gas_price_range: (142, 654),
+        gas_price_range: cfg.gas_price_range(),
         run_duration_seconds: cfg.run_duration_seconds,
-    };
-    let results = run_stress_test(mempool, config);
-    results.print_summary();
-    Ok(())
+        consumer_phase: ConsumerPhase::default(),
+        stop_when_drained: cfg.stop_when_drained,
+        seed: cfg.seed,
+        max_producers: cfg.max_producers,
+    }
 }
 
-fn run_sync_lock_based(cfg: Cfg) -> anyhow::Result<()> {
-    use mempool::test::stress::{StressTestConfig, run_stress_test};
-    use std::sync::Arc;
-
-    let capacity = cfg
-        .transaction_num
-        .checked_mul(cfg.producer_num)
-        .ok_or_else(|| anyhow::anyhow!("Overflow while calculating mempool capacity"))?;
+/// Stress test config of the async implementations.
+fn async_stress_cfg(cfg: &Cfg) -> async_impl::StressTestCfg {
+    use async_impl::StressTestCfg;
 
-    let mempool = Arc::new(LockedQueue::new(capacity));
-    let config = StressTestConfig {
+    StressTestCfg {
         num_producers: cfg.producer_num,
         num_transactions: cfg.transaction_num,
         num_consumers: cfg.consumer_num,
-        payload_size_range: (256, 1_024),
-        drain_interval_ms: cfg.drain_interval_us / 1_000,
+        payload_size_range: cfg.payload_size_range(),
+        drain_interval_us: cfg.drain_interval_us,
         drain_batch_size: cfg.drain_batch_size,
-        gas_price_range: (142, 654),
+        drain_timeout_us: 50_000,
+        gas_price_range: cfg.gas_price_range(),
         run_duration_seconds: cfg.run_duration_seconds,
-    };
-    let results = run_stress_test(mempool, config);
-    results.print_summary();
-    Ok(())
+        submission_rate: None, // Max speed
+        latency_tracking: true,
+        print_stats_interval_ms: 1000,
+        latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
+        histogram_sigfigs: cfg.histogram_sigfigs,
+        http_port: cfg.http_port,
+        reuse_payloads: cfg.reuse_payloads,
+        stop_when_drained: cfg.stop_when_drained,
+        min_drain_batch: cfg.min_drain_batch,
+        max_producers: cfg.max_producers,
+        seed: cfg.seed,
+    }
 }
 
 fn run_async(cfg: Cfg) -> anyhow::Result<()> {
-    use async_impl::{StressTestCfg, run_stress_test};
+    use async_impl::run_stress_test;
 
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
+    let server_cfg = cfg.http_port.map(|port| http::ServerCfg {
+        port,
+        trust_client_timestamp: cfg.trust_client_timestamp,
+        default_submit_timeout_us: cfg.default_submit_timeout_us,
+        max_submit_timeout_us: cfg.max_submit_timeout_us,
+        payload_ttl: std::time::Duration::from_millis(cfg.payload_ttl_ms),
+        max_body_bytes: cfg.max_body_bytes,
+        max_drain_n: cfg.max_drain_n,
+        submit_overflow: match cfg.submit_overflow_buffer {
+            Some(max_buffer) => http::SubmitOverflowPolicy::Accept202WithBuffer { max_buffer },
+            None => http::SubmitOverflowPolicy::Reject503,
+        },
+    });
+    let max_drain_batch = cfg.max_drain_batch;
+    let drain_request_buffer = cfg.drain_request_buffer;
+    let drain_retry_delay = std::time::Duration::from_micros(cfg.drain_retry_delay_us);
+    let max_drain_retry_delay = std::time::Duration::from_micros(cfg.max_drain_retry_delay_us);
+    let auto_tune = auto_tune_cfg(&cfg);
     rt.block_on(async {
-        let cfg = StressTestCfg {
-            num_producers: cfg.producer_num,
-            num_transactions: cfg.transaction_num,
-            num_consumers: cfg.consumer_num,
-            payload_size_range: (100, 1000),
-            drain_interval_us: cfg.drain_interval_us,
-            drain_batch_size: cfg.drain_batch_size,
-            drain_timeout_us: 50_000,
-            gas_price_range: (1, 1000),
-            run_duration_seconds: cfg.run_duration_seconds,
-            submission_rate: None, // Max speed
-            latency_tracking: true,
-            print_stats_interval_ms: 1000,
-            latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
-            http_port: cfg.http_port,
-        };
+        let cfg = async_stress_cfg(&cfg);
         let queue_cfg = async_impl::worker::Cfg {
             capacity: cfg.num_producers * cfg.num_transactions,
             submittance_back_pressure: 3_000,
+            max_drain_batch,
+            drain_request_buffer,
+            drain_retry_delay,
+            max_drain_retry_delay,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
         };
 
-        if cfg.http_port.is_some() {
-            let http_based_tester = prepare_http_server(queue_cfg.clone(), &cfg).await;
-            run_stress_test(cfg, http_based_tester.clone()).await;
+        if let Some(server_cfg) = server_cfg {
+            let http_based_tester = prepare_http_server(queue_cfg.clone(), server_cfg).await;
+            let result = run_stress_test(cfg, http_based_tester.clone()).await;
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             http_based_tester.stop();
+            result?;
+        } else if let Some(tune) = auto_tune {
+            let queue_cfg = &queue_cfg;
+            let mut queues = Vec::new();
+            let report = async_impl::auto_tune(cfg, &tune, || {
+                let queue = async_impl::worker::Queue::start(queue_cfg.clone());
+                queues.push(queue.clone());
+                queue
+            })
+            .await;
+            queues.into_iter().for_each(async_impl::worker::Queue::stop);
+            report?.print_summary();
         } else {
             let queue = async_impl::worker::Queue::start(queue_cfg);
-            run_stress_test(cfg, queue.clone()).await;
+            let result = run_stress_test(cfg, queue.clone()).await;
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            queue.stop()
+            queue.stop();
+            result?;
         }
-    });
-    Ok(())
+        Ok(())
+    })
+}
+
+/// Settings of the `--auto-tune` mode, `None` if it is disabled.
+fn auto_tune_cfg(cfg: &Cfg) -> Option<async_impl::AutoTuneCfg> {
+    cfg.auto_tune.then_some(async_impl::AutoTuneCfg {
+        max_consumers: cfg.auto_tune_max_consumers,
+        target_p99_latency_us: cfg.auto_tune_target_p99_us,
+    })
 }
 
 async fn prepare_http_server(
     queue_cfg: async_impl::worker::Cfg,
-    cfg: &async_impl::StressTestCfg,
+    server_cfg: http::ServerCfg,
 ) -> HttpFacade {
     use std::sync::Arc;
 
     let queue = async_impl::worker::Queue::start(queue_cfg);
     let (channels, runner_handle) = queue.detach_channels();
+    let remove_request_source = channels.remove_request_source();
     let (submittance_source, drain_request_source) = channels.into_parts();
 
     let server_handle = http::start_server(
-        cfg.http_port.unwrap_or(8080),
+        server_cfg,
         submittance_source,
         drain_request_source,
+        remove_request_source,
     )
     .await
     .expect("can start server");
@@ -164,40 +186,143 @@ async fn prepare_http_server(
 }
 
 fn run_async_locks(cfg: Cfg) -> anyhow::Result<()> {
-    use async_impl::{StressTestCfg, run_stress_test};
+    use async_impl::run_stress_test;
 
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
+    let max_drain_batch = cfg.max_drain_batch;
+    let drain_request_buffer = cfg.drain_request_buffer;
+    let drain_retry_delay = std::time::Duration::from_micros(cfg.drain_retry_delay_us);
+    let max_drain_retry_delay = std::time::Duration::from_micros(cfg.max_drain_retry_delay_us);
+    let auto_tune = auto_tune_cfg(&cfg);
     rt.block_on(async {
-        let cfg = StressTestCfg {
-            num_producers: cfg.producer_num,
-            num_transactions: cfg.transaction_num,
-            num_consumers: cfg.consumer_num,
-            payload_size_range: (100, 1000),
-            drain_interval_us: cfg.drain_interval_us,
-            drain_batch_size: cfg.drain_batch_size,
-            drain_timeout_us: 50_000,
-            gas_price_range: (1, 1000),
-            run_duration_seconds: cfg.run_duration_seconds,
-            submission_rate: None, // Max speed
-            latency_tracking: true,
-            print_stats_interval_ms: 1000,
-            latency_percentiles: vec![50.0, 90.0, 99.0, 99.9],
-            http_port: cfg.http_port,
-        };
+        let cfg = async_stress_cfg(&cfg);
         let _queue_cfg = async_impl::worker::Cfg {
             capacity: cfg.num_producers * cfg.num_transactions,
             submittance_back_pressure: 3_000,
+            max_drain_batch,
+            drain_request_buffer,
+            drain_retry_delay,
+            max_drain_retry_delay,
+            idle_shutdown: None,
+            backpressure: async_impl::worker::BackpressureStrategy::Block,
         };
 
         if cfg.http_port.is_some() {
             todo!("implement http based testing of the locked queue...");
+        } else if let Some(tune) = auto_tune {
+            let capacity = cfg.num_producers * cfg.num_transactions;
+            let report =
+                async_impl::auto_tune(cfg, &tune, || async_impl::LockedQueue::new(capacity))
+                    .await?;
+            report.print_summary();
         } else {
             let queue = async_impl::LockedQueue::new(cfg.num_producers * cfg.num_transactions);
-            run_stress_test(cfg, queue.clone()).await;
+            run_stress_test(cfg, queue.clone()).await?;
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
         }
-    });
-    Ok(())
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn payload_and_gas_flags_reach_every_implementation() {
+        for implementation in [
+            "naive",
+            "sync-channels",
+            "sync-locks",
+            "async",
+            "async-locks",
+        ] {
+            let cfg = Cfg::parse_from([
+                "stress_tester",
+                implementation,
+                "-p=1",
+                "-t=1",
+                "--payload-min=7",
+                "--payload-max=77",
+                "--gas-min=3",
+                "--gas-max=33",
+            ]);
+
+            let sync_cfg = sync_stress_cfg(&cfg);
+            assert_eq!(sync_cfg.payload_size_range, (7, 77));
+            assert_eq!(sync_cfg.gas_price_range, (3, 33));
+
+            let async_cfg = async_stress_cfg(&cfg);
+            assert_eq!(async_cfg.payload_size_range, (7, 77));
+            assert_eq!(async_cfg.gas_price_range, (3, 33));
+        }
+    }
+
+    #[test]
+    fn stop_when_drained_reaches_every_implementation() {
+        let args = ["stress_tester", "sync-locks", "-p=1", "-t=1"];
+        let cfg = Cfg::parse_from(args.into_iter().chain(["--stop-when-drained"]));
+        assert!(sync_stress_cfg(&cfg).stop_when_drained);
+        assert!(async_stress_cfg(&cfg).stop_when_drained);
+
+        let cfg = Cfg::parse_from(args);
+        assert!(!sync_stress_cfg(&cfg).stop_when_drained);
+        assert!(!async_stress_cfg(&cfg).stop_when_drained);
+    }
+
+    #[test]
+    fn empty_drain_request_buffer_is_rejected() {
+        let parsed = Cfg::try_parse_from(["stress_tester", "async", "--drain-request-buffer=0"]);
+        assert!(parsed.is_err());
+    }
+
+    #[test]
+    fn inverted_ranges_are_rejected_and_equal_bounds_are_accepted() {
+        for range in [
+            ["--payload-min=8", "--payload-max=7"],
+            ["--gas-min=8", "--gas-max=7"],
+        ] {
+            let parsed = Cfg::try_parse_validated_from(
+                ["stress_tester", "sync-locks", "-p=1", "-t=1"]
+                    .into_iter()
+                    .chain(range),
+            );
+            assert!(parsed.is_err());
+        }
+
+        // -- A single payload size and gas price is a valid range for every harness.
+        let cfg = Cfg::try_parse_validated_from([
+            "stress_tester",
+            "sync-locks",
+            "-p=1",
+            "-t=50",
+            "--payload-min=7",
+            "--payload-max=7",
+            "--gas-min=3",
+            "--gas-max=3",
+            "--run-duration-seconds=1",
+        ])
+        .unwrap();
+        run_sync(cfg).unwrap();
+    }
+
+    #[test]
+    fn too_many_producers_are_rejected() {
+        let cfg = Cfg::parse_from([
+            "stress_tester",
+            "sync-locks",
+            "-p=3",
+            "-t=1",
+            "--max-producers=2",
+        ]);
+
+        let err = run_sync(cfg).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("3 producers exceed the maximum of 2")
+        );
+    }
 }